@@ -0,0 +1,193 @@
+//! Throughput of byte rendering.
+//!
+//! `per_byte` is the previous implementation, which writes every rendered byte with its own `write_all` call.
+//! It is kept here as a reference point for the table-driven [`ByteFormatter`].
+//!
+//! `dyn` is the same [`ByteFormatter`], printed by [`DynPrinter`] with type-erased formatters and output
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use kex::*;
+use std::io::{Result, Write};
+
+const DATA_LEN: usize = 1 << 20;
+const BYTES_PER_ROW: usize = 16;
+
+/// Name, whether style is binary, style and group size
+const CASES: [(&str, bool, ByteStyle, usize); 3] = [
+    ("hex/4", false, ByteStyle::Hex, 4),
+    ("hex/16", false, ByteStyle::Hex, 16),
+    ("bin/4", true, ByteStyle::Bin, 4),
+];
+
+/// Previous rendering of groups, separated by spaces
+#[derive(Clone)]
+struct PerByteFormatter {
+    is_binary: bool,
+    group_size: usize,
+    separators: Separators,
+}
+
+impl PerByteFormatter {
+    fn new(is_binary: bool, group_size: usize) -> Self {
+        Self {
+            is_binary,
+            group_size,
+            separators: Separators::new(" ", " "),
+        }
+    }
+
+    fn fmt_hex<O: Write>(byte: u8, out: &mut O) -> Result<()> {
+        const LOWER_HEX: &[u8; 16] = b"0123456789abcdef";
+
+        let buf = [LOWER_HEX[(byte >> 4) as usize], LOWER_HEX[(byte & 0x0f) as usize]];
+        out.write_all(&buf)
+    }
+
+    fn fmt_bin<O: Write>(byte: u8, out: &mut O) -> Result<()> {
+        let s = format!("{:08b}", byte);
+        out.write_all(s.as_bytes())
+    }
+}
+
+impl ByteFormatting for PerByteFormatter {
+    fn byte_order(&self) -> GroupAtomicity {
+        GroupAtomicity::Optional
+    }
+
+    fn groupping(&self) -> Groupping {
+        Groupping::RepeatingGroup(Group::new(self.group_size, " "), BYTES_PER_ROW / self.group_size)
+    }
+
+    fn format<O: Write>(&self, bytes: &[u8], byte_number_in_row: usize, out: &mut O) -> Result<usize> {
+        for (idx, byte) in bytes.iter().enumerate() {
+            let number = byte_number_in_row + idx;
+            if number != 0 && number.is_multiple_of(self.group_size) {
+                out.write_all(b" ")?;
+            }
+
+            match self.is_binary {
+                true => Self::fmt_bin(*byte, out)?,
+                false => Self::fmt_hex(*byte, out)?,
+            }
+        }
+
+        Ok(bytes.len())
+    }
+
+    fn format_padding<O: Write>(&self, byte_number_in_row: usize, out: &mut O) -> Result<()> {
+        for number in byte_number_in_row..BYTES_PER_ROW {
+            if number != 0 && number.is_multiple_of(self.group_size) {
+                out.write_all(b" ")?;
+            }
+            out.write_all(b"..")?;
+        }
+
+        Ok(())
+    }
+
+    fn separators(&self) -> &Separators {
+        &self.separators
+    }
+}
+
+fn table_formatter(style: ByteStyle, group_size: usize, bytes_per_row: usize) -> ByteFormatter {
+    ByteFormatter::new(
+        style,
+        Groupping::RepeatingGroup(Group::new(group_size, " "), bytes_per_row / group_size),
+        "",
+        false,
+        Separators::new(" ", " "),
+    )
+}
+
+fn sample() -> Vec<u8> {
+    let mut seed = 0x9e3779b9u32;
+    (0..DATA_LEN)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as u8
+        })
+        .collect()
+}
+
+fn dump<B: ByteFormatting + Clone>(fmt: B, data: &[u8], out: &mut Vec<u8>) {
+    out.clear();
+
+    let config = Config::new(Some(AddressFormatter::default()), fmt, Some(CharFormatter::default()), false);
+    let mut printer = Printer::new(out, 0, config);
+    _ = printer.push(data);
+    printer.finish();
+}
+
+fn dyn_dump(fmt: ByteFormatter, data: &[u8], out: &mut Vec<u8>) {
+    out.clear();
+
+    let config = DynConfig::new(
+        Some(DynAddressFormatter::new(AddressFormatter::default())),
+        DynByteFormatter::new(fmt),
+        Some(DynCharFormatter::new(CharFormatter::default())),
+        false,
+    );
+    let mut printer: DynPrinter = Printer::new(Box::new(out), 0, config);
+    _ = printer.push(data);
+    printer.finish();
+}
+
+fn byte_column(c: &mut Criterion) {
+    let data = sample();
+    let mut out = Vec::with_capacity(16 * DATA_LEN);
+
+    let mut group = c.benchmark_group("byte_column");
+    group.throughput(Throughput::Bytes(DATA_LEN as u64));
+
+    for (name, is_binary, style, group_size) in CASES {
+        group.bench_with_input(BenchmarkId::new("per_byte", name), &data, |b, data| {
+            let fmt = PerByteFormatter::new(is_binary, group_size);
+            b.iter(|| {
+                out.clear();
+                fmt.format(black_box(data), 0, &mut out)
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("table", name), &data, |b, data| {
+            let fmt = table_formatter(style.clone(), group_size, DATA_LEN);
+            b.iter(|| {
+                out.clear();
+                fmt.format(black_box(data), 0, &mut out)
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn printer(c: &mut Criterion) {
+    let data = sample();
+    let mut out = Vec::with_capacity(16 * DATA_LEN);
+
+    let mut group = c.benchmark_group("printer");
+    group.throughput(Throughput::Bytes(DATA_LEN as u64));
+
+    for (name, is_binary, style, group_size) in CASES {
+        group.bench_with_input(BenchmarkId::new("per_byte", name), &data, |b, data| {
+            b.iter(|| dump(PerByteFormatter::new(is_binary, group_size), black_box(data), &mut out))
+        });
+
+        group.bench_with_input(BenchmarkId::new("table", name), &data, |b, data| {
+            let fmt = table_formatter(style.clone(), group_size, BYTES_PER_ROW);
+            b.iter(|| dump(fmt.clone(), black_box(data), &mut out))
+        });
+
+        group.bench_with_input(BenchmarkId::new("dyn", name), &data, |b, data| {
+            let fmt = table_formatter(style.clone(), group_size, BYTES_PER_ROW);
+            b.iter(|| dyn_dump(fmt.clone(), black_box(data), &mut out))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, byte_column, printer);
+criterion_main!(benches);
@@ -12,11 +12,11 @@ fn main() {
     
     println!("Printing in big endian");
     print_data_per_byte(data, false);
-    println!("");
+    println!();
     
     println!("Printing in little endian");
     print_data_per_byte(data, true);
-    println!("");
+    println!();
 }
 
 fn print_data_per_byte(data: &[u8], is_little_endian: bool) {
@@ -35,7 +35,7 @@ fn print_data_per_byte(data: &[u8], is_little_endian: bool) {
         false,
     );
 
-    let mut printer = Printer::new(stdout(), 0 as usize, config);
+    let mut printer = Printer::new(stdout(), 0_u64, config);
 
     
     for s in data {
@@ -45,5 +45,5 @@ fn print_data_per_byte(data: &[u8], is_little_endian: bool) {
     }
 
     _ = printer.finish();
-    println!("");
+    println!();
 }
\ No newline at end of file
@@ -23,7 +23,7 @@ fn main() {
         if size == 0 {
             break;
         }
-        assert!(printer.write_all(&mut buf[..size]).is_ok());
+        assert!(printer.write_all(&buf[..size]).is_ok());
     }
 
     printer.finish();
@@ -0,0 +1,44 @@
+//! Helpers for parsing of binary formats
+
+use std::io::{self, ErrorKind};
+
+/// Maximum length of block, packet or table. Larger values are treated as corruption
+pub(crate) const MAX_RECORD_LEN: u32 = 1 << 28;
+
+/// Byte order of the parsed data
+#[derive(Clone, Copy)]
+pub(crate) struct Endian {
+    pub(crate) big: bool,
+}
+
+impl Endian {
+    pub(crate) fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        match self.big {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        }
+    }
+
+    pub(crate) fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self.big {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        }
+    }
+
+    pub(crate) fn u64(&self, bytes: &[u8]) -> u64 {
+        let mut array = [0u8; 8];
+        array.copy_from_slice(&bytes[..8]);
+        match self.big {
+            true => u64::from_be_bytes(array),
+            false => u64::from_le_bytes(array),
+        }
+    }
+}
+
+/// Error of malformed data
+pub(crate) fn invalid(description: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, description)
+}
@@ -0,0 +1,634 @@
+//! Packets of pcap and pcapng captures
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, stdin, stdout, BufReader, BufWriter, ErrorKind, Read, Write};
+
+use super::binary::*;
+use super::*;
+
+const PCAP_MICROS_MAGIC: u32 = 0xa1b2c3d4;
+const PCAP_NANOS_MAGIC: u32 = 0xa1b23c4d;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const PACKET_BLOCK: u32 = 2;
+const SIMPLE_PACKET_BLOCK: u32 = 3;
+const ENHANCED_PACKET_BLOCK: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+
+const IF_NAME_OPTION: u16 = 2;
+const IF_TSRESOL_OPTION: u16 = 9;
+
+/// Dump of captures from `--pcap`
+pub(crate) struct Captures {
+    content: Content,
+    range: PacketRange,
+    skip_link: bool,
+    config: AppDumpConfig,
+}
+
+impl Captures {
+    pub(super) fn new(settings: &Settings) -> AppResult<Self> {
+        Ok(Self {
+            content: Content::new(settings)?,
+            range: PacketRange::new(settings)?,
+            skip_link: settings.opt_present(SKIP_LINK_LONG_NAME),
+            config: dump_config(settings)?,
+        })
+    }
+
+    /// Errors of captures are printed, only errors of output are returned
+    pub(crate) fn run(self) -> io::Result<()> {
+        let mut out = BufWriter::new(stdout());
+
+        match &self.content {
+            Content::Files(files) => {
+                for path in files {
+                    let file = match File::open(path) {
+                        Ok(f) => f,
+                        Err(e) => {
+                            eprintln!("{path}: {e}");
+                            continue;
+                        }
+                    };
+
+                    if files.len() > 1 {
+                        writeln!(out, "{path}:")?;
+                    }
+                    self.dump(BufReader::new(file), path, &mut out)?;
+                }
+            }
+            Content::Stdin => self.dump(stdin().lock(), "stdin", &mut out)?,
+        }
+
+        out.flush()
+    }
+
+    fn dump(&self, input: impl Read, name: &str, out: &mut impl Write) -> io::Result<()> {
+        let mut reader = match CaptureReader::new(input) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("{name}: {e}");
+                return Ok(());
+            }
+        };
+
+        for index in 1.. {
+            if self.range.last.is_some_and(|last| index > last) {
+                break;
+            }
+
+            let packet = match reader.next_packet() {
+                Ok(Some(p)) => p,
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("{name}: packet {index}: {e}");
+                    break;
+                }
+            };
+
+            if index < self.range.first {
+                continue;
+            }
+
+            let interface = reader.interface(packet.interface);
+            write!(
+                out,
+                "#{index} {} captured={} original={} interface={}",
+                packet.timestamp,
+                packet.data.len(),
+                packet.original_len,
+                packet.interface
+            )?;
+            if let Some(name) = interface.and_then(|i| i.name.as_ref()) {
+                write!(out, " ({name})")?;
+            }
+            writeln!(out)?;
+
+            let skipped = match (self.skip_link, interface) {
+                (true, Some(i)) => link_header_len(i.link_type, &packet.data).unwrap_or(0),
+                _ => 0,
+            };
+            let skipped = std::cmp::min(skipped, packet.data.len());
+
+            // Addresses are offsets in the packet
+            let mut printer = Printer::new(&mut *out, skipped as u64, self.config.clone());
+            printer.push(&packet.data[skipped..])?;
+            printer.finish();
+        }
+
+        Ok(())
+    }
+}
+
+/// Packets of `--packets FIRST[-LAST]`. Indexes start from 1
+pub(crate) struct PacketRange {
+    pub(crate) first: u64,
+    /// Inclusive
+    pub(crate) last: Option<u64>,
+}
+
+impl PacketRange {
+    fn new(settings: &Settings) -> AppResult<Self> {
+        match settings.opt_get::<String>(PACKETS_LONG_NAME)? {
+            Some(s) => Self::parse(&s),
+            None => Ok(Self { first: 1, last: None }),
+        }
+    }
+
+    fn parse(s: &str) -> AppResult<Self> {
+        let index = |i: &str| {
+            i.parse::<u64>()
+                .map_err(|e| AppError::new(format!("{s}: {e}")))
+        };
+
+        let (first, last) = match s.split_once('-') {
+            Some((first, "")) => (index(first)?, None),
+            Some((first, last)) => (index(first)?, Some(index(last)?)),
+            None => (index(s)?, Some(index(s)?)),
+        };
+
+        if first == 0 {
+            return Err(AppError::new(format!("{s}: Packet indexes start from 1")));
+        }
+        if last.is_some_and(|last| last < first) {
+            return Err(AppError::new(format!("{s}: Last packet is before the first one")));
+        }
+
+        Ok(Self { first, last })
+    }
+}
+
+/// Time of packet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Timestamp {
+    secs: u64,
+    nanos: u32,
+    /// Printed digits of the fraction: 6 or 9
+    digits: u32,
+}
+
+impl Timestamp {
+    /// `units` of `1 / units_per_sec` seconds since the Unix epoch
+    fn new(units: u64, units_per_sec: u64) -> Self {
+        let secs = units / units_per_sec;
+        let nanos = (units % units_per_sec) as u128 * 1_000_000_000 / units_per_sec as u128;
+
+        Self {
+            secs,
+            nanos: nanos as u32,
+            digits: if units_per_sec <= 1_000_000 { 6 } else { 9 },
+        }
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fraction = self.nanos / 10u32.pow(9 - self.digits);
+        write!(f, "{}.{:0width$}", self.secs, fraction, width = self.digits as usize)
+    }
+}
+
+pub(crate) struct Packet {
+    pub(crate) timestamp: Timestamp,
+    pub(crate) original_len: u32,
+    /// Captured bytes
+    pub(crate) data: Vec<u8>,
+    pub(crate) interface: u32,
+}
+
+pub(crate) struct Interface {
+    /// `LINKTYPE_*` value
+    pub(crate) link_type: u16,
+    pub(crate) name: Option<String>,
+    /// Resolution of timestamps
+    units_per_sec: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Pcap,
+    Pcapng,
+}
+
+/// Sequential reader of pcap or pcapng packets. Format is detected by the magic number
+pub(crate) struct CaptureReader<R: Read> {
+    input: R,
+    format: Format,
+    endian: Endian,
+    /// Interfaces of the current section. Pcap has a single one
+    interfaces: Vec<Interface>,
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub(crate) fn new(mut input: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+
+        let le_magic = u32::from_le_bytes(magic);
+        let be_magic = u32::from_be_bytes(magic);
+
+        let mut reader = Self {
+            input,
+            format: Format::Pcap,
+            endian: Endian { big: false },
+            interfaces: vec![],
+        };
+
+        let units_per_sec = match (le_magic, be_magic) {
+            (SECTION_HEADER_BLOCK, _) => {
+                reader.format = Format::Pcapng;
+                reader.read_section_header()?;
+                return Ok(reader);
+            }
+            (PCAP_MICROS_MAGIC, _) | (_, PCAP_MICROS_MAGIC) => 1_000_000,
+            (PCAP_NANOS_MAGIC, _) | (_, PCAP_NANOS_MAGIC) => 1_000_000_000,
+            _ => return Err(invalid("Not a pcap or pcapng capture")),
+        };
+        reader.endian.big = be_magic == PCAP_MICROS_MAGIC || be_magic == PCAP_NANOS_MAGIC;
+
+        // Version, time zone, accuracy, snapshot length and link type
+        let mut header = [0u8; 20];
+        reader.input.read_exact(&mut header)?;
+
+        reader.interfaces.push(Interface {
+            link_type: reader.endian.u32(&header[16..]) as u16,
+            name: None,
+            units_per_sec,
+        });
+
+        Ok(reader)
+    }
+
+    pub(crate) fn interface(&self, id: u32) -> Option<&Interface> {
+        self.interfaces.get(id as usize)
+    }
+
+    /// `None` at the end of capture
+    pub(crate) fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        match self.format {
+            Format::Pcap => self.next_pcap_packet(),
+            Format::Pcapng => self.next_pcapng_packet(),
+        }
+    }
+
+    fn next_pcap_packet(&mut self) -> io::Result<Option<Packet>> {
+        let mut header = [0u8; 16];
+        if !self.read_header(&mut header)? {
+            return Ok(None);
+        }
+
+        let e = self.endian;
+        let captured = checked_len(e.u32(&header[8..]))?;
+        let mut data = vec![0u8; captured];
+        self.input.read_exact(&mut data)?;
+
+        let units_per_sec = self.interfaces[0].units_per_sec;
+        let units = e.u32(&header[..4]) as u64 * units_per_sec + e.u32(&header[4..]) as u64;
+
+        Ok(Some(Packet {
+            timestamp: Timestamp::new(units, units_per_sec),
+            original_len: e.u32(&header[12..]),
+            data,
+            interface: 0,
+        }))
+    }
+
+    fn next_pcapng_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            let mut block_type = [0u8; 4];
+            if !self.read_header(&mut block_type)? {
+                return Ok(None);
+            }
+
+            // Byte order of the new section is not known yet
+            if u32::from_le_bytes(block_type) == SECTION_HEADER_BLOCK {
+                self.read_section_header()?;
+                continue;
+            }
+
+            let block_type = self.endian.u32(&block_type);
+            let body = self.read_block_body()?;
+
+            let packet = match block_type {
+                INTERFACE_DESCRIPTION_BLOCK => {
+                    self.add_interface(&body)?;
+                    continue;
+                }
+                ENHANCED_PACKET_BLOCK => self.enhanced_packet(&body, 4)?,
+                // Obsolete block has 16 bit interface id and 16 bit drops counter instead of 32 bit id
+                PACKET_BLOCK => self.enhanced_packet(&body, 2)?,
+                SIMPLE_PACKET_BLOCK => self.simple_packet(&body)?,
+                _ => continue,
+            };
+
+            return Ok(Some(packet));
+        }
+    }
+
+    /// Reads the header of the next record. `false` at the end of capture
+    fn read_header(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let mut read = 0;
+        while read < buf.len() {
+            match self.input.read(&mut buf[read..]) {
+                Ok(0) if read == 0 => return Ok(false),
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Reads the rest of section header block, which type is already read
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 8];
+        self.input.read_exact(&mut header)?;
+
+        self.endian.big = match u32::from_le_bytes([header[4], header[5], header[6], header[7]]) {
+            BYTE_ORDER_MAGIC => false,
+            m if m.swap_bytes() == BYTE_ORDER_MAGIC => true,
+            _ => return Err(invalid("Invalid byte order of pcapng section")),
+        };
+
+        let len = self.endian.u32(&header);
+        if len < 28 || !len.is_multiple_of(4) || len > MAX_RECORD_LEN {
+            return Err(invalid("Invalid length of pcapng block"));
+        }
+
+        // Version, section length, options and the trailing length
+        let mut rest = vec![0u8; len as usize - 12];
+        self.input.read_exact(&mut rest)?;
+
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    /// Reads the rest of block, which type is already read. Returns the body without lengths
+    fn read_block_body(&mut self) -> io::Result<Vec<u8>> {
+        let mut len = [0u8; 4];
+        self.input.read_exact(&mut len)?;
+
+        let len = self.endian.u32(&len);
+        if len < 12 || !len.is_multiple_of(4) || len > MAX_RECORD_LEN {
+            return Err(invalid("Invalid length of pcapng block"));
+        }
+
+        let mut body = vec![0u8; len as usize - 8];
+        self.input.read_exact(&mut body)?;
+        body.truncate(len as usize - 12);
+
+        Ok(body)
+    }
+
+    fn add_interface(&mut self, body: &[u8]) -> io::Result<()> {
+        if body.len() < 8 {
+            return Err(invalid("Truncated interface description"));
+        }
+
+        let e = self.endian;
+        let mut interface = Interface {
+            link_type: e.u16(body),
+            name: None,
+            units_per_sec: 1_000_000,
+        };
+
+        let mut options = &body[8..];
+        while options.len() >= 4 {
+            let code = e.u16(options);
+            let len = e.u16(&options[2..]) as usize;
+            let value = match options.get(4..4 + len) {
+                Some(v) => v,
+                None => return Err(invalid("Truncated interface option")),
+            };
+
+            match code {
+                0 => break,
+                IF_NAME_OPTION => interface.name = Some(String::from_utf8_lossy(value).into_owned()),
+                IF_TSRESOL_OPTION if len == 1 => {
+                    let exponent = (value[0] & 0x7f) as u32;
+                    interface.units_per_sec = match value[0] & 0x80 {
+                        0 if exponent <= 19 => 10u64.pow(exponent),
+                        0 => return Err(invalid("Unsupported timestamp resolution")),
+                        _ if exponent <= 63 => 1 << exponent,
+                        _ => return Err(invalid("Unsupported timestamp resolution")),
+                    };
+                }
+                _ => {}
+            }
+
+            options = options.get(4 + len.next_multiple_of(4)..).unwrap_or_default();
+        }
+
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    /// Enhanced or obsolete packet block, which interface id has `id_len` bytes
+    fn enhanced_packet(&self, body: &[u8], id_len: usize) -> io::Result<Packet> {
+        if body.len() < 20 {
+            return Err(invalid("Truncated packet block"));
+        }
+
+        let e = self.endian;
+        let interface = match id_len {
+            2 => e.u16(body) as u32,
+            _ => e.u32(body),
+        };
+        let units = (e.u32(&body[4..]) as u64) << 32 | e.u32(&body[8..]) as u64;
+        let captured = checked_len(e.u32(&body[12..]))?;
+        let data = match body.get(20..20 + captured) {
+            Some(d) => d.to_vec(),
+            None => return Err(invalid("Truncated packet data")),
+        };
+
+        Ok(Packet {
+            timestamp: Timestamp::new(units, self.units_per_sec(interface)?),
+            original_len: e.u32(&body[16..]),
+            data,
+            interface,
+        })
+    }
+
+    /// Simple packet block has no timestamp and belongs to the first interface
+    fn simple_packet(&self, body: &[u8]) -> io::Result<Packet> {
+        if body.len() < 4 {
+            return Err(invalid("Truncated packet block"));
+        }
+
+        let original_len = self.endian.u32(body);
+        let captured = std::cmp::min(original_len as usize, body.len() - 4);
+
+        Ok(Packet {
+            timestamp: Timestamp::new(0, self.units_per_sec(0)?),
+            original_len,
+            data: body[4..4 + captured].to_vec(),
+            interface: 0,
+        })
+    }
+
+    fn units_per_sec(&self, interface: u32) -> io::Result<u64> {
+        match self.interface(interface) {
+            Some(i) => Ok(i.units_per_sec),
+            None => Err(invalid("Packet of unknown interface")),
+        }
+    }
+}
+
+fn checked_len(len: u32) -> io::Result<usize> {
+    match len {
+        0..=MAX_RECORD_LEN => Ok(len as usize),
+        _ => Err(invalid("Packet is too large")),
+    }
+}
+
+/// Length of link-layer header of `frame`. `None` for unsupported link types
+fn link_header_len(link_type: u16, frame: &[u8]) -> Option<usize> {
+    const ETHERNET: u16 = 1;
+    const NULL: u16 = 0;
+    const LOOP: u16 = 108;
+    const RAW: u16 = 101;
+    const IPV4: u16 = 228;
+    const IPV6: u16 = 229;
+    const LINUX_SLL: u16 = 113;
+    const LINUX_SLL2: u16 = 276;
+
+    match link_type {
+        ETHERNET => {
+            // VLAN tags precede the ether type
+            let mut len = 14;
+            while let Some(ether_type) = frame.get(len - 2..len) {
+                match u16::from_be_bytes([ether_type[0], ether_type[1]]) {
+                    0x8100 | 0x88a8 | 0x9100 => len += 4,
+                    _ => break,
+                }
+            }
+            Some(len)
+        }
+        NULL | LOOP => Some(4),
+        RAW | IPV4 | IPV6 => Some(0),
+        LINUX_SLL => Some(16),
+        LINUX_SLL2 => Some(20),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ethernet frame with a VLAN tag and 4 bytes of payload
+    const FRAME: [u8; 22] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0x81, 0x00, 0, 5, 0x08, 0x00, b'p', b'i', b'n', b'g',
+    ];
+
+    fn pcap(big_endian: bool) -> Vec<u8> {
+        let u32 = |v: u32| match big_endian {
+            true => v.to_be_bytes(),
+            false => v.to_le_bytes(),
+        };
+
+        let mut capture = vec![];
+        capture.extend(u32(PCAP_NANOS_MAGIC));
+        capture.extend([0u8; 16]);
+        capture.extend(u32(1));
+        for (secs, nanos) in [(1_760_000_000, 5), (1_760_000_001, 999_999_999)] {
+            capture.extend(u32(secs));
+            capture.extend(u32(nanos));
+            capture.extend(u32(FRAME.len() as u32));
+            capture.extend(u32(60));
+            capture.extend(FRAME);
+        }
+
+        capture
+    }
+
+    fn pcapng() -> Vec<u8> {
+        let block = |block_type: u32, body: &[u8]| {
+            let len = (12 + body.len().next_multiple_of(4)) as u32;
+            let mut block = vec![];
+            block.extend(block_type.to_le_bytes());
+            block.extend(len.to_le_bytes());
+            block.extend(body);
+            block.resize(len as usize - 4, 0);
+            block.extend(len.to_le_bytes());
+            block
+        };
+
+        let mut section = BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        section.extend([1, 0, 0, 0]);
+        section.extend(u64::MAX.to_le_bytes());
+
+        // Ethernet interface `eth0` with millisecond timestamps
+        let mut interface = vec![1, 0, 0, 0, 0, 0, 0, 0];
+        interface.extend([2, 0, 4, 0, b'e', b't', b'h', b'0', 9, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0]);
+
+        let units = 1_760_000_000_250u64;
+        let mut packet = 0u32.to_le_bytes().to_vec();
+        packet.extend(((units >> 32) as u32).to_le_bytes());
+        packet.extend((units as u32).to_le_bytes());
+        packet.extend((FRAME.len() as u32).to_le_bytes());
+        packet.extend(60u32.to_le_bytes());
+        packet.extend(FRAME);
+
+        let mut capture = block(SECTION_HEADER_BLOCK, &section);
+        capture.extend(block(0xbad, &[1, 2, 3, 4]));
+        capture.extend(block(INTERFACE_DESCRIPTION_BLOCK, &interface));
+        capture.extend(block(ENHANCED_PACKET_BLOCK, &packet));
+        capture.extend(block(SIMPLE_PACKET_BLOCK, &[&4u32.to_le_bytes()[..], b"pong"].concat()));
+        capture
+    }
+
+    fn packets(capture: &[u8]) -> Vec<(String, Vec<u8>, u32, u32)> {
+        let mut reader = CaptureReader::new(capture).unwrap();
+        let mut packets = vec![];
+        while let Some(p) = reader.next_packet().unwrap() {
+            packets.push((p.timestamp.to_string(), p.data, p.original_len, p.interface));
+        }
+        packets
+    }
+
+    #[test]
+    fn formats() {
+        for big_endian in [false, true] {
+            let expected = [
+                ("1760000000.000000005".to_string(), FRAME.to_vec(), 60, 0),
+                ("1760000001.999999999".to_string(), FRAME.to_vec(), 60, 0),
+            ];
+            assert_eq!(packets(&pcap(big_endian)), expected);
+        }
+
+        let capture = pcapng();
+        let expected = [
+            ("1760000000.250000".to_string(), FRAME.to_vec(), 60, 0),
+            ("0.000000".to_string(), b"pong".to_vec(), 4, 0),
+        ];
+        assert_eq!(packets(&capture), expected);
+
+        let mut reader = CaptureReader::new(&capture[..]).unwrap();
+        reader.next_packet().unwrap();
+        let interface = reader.interface(0).unwrap();
+        assert_eq!(interface.name.as_deref(), Some("eth0"));
+        assert_eq!(link_header_len(interface.link_type, &FRAME), Some(18));
+
+        // Truncated capture
+        let mut reader = CaptureReader::new(&capture[..capture.len() - 6]).unwrap();
+        reader.next_packet().unwrap();
+        assert!(reader.next_packet().is_err());
+        assert!(CaptureReader::new(&b"not a capture"[..]).is_err());
+    }
+
+    #[test]
+    fn packet_range() {
+        let range = |s| PacketRange::parse(s).map(|r| (r.first, r.last));
+        assert_eq!(range("3").unwrap(), (3, Some(3)));
+        assert_eq!(range("2-5").unwrap(), (2, Some(5)));
+        assert_eq!(range("7-").unwrap(), (7, None));
+        for invalid in ["0", "5-2", "-3", "a-b", ""] {
+            assert!(range(invalid).is_err(), "{invalid}");
+        }
+    }
+}
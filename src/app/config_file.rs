@@ -0,0 +1,271 @@
+//! User configuration file.
+//!
+//! The file is TOML. Keys of the root table and of `[profiles.<name>]` tables are options:
+//! strings, non-negative integers, booleans of flags and arrays of strings of repeated options.
+//!
+//! ```toml
+//! address = "h8"
+//! group = "4/4"
+//! text_gutters = true
+//!
+//! [profiles.firmware]
+//! address = "h16"
+//! skip = 4096
+//! column = ["rel", "va=0x8000000"]
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use super::result::*;
+
+/// Overrides location of the configuration file
+pub(super) const CONFIG_PATH_ENV: &str = "KEX_CONFIG";
+/// Selects profile, when `--profile` is not specified
+pub(super) const PROFILE_ENV: &str = "KEX_PROFILE";
+
+const PROFILES_TABLE: &str = "profiles";
+const APP_DIR_NAME: &str = "kex";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+#[derive(Clone, PartialEq, Debug)]
+pub(super) enum Value {
+    Str(String),
+    Int(u64),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+impl Value {
+    fn from_toml(value: toml::Value) -> Option<Self> {
+        match value {
+            toml::Value::String(s) => Some(Value::Str(s)),
+            toml::Value::Integer(i) => u64::try_from(i).ok().map(Value::Int),
+            toml::Value::Boolean(b) => Some(Value::Bool(b)),
+            toml::Value::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    toml::Value::String(s) => Some(s),
+                    _ => None,
+                })
+                .collect::<Option<Vec<String>>>()
+                .map(Value::List),
+            _ => None,
+        }
+    }
+
+    /// Value as it would be typed in the command line
+    pub(super) fn to_arg(&self) -> String {
+        match self {
+            Value::Str(s) => s.clone(),
+            Value::Int(i) => i.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::List(items) => items.join(","),
+        }
+    }
+}
+
+/// Value as it is written in the configuration file
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "{}", toml::Value::from(s.as_str())),
+            Value::Int(i) => write!(f, "{i}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::List(items) => write!(f, "{}", toml::Value::from(items.clone())),
+        }
+    }
+}
+
+pub(super) type Table = BTreeMap<String, Value>;
+
+#[derive(Default)]
+pub(super) struct ConfigFile {
+    pub(super) path: Option<PathBuf>,
+    pub(super) root: Table,
+    pub(super) profiles: BTreeMap<String, Table>,
+}
+
+impl ConfigFile {
+    /// Loads file from `$KEX_CONFIG`, or from `$XDG_CONFIG_HOME/kex/config.toml`
+    /// (`~/.config/kex/config.toml` if `XDG_CONFIG_HOME` is not set).
+    ///
+    /// Absence of the file in the default location is not an error.
+    pub(super) fn load() -> AppResult<Self> {
+        if let Some(path) = env::var_os(CONFIG_PATH_ENV) {
+            return Self::read(Path::new(&path));
+        }
+
+        match default_path() {
+            Some(path) if path.is_file() => Self::read(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn read(path: &Path) -> AppResult<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => {
+                return Err(AppError::new(format!("{}: {e}", path.display())));
+            }
+        };
+
+        let mut file = match Self::parse(&text) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(AppError::new(format!("{}: {e}", path.display())));
+            }
+        };
+        file.path = Some(path.to_path_buf());
+
+        Ok(file)
+    }
+
+    pub(super) fn parse(text: &str) -> AppResult<Self> {
+        let mut root = match text.parse::<toml::Table>() {
+            Ok(t) => t,
+            Err(e) => {
+                let line_num = match e.span() {
+                    Some(span) => text[..span.start].matches('\n').count() + 1,
+                    None => 1,
+                };
+                return Err(line_error(line_num, &e.message().trim_end().replace('\n', ", ")));
+            }
+        };
+
+        let mut file = Self::default();
+
+        if let Some(profiles) = root.remove(PROFILES_TABLE) {
+            let profiles = match profiles {
+                toml::Value::Table(t) => t,
+                _ => {
+                    return Err(AppError::new(format!("{PROFILES_TABLE}: Expected table of profiles")));
+                }
+            };
+
+            for (name, profile) in profiles {
+                let table = match profile {
+                    toml::Value::Table(t) => table(t),
+                    _ => Err(AppError::new("Expected table".to_string())),
+                };
+
+                match table {
+                    Ok(t) => file.profiles.insert(name, t),
+                    Err(e) => return Err(AppError::new(format!("profile `{name}`: {e}"))),
+                };
+            }
+        }
+
+        file.root = table(root)?;
+
+        Ok(file)
+    }
+
+    pub(super) fn profile(&self, name: &str) -> AppResult<&Table> {
+        match self.profiles.get(name) {
+            Some(t) => Ok(t),
+            None => Err(AppError::new(format!("{name}: Unknown profile"))),
+        }
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_home.join(APP_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Options of the root table or of the profile
+fn table(toml: toml::Table) -> AppResult<Table> {
+    let mut table = Table::new();
+
+    for (key, value) in toml {
+        let value = match value {
+            toml::Value::Table(_) => {
+                return Err(AppError::new(format!("{key}: Unknown table")));
+            }
+            v => match Value::from_toml(v) {
+                Some(v) => v,
+                None => {
+                    return Err(AppError::new(format!("{key}: Invalid value")));
+                }
+            },
+        };
+
+        table.insert(key, value);
+    }
+
+    Ok(table)
+}
+
+fn line_error(line_num: usize, description: &str) -> AppError {
+    AppError::new(format!("line {line_num}: {description}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_profiles() {
+        let text = r#"
+# Defaults for every invocation
+address = "h8"
+group = "4/4" # trailing comment
+
+text_gutters = true
+
+[profiles.firmware]
+address = "h16"
+skip = 4_096
+column = ["rel", "va=0x8000000:#h8"]
+pcap = false
+
+[profiles."with spaces"]
+bytes = "C"
+"#;
+        let file = ConfigFile::parse(text).expect("Valid config");
+
+        assert_eq!(file.root["address"].to_arg(), "h8");
+        assert_eq!(file.root["group"].to_arg(), "4/4");
+
+        let firmware = file.profile("firmware").unwrap();
+        assert_eq!(firmware["address"].to_arg(), "h16");
+        assert_eq!(firmware["skip"].to_arg(), "4096");
+        assert_eq!(firmware["skip"].to_string(), "4096");
+        assert_eq!(firmware["pcap"], Value::Bool(false));
+        assert_eq!(firmware["column"].to_string(), r#"["rel", "va=0x8000000:#h8"]"#);
+        assert_eq!(file.root["text_gutters"].to_string(), "true");
+
+        assert_eq!(file.profile("with spaces").unwrap()["bytes"].to_string(), "\"C\"");
+        assert!(file.profile("missing").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("address = h8", "line 1: invalid string, expected `\"`, `'`"),
+            ("\n[other]", "other: Unknown table"),
+            ("[profiles.a", "line 1: invalid table header, expected `.`, `]`"),
+            ("skip", "line 1: expected `.`, `=`"),
+            ("skip = 1\nskip = 2", "line 2: duplicate key `skip` in document root"),
+            ("skip = -1", "skip: Invalid value"),
+            ("column = [1, 2]", "column: Invalid value"),
+            ("profiles = 1", "profiles: Expected table of profiles"),
+            ("[profiles]\na = 1", "profile `a`: Expected table"),
+            ("[profiles.a]\nb = 1.5", "profile `a`: b: Invalid value"),
+        ];
+
+        for (text, expected) in cases {
+            match ConfigFile::parse(text) {
+                Ok(_) => panic!("{text:?} must not be parsed"),
+                Err(e) => assert_eq!(e.to_string(), expected),
+            }
+        }
+    }
+}
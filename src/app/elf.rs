@@ -0,0 +1,271 @@
+//! Layout of ELF files, 32 and 64 bit of both byte orders
+
+use std::io::{self, Read, Seek};
+
+use super::object::*;
+
+pub(super) const MAGIC: &[u8] = b"\x7fELF";
+
+const SHT_NULL: u32 = 0;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u64 = 2;
+const PT_LOAD: u32 = 1;
+/// Index of section names table is in `sh_link` of the first section
+const SHN_XINDEX: u16 = 0xffff;
+
+const MAX_SECTIONS: u64 = 1 << 20;
+
+/// Sizes and offsets of fields, which depend on the class
+struct Class {
+    is64: bool,
+    endian: Endian,
+}
+
+impl Class {
+    /// Address or offset
+    fn word(&self, bytes: &[u8]) -> u64 {
+        match self.is64 {
+            true => self.endian.u64(bytes),
+            false => self.endian.u32(bytes) as u64,
+        }
+    }
+
+    /// Offset of the field in ELF64 and ELF32 structure
+    fn at(&self, offset64: usize, offset32: usize) -> usize {
+        match self.is64 {
+            true => offset64,
+            false => offset32,
+        }
+    }
+}
+
+pub(super) fn parse(file: &mut (impl Read + Seek)) -> io::Result<Layout> {
+    let ident = read_at(file, 0, 16)?;
+    let is64 = match ident[4] {
+        1 => false,
+        2 => true,
+        _ => return Err(invalid("Invalid ELF class")),
+    };
+    let big = match ident[5] {
+        1 => false,
+        2 => true,
+        _ => return Err(invalid("Invalid ELF byte order")),
+    };
+
+    let class = Class {
+        is64,
+        endian: Endian { big },
+    };
+    let e = class.endian;
+
+    let header = read_at(file, 0, class.at(64, 52) as u64)?;
+    let phoff = class.word(&header[class.at(32, 28)..]);
+    let shoff = class.word(&header[class.at(40, 32)..]);
+    let sizes = &header[class.at(52, 40)..];
+    let ehsize = e.u16(sizes) as u64;
+    let phentsize = e.u16(&sizes[2..]) as u64;
+    let phnum = e.u16(&sizes[4..]) as u64;
+    let shentsize = e.u16(&sizes[6..]) as u64;
+    let mut shnum = e.u16(&sizes[8..]) as u64;
+    let mut shstrndx = e.u16(&sizes[10..]) as u64;
+
+    let mut layout = Layout {
+        sections: vec![],
+        structures: vec![structure("<ELF header>", 0, ehsize)],
+        segments: vec![],
+    };
+
+    if phnum != 0 {
+        if phentsize < class.at(56, 32) as u64 {
+            return Err(invalid("Invalid size of ELF program header"));
+        }
+
+        let table = read_at(file, phoff, phnum * phentsize)?;
+        for header in table.chunks_exact(phentsize as usize) {
+            if e.u32(header) != PT_LOAD {
+                continue;
+            }
+
+            layout.segments.push(Segment {
+                offset: class.word(&header[class.at(8, 4)..]),
+                address: class.word(&header[class.at(16, 8)..]),
+                size: class.word(&header[class.at(32, 16)..]),
+            });
+        }
+
+        layout.structures.push(structure("<program headers>", phoff, phnum * phentsize));
+    }
+
+    if shoff == 0 {
+        return Ok(layout);
+    }
+    if shentsize < class.at(64, 40) as u64 {
+        return Err(invalid("Invalid size of ELF section header"));
+    }
+
+    // Large numbers are stored in the first section header
+    let first = read_at(file, shoff, shentsize)?;
+    if shnum == 0 {
+        shnum = class.word(&first[class.at(32, 20)..]);
+    }
+    if shstrndx == SHN_XINDEX as u64 {
+        shstrndx = e.u32(&first[class.at(40, 24)..]) as u64;
+    }
+    if shnum > MAX_SECTIONS {
+        return Err(invalid("Too many ELF sections"));
+    }
+
+    let table = read_at(file, shoff, shnum * shentsize)?;
+    let headers: Vec<&[u8]> = table.chunks_exact(shentsize as usize).collect();
+
+    let names = match headers.get(shstrndx as usize) {
+        Some(header) => {
+            let offset = class.word(&header[class.at(24, 16)..]);
+            let size = class.word(&header[class.at(32, 20)..]);
+            read_at(file, offset, size)?
+        }
+        None => vec![],
+    };
+
+    for header in headers {
+        let section_type = e.u32(&header[4..]);
+        if section_type == SHT_NULL {
+            continue;
+        }
+
+        let flags = class.word(&header[8..]);
+        let address = class.word(&header[class.at(16, 12)..]);
+
+        layout.sections.push(Section {
+            name: c_string(names.get(e.u32(header) as usize..).unwrap_or_default()),
+            offset: class.word(&header[class.at(24, 16)..]),
+            size: class.word(&header[class.at(32, 20)..]),
+            address: (flags & SHF_ALLOC != 0).then_some(address),
+            is_zero_fill: section_type == SHT_NOBITS,
+        });
+    }
+
+    layout.structures.push(structure("<section headers>", shoff, shnum * shentsize));
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// ELF with `.text`, `.rodata`, `.bss` and `.shstrtab` sections and a single loaded segment
+    fn elf(is64: bool, big: bool) -> Vec<u8> {
+        let half = |v: u16| match big {
+            true => v.to_be_bytes().to_vec(),
+            false => v.to_le_bytes().to_vec(),
+        };
+        let u32 = |v: u32| match big {
+            true => v.to_be_bytes().to_vec(),
+            false => v.to_le_bytes().to_vec(),
+        };
+        let word = |v: u64| match (is64, big) {
+            (true, true) => v.to_be_bytes().to_vec(),
+            (true, false) => v.to_le_bytes().to_vec(),
+            (false, _) => u32(v as u32),
+        };
+
+        let (ehsize, phentsize, shentsize) = match is64 {
+            true => (64u16, 56u16, 64u16),
+            false => (52, 32, 40),
+        };
+        let names = b"\0.text\0.rodata\0.bss\0.shstrtab\0";
+
+        let mut elf = vec![0x7f, b'E', b'L', b'F', if is64 { 2 } else { 1 }, if big { 2 } else { 1 }, 1];
+        elf.resize(16, 0);
+        elf.extend(half(2));
+        elf.extend(half(62));
+        elf.extend(u32(1));
+        elf.extend(word(0x400100));
+        elf.extend(word(ehsize as u64));
+        elf.extend(word(0x200));
+        elf.extend(u32(0));
+        for v in [ehsize, phentsize, 1, shentsize, 5, 4] {
+            elf.extend(half(v));
+        }
+
+        // PT_LOAD of the first 0x118 bytes
+        let mut segment = u32(PT_LOAD);
+        match is64 {
+            true => {
+                segment.extend(u32(5));
+                for v in [0, 0x400000, 0x400000, 0x118, 0x200, 0x1000] {
+                    segment.extend(word(v));
+                }
+            }
+            false => {
+                for v in [0, 0x400000, 0x400000, 0x118, 0x200, 5, 0x1000] {
+                    segment.extend(u32(v));
+                }
+            }
+        }
+        elf.extend(segment);
+
+        elf.resize(0x100, 0);
+        elf.extend(b"0123456789abcdef");
+        elf.extend(b"rodata\0\0");
+        elf.resize(0x120, 0);
+        elf.extend(names);
+        elf.resize(0x200, 0);
+
+        let section = |name: u32, section_type: u32, flags: u64, address: u64, offset: u64, size: u64| {
+            let mut header = u32(name);
+            header.extend(u32(section_type));
+            for v in [flags, address, offset, size] {
+                header.extend(word(v));
+            }
+            header.extend(u32(0));
+            header.extend(u32(0));
+            header.extend(word(1));
+            header.extend(word(0));
+            header
+        };
+        elf.extend(section(0, SHT_NULL, 0, 0, 0, 0));
+        elf.extend(section(1, 1, SHF_ALLOC | 4, 0x400100, 0x100, 0x10));
+        elf.extend(section(7, 1, SHF_ALLOC, 0x400110, 0x110, 0x8));
+        elf.extend(section(15, SHT_NOBITS, SHF_ALLOC | 1, 0x401000, 0x118, 0x40));
+        elf.extend(section(20, 3, 0, 0, 0x120, names.len() as u64));
+
+        elf
+    }
+
+    #[test]
+    fn classes_and_byte_orders() {
+        for (is64, big) in [(true, false), (true, true), (false, false), (false, true)] {
+            let layout = parse(&mut Cursor::new(elf(is64, big))).unwrap();
+
+            let sections: Vec<_> = layout
+                .sections
+                .iter()
+                .map(|s| (s.name.as_str(), s.offset, s.size, s.address, s.is_zero_fill))
+                .collect();
+            let expected = [
+                (".text", 0x100, 0x10, Some(0x400100), false),
+                (".rodata", 0x110, 0x8, Some(0x400110), false),
+                (".bss", 0x118, 0x40, Some(0x401000), true),
+                (".shstrtab", 0x120, 30, None, false),
+            ];
+            assert_eq!(sections, expected, "64 bit: {is64}, big endian: {big}");
+
+            let structures: Vec<_> = layout.structures.iter().map(|s| (s.name.as_str(), s.offset)).collect();
+            let header_len = if is64 { 64 } else { 52 };
+            assert_eq!(
+                structures,
+                [("<ELF header>", 0), ("<program headers>", header_len), ("<section headers>", 0x200)]
+            );
+
+            assert_eq!(layout.address_of(0x104), Some(0x400104));
+            assert_eq!(layout.address_of(0x118), None);
+        }
+
+        let mut invalid = elf(true, false);
+        invalid[4] = 3;
+        assert!(parse(&mut Cursor::new(invalid)).is_err());
+        assert!(parse(&mut Cursor::new(&elf(false, true)[..0x80])).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Layout of Mach-O files, 32 and 64 bit of both byte orders, and fat binaries
+
+use std::io::{self, Read, Seek};
+
+use super::object::*;
+
+const MH_MAGIC: u32 = 0xfeedface;
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const FAT_MAGIC: u32 = 0xcafebabe;
+const FAT_MAGIC_64: u32 = 0xcafebabf;
+/// Java class files share the magic with fat binaries, their version is at least 45
+const MAX_FAT_ARCHS: u32 = 32;
+
+const LC_SEGMENT: u32 = 0x1;
+const LC_SEGMENT_64: u32 = 0x19;
+
+const SECTION_TYPE: u32 = 0xff;
+const S_ZEROFILL: u32 = 0x1;
+const S_GB_ZEROFILL: u32 = 0xc;
+const S_THREAD_LOCAL_ZEROFILL: u32 = 0x12;
+
+pub(super) fn is_magic(magic: &[u8]) -> bool {
+    let magic = match magic.get(..4) {
+        Some(m) => [m[0], m[1], m[2], m[3]],
+        None => return false,
+    };
+
+    [u32::from_be_bytes(magic), u32::from_le_bytes(magic)].iter().any(|m| [MH_MAGIC, MH_MAGIC_64].contains(m))
+        || [FAT_MAGIC, FAT_MAGIC_64].contains(&u32::from_be_bytes(magic))
+}
+
+pub(super) fn parse(file: &mut (impl Read + Seek)) -> io::Result<Layout> {
+    let mut layout = Layout {
+        sections: vec![],
+        structures: vec![],
+        segments: vec![],
+    };
+
+    // Fat header is always big endian
+    let e = Endian { big: true };
+    let header = read_at(file, 0, 8)?;
+    let magic = e.u32(&header);
+    if magic != FAT_MAGIC && magic != FAT_MAGIC_64 {
+        parse_slice(file, 0, "", &mut layout)?;
+        return Ok(layout);
+    }
+
+    let archs_num = e.u32(&header[4..]);
+    if archs_num > MAX_FAT_ARCHS {
+        return Err(invalid("Unknown object file format"));
+    }
+    let arch_len = if magic == FAT_MAGIC_64 { 32 } else { 20 };
+    let table = read_at(file, 8, archs_num as u64 * arch_len)?;
+    layout.structures.push(structure("<fat header>", 0, 8 + table.len() as u64));
+
+    for arch in table.chunks_exact(arch_len as usize) {
+        let (offset, size) = match magic {
+            FAT_MAGIC_64 => (e.u64(&arch[8..]), e.u64(&arch[16..])),
+            _ => (e.u32(&arch[8..]) as u64, e.u32(&arch[12..]) as u64),
+        };
+        let prefix = format!("{}:", cpu_name(e.u32(arch)));
+
+        let slice_magic = read_at(file, offset, 4)?;
+        match is_magic(&slice_magic) {
+            true => parse_slice(file, offset, &prefix, &mut layout)?,
+            // Like static libraries
+            false => layout.structures.push(structure(&format!("{prefix}<slice>"), offset, size)),
+        }
+    }
+
+    Ok(layout)
+}
+
+/// Thin Mach-O file at `base`. Names of sections and structures are prefixed by `prefix`
+fn parse_slice(file: &mut (impl Read + Seek), base: u64, prefix: &str, layout: &mut Layout) -> io::Result<()> {
+    let header = read_at(file, base, 32)?;
+    let (is64, big) = match (u32::from_le_bytes([header[0], header[1], header[2], header[3]]), header[0]) {
+        (MH_MAGIC, _) => (false, false),
+        (MH_MAGIC_64, _) => (true, false),
+        (_, 0xfe) if header[3] == 0xce => (false, true),
+        (_, 0xfe) if header[3] == 0xcf => (true, true),
+        _ => return Err(invalid("Invalid Mach-O magic")),
+    };
+    let e = Endian { big };
+
+    let header_len = if is64 { 32 } else { 28 };
+    let commands_num = e.u32(&header[16..]);
+    let commands_len = e.u32(&header[20..]) as u64;
+
+    layout.structures.push(structure(&format!("{prefix}<Mach-O header>"), base, header_len));
+    layout.structures.push(structure(&format!("{prefix}<load commands>"), base + header_len, commands_len));
+
+    let commands = read_at(file, base + header_len, commands_len)?;
+    let mut rest = commands.as_slice();
+    for _ in 0..commands_num {
+        if rest.len() < 8 {
+            return Err(invalid("Truncated Mach-O load command"));
+        }
+        let command_len = e.u32(&rest[4..]) as usize;
+        if command_len < 8 || command_len > rest.len() {
+            return Err(invalid("Invalid size of Mach-O load command"));
+        }
+
+        let (command, next) = rest.split_at(command_len);
+        match e.u32(command) {
+            LC_SEGMENT if command.len() >= 56 => segment(command, false, e, base, prefix, layout)?,
+            LC_SEGMENT_64 if command.len() >= 72 => segment(command, true, e, base, prefix, layout)?,
+            LC_SEGMENT | LC_SEGMENT_64 => return Err(invalid("Invalid size of Mach-O segment command")),
+            _ => {}
+        }
+
+        rest = next;
+    }
+
+    Ok(())
+}
+
+/// `LC_SEGMENT` or `LC_SEGMENT_64` command with its sections
+fn segment(command: &[u8], is64: bool, e: Endian, base: u64, prefix: &str, layout: &mut Layout) -> io::Result<()> {
+    let word = |bytes: &[u8]| match is64 {
+        true => e.u64(bytes),
+        false => e.u32(bytes) as u64,
+    };
+
+    let (address, offset, size) = match is64 {
+        true => (word(&command[24..]), word(&command[40..]), word(&command[48..])),
+        false => (word(&command[24..]), word(&command[32..]), word(&command[36..])),
+    };
+    if size != 0 {
+        layout.segments.push(Segment {
+            offset: base.checked_add(offset).ok_or_else(|| invalid("Invalid Mach-O segment offset"))?,
+            size,
+            address,
+        });
+    }
+
+    let (sections_num, header_len, section_len) = match is64 {
+        true => (e.u32(&command[64..]) as usize, 72, 80),
+        false => (e.u32(&command[48..]) as usize, 56, 68),
+    };
+    let headers = &command[header_len..];
+    if sections_num * section_len > headers.len() {
+        return Err(invalid("Invalid number of Mach-O sections"));
+    }
+
+    for header in headers.chunks_exact(section_len).take(sections_num) {
+        let (address, size, offset, flags) = match is64 {
+            true => (word(&header[32..]), word(&header[40..]), e.u32(&header[48..]), e.u32(&header[64..])),
+            false => (word(&header[32..]), word(&header[36..]), e.u32(&header[40..]), e.u32(&header[56..])),
+        };
+
+        layout.sections.push(Section {
+            name: format!("{prefix}{},{}", c_string(&header[16..32]), c_string(&header[..16])),
+            offset: base.checked_add(offset as u64).ok_or_else(|| invalid("Invalid Mach-O section offset"))?,
+            size,
+            address: Some(address),
+            is_zero_fill: [S_ZEROFILL, S_GB_ZEROFILL, S_THREAD_LOCAL_ZEROFILL].contains(&(flags & SECTION_TYPE)),
+        });
+    }
+
+    Ok(())
+}
+
+/// Architecture of the fat binary slice
+fn cpu_name(cpu_type: u32) -> String {
+    let name = match cpu_type {
+        7 => "i386",
+        0x01000007 => "x86_64",
+        12 => "arm",
+        0x0100000c => "arm64",
+        0x0200000c => "arm64_32",
+        18 => "ppc",
+        0x01000012 => "ppc64",
+        _ => return format!("cpu{cpu_type}"),
+    };
+
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Mach-O with `__TEXT` segment of `__text` and `__cstring` sections and `__DATA` segment of `__bss`
+    fn macho(is64: bool, big: bool) -> Vec<u8> {
+        let u32 = |v: u32| match big {
+            true => v.to_be_bytes().to_vec(),
+            false => v.to_le_bytes().to_vec(),
+        };
+        let word = |v: u64| match (is64, big) {
+            (true, true) => v.to_be_bytes().to_vec(),
+            (true, false) => v.to_le_bytes().to_vec(),
+            (false, _) => u32(v as u32),
+        };
+        let name = |name: &str| {
+            let mut bytes = name.as_bytes().to_vec();
+            bytes.resize(16, 0);
+            bytes
+        };
+
+        let (header_len, segment_len, section_len) = match is64 {
+            true => (32, 72, 80),
+            false => (28, 56, 68),
+        };
+        let commands_len = 2 * segment_len + 3 * section_len;
+
+        let mut macho = u32(if is64 { MH_MAGIC_64 } else { MH_MAGIC });
+        for v in [0x0100000c, 0, 2, 2, commands_len as u32, 0] {
+            macho.extend(u32(v));
+        }
+        macho.resize(header_len, 0);
+
+        let segment = |segment_name: &str, address: u64, offset: u64, size: u64, sections: u32| {
+            let mut command = u32(if is64 { LC_SEGMENT_64 } else { LC_SEGMENT });
+            command.extend(u32((segment_len + sections as usize * section_len) as u32));
+            command.extend(name(segment_name));
+            for v in [address, 0x1000, offset, size] {
+                command.extend(word(v));
+            }
+            for v in [5, 5, sections, 0] {
+                command.extend(u32(v));
+            }
+            command
+        };
+        let section = |section_name: &str, segment_name: &str, address: u64, size: u64, offset: u32, flags: u32| {
+            let mut header = name(section_name);
+            header.extend(name(segment_name));
+            header.extend(word(address));
+            header.extend(word(size));
+            for v in [offset, 0, 0, 0, flags, 0, 0] {
+                header.extend(u32(v));
+            }
+            header.resize(section_len, 0);
+            header
+        };
+
+        macho.extend(segment("__TEXT", 0x100000000, 0, 0x200, 2));
+        macho.extend(section("__text", "__TEXT", 0x100000180, 0x40, 0x180, 0));
+        macho.extend(section("__cstring", "__TEXT", 0x1000001c0, 0x10, 0x1c0, 2));
+        macho.extend(segment("__DATA", 0x100001000, 0, 0, 1));
+        macho.extend(section("__bss", "__DATA", 0x100001000, 0x100, 0, S_ZEROFILL));
+
+        macho.resize(0x200, 0x90);
+        macho
+    }
+
+    #[test]
+    fn thin() {
+        for (is64, big) in [(true, false), (true, true), (false, false), (false, true)] {
+            let file = macho(is64, big);
+            assert!(is_magic(&file));
+            let layout = parse(&mut Cursor::new(file)).unwrap();
+
+            let sections: Vec<_> = layout
+                .sections
+                .iter()
+                .map(|s| (s.name.as_str(), s.offset, s.size, s.address, s.is_zero_fill))
+                .collect();
+            let text = if is64 { 0x100000180 } else { 0x180 };
+            let expected = [
+                ("__TEXT,__text", 0x180, 0x40, Some(text), false),
+                ("__TEXT,__cstring", 0x1c0, 0x10, Some(text + 0x40), false),
+                ("__DATA,__bss", 0, 0x100, Some(text - 0x180 + 0x1000), true),
+            ];
+            assert_eq!(sections, expected, "64 bit: {is64}, big endian: {big}");
+            assert_eq!(layout.address_of(0x1c4), Some(text + 0x44));
+        }
+    }
+
+    #[test]
+    fn fat() {
+        let mut fat = FAT_MAGIC.to_be_bytes().to_vec();
+        fat.extend(2u32.to_be_bytes());
+        for (cpu_type, offset) in [(0x01000007u32, 0x1000u32), (0x0100000c, 0x2000)] {
+            for v in [cpu_type, 3, offset, 0x200, 12] {
+                fat.extend(v.to_be_bytes());
+            }
+        }
+        fat.resize(0x1000, 0);
+        fat.extend(macho(true, false));
+        fat.resize(0x2000, 0);
+        fat.extend(macho(true, false));
+
+        let layout = parse(&mut Cursor::new(&fat)).unwrap();
+        let text = layout.section("arm64:__TEXT,__text").unwrap();
+        assert_eq!((text.offset, text.address), (0x2180, Some(0x100000180)));
+        let text = layout.section("__TEXT,__text").unwrap();
+        assert_eq!((text.name.as_str(), text.offset), ("x86_64:__TEXT,__text", 0x1180));
+
+        assert_eq!(layout.address_of(0x2004), Some(0x100000004));
+        assert_eq!(layout.structures[0].name, "<fat header>");
+        assert_eq!(layout.structures[3].name, "arm64:<Mach-O header>");
+
+        // File offset of `__TEXT` of the second slice
+        let mut overflowing = fat;
+        overflowing[0x2000 + 72..0x2000 + 80].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(parse(&mut Cursor::new(overflowing)).is_err());
+
+        let mut class = FAT_MAGIC.to_be_bytes().to_vec();
+        class.extend(52u32.to_be_bytes());
+        assert!(parse(&mut Cursor::new(class)).is_err());
+    }
+}
@@ -1,8 +1,7 @@
-use getopts::*;
 use kex::*;
 use std::env;
 use std::{
-    io::{stdout, Stdout, Write},
+    io::{stdout, Read, Stdout, Write},
 };
 
 mod result;
@@ -11,9 +10,34 @@ pub(crate) use result::*;
 mod opts;
 use opts::*;
 
+mod config_file;
+use config_file::*;
+
+mod table_file;
+
+mod settings;
+use settings::*;
+
+mod sparse;
+pub(crate) use sparse::{stream_sparse, HoleOutput};
+
 pub(crate) use opts::ContentRange;
 
-pub(crate) fn get_app_config() -> AppResult<AppConfig> {
+mod proxy;
+pub(crate) use proxy::Proxy;
+
+mod binary;
+
+mod capture;
+pub(crate) use capture::Captures;
+
+mod elf;
+mod macho;
+mod object;
+mod pe;
+pub(crate) use object::Sections;
+
+pub(crate) fn get_command() -> AppResult<Command> {
     let args = env::args().skip(1);
 
     let opts = get_configured_opts();
@@ -25,7 +49,51 @@ pub(crate) fn get_app_config() -> AppResult<AppConfig> {
         }
     };
 
-    AppConfig::new(matches)
+    let settings = Settings::new(matches, ConfigFile::load()?)?;
+
+    if settings.opt_present(PRINT_CONFIG_LONG_NAME) {
+        // Catch invalid values before printing them as effective
+        AddressFormat::new(&settings)?;
+        ByteFormat::new(&settings)?;
+        Groupping::new(&settings)?;
+        ContentRange::new(&settings)?;
+
+        return Ok(Command::PrintConfig(settings.describe()));
+    }
+
+    if let Some(addrs) = ProxyAddrs::new(&settings)? {
+        if !settings.free().is_empty() {
+            return Err(AppError::new(format!("--{PROXY_LONG_NAME} can not be used with files")));
+        }
+
+        return Ok(Command::Proxy(Box::new(Proxy::new(addrs, dump_config(&settings)?))));
+    }
+
+    if settings.opt_present(PCAP_LONG_NAME) {
+        return Ok(Command::Captures(Box::new(Captures::new(&settings)?)));
+    }
+    if settings.opt_present(SECTION_LONG_NAME) || settings.opt_present(SECTIONS_LONG_NAME) {
+        return Ok(Command::Sections(Box::new(Sections::new(&settings)?)));
+    }
+    for name in [SKIP_LINK_LONG_NAME, PACKETS_LONG_NAME] {
+        if settings.opt_present(name) {
+            return Err(AppError::new(format!("--{name} requires --{PCAP_LONG_NAME}")));
+        }
+    }
+
+    Ok(Command::Dump(Box::new(AppConfig::new(settings)?)))
+}
+
+pub(crate) enum Command {
+    Dump(Box<AppConfig>),
+    /// Forward connections and dump their traffic
+    Proxy(Box<Proxy>),
+    /// Dump packets of captures
+    Captures(Box<Captures>),
+    /// Dump sections of object files
+    Sections(Box<Sections>),
+    /// Print effective configuration and exit
+    PrintConfig(String),
 }
 
 pub(crate) struct AppConfig {
@@ -34,9 +102,9 @@ pub(crate) struct AppConfig {
 }
 
 impl AppConfig {
-    fn new(matches: Matches) -> AppResult<Self> {
-        let input = Input::new(&matches)?;
-        let output = Output::new(&matches, input.range.skip)?;
+    fn new(settings: Settings) -> AppResult<Self> {
+        let input = Input::new(&settings)?;
+        let output = Output::new(&settings, input.range.skip)?;
 
         Ok(Self { input, output })
     }
@@ -48,9 +116,9 @@ pub(crate) struct Input {
 }
 
 impl Input {
-    fn new(matches: &Matches) -> AppResult<Self> {
-        let range = ContentRange::new(matches)?;
-        let content = Content::new(matches)?;
+    fn new(settings: &Settings) -> AppResult<Self> {
+        let range = ContentRange::new(settings)?;
+        let content = Content::new(settings)?;
 
         Ok(Self {
             range,
@@ -65,10 +133,10 @@ pub(crate) enum Content {
 }
 
 impl Content {
-    fn new(matches: &Matches) -> AppResult<Self> {
-        let free_args = matches.free.clone();
+    fn new(settings: &Settings) -> AppResult<Self> {
+        let free_args = settings.free().to_vec();
 
-        if free_args.len() != 0 {
+        if !free_args.is_empty() {
             Ok(Content::Files(free_args))
         } else {
             Ok(Content::Stdin)
@@ -76,47 +144,109 @@ impl Content {
     }
 }
 
-pub(crate) struct Output {
-    printer: Printer<Stdout, AddressFormatter, ByteFormatter, CharFormatter>,
+type AppDumpConfig = Config<AddressFormatter, ByteFormatter, CharFormatter>;
+type AppPrinter = Printer<Stdout, AddressFormatter, ByteFormatter, CharFormatter>;
+type AppParallelPrinter = ParallelPrinter<Stdout, AddressFormatter, ByteFormatter, CharFormatter>;
+
+pub(crate) enum Output {
+    Single(AppPrinter),
+    Parallel(AppParallelPrinter),
 }
 
 impl Output {
-    fn new(matches: &Matches, offset: usize) -> AppResult<Self> {
-        let byte_style = ByteStyle::new(matches)?;
-        let char_formatter = match byte_style {
-            ByteStyle::Ascii | ByteStyle::CaretAscii => None,
-            _ => Some(CharFormatter::new(".", Separators::new(" |", "|")))
+    fn new(settings: &Settings, offset: u64) -> AppResult<Self> {
+        let config = dump_config(settings)?;
+
+        let threads: usize = settings.opt_get(THREADS_SHORT_NAME)?.unwrap_or(1);
+        let output = match threads {
+            0 => return Err(AppError::new("Number of threads must be positive".to_string())),
+            1 => Printer::try_new(stdout(), offset, config).map(Output::Single),
+            n => ParallelPrinter::try_new(stdout(), offset, config, n).map(Output::Parallel),
         };
+        output.map_err(|e| AppError::new(format!("{e}")))
+    }
+}
 
-        let config = Config::new(
-            Some(AddressFormatter::new(
-                AddressStyle::new(matches)?,
-                Separators::new("", " "),
-            )),
-            ByteFormatter::new(
-                byte_style,
-                Groupping::new(matches)?,
-                " ",
-                false,
-                Separators::new(" ", " "),
-            ),
-            char_formatter,
-            true,
+/// Formatting configuration from the formatting options
+fn dump_config(settings: &Settings) -> AppResult<AppDumpConfig> {
+    let byte_format = ByteFormat::new(settings)?;
+    let groupping = Groupping::new(settings)?;
+
+    let mut char_formatter = CharFormatter::new(".", Separators::new(" |", "|"));
+    if let Some(table) = &byte_format.table {
+        char_formatter = char_formatter.with_table(table.clone());
+    }
+    if settings.opt_present(TEXT_GUTTERS_LONG_NAME) {
+        char_formatter = char_formatter.with_gutters(groupping.clone(), " ");
+    }
+    let char_formatter = match &byte_format.style {
+        ByteStyle::Ascii | ByteStyle::CaretAscii | ByteStyle::Table(_) => None,
+        _ => Some(char_formatter),
+    };
+
+    let address_format = AddressFormat::new(settings)?;
+
+    let max_dedup_period: usize = settings.opt_get(DEDUP_PERIOD_LONG_NAME)?.unwrap_or(1);
+    let placeholder = match settings.opt_get::<String>(PLACEHOLDER_LONG_NAME)? {
+        Some(template) => DedupPlaceholder::new(&template),
+        None if max_dedup_period > 1 => DedupPlaceholder::periodic(),
+        None => DedupPlaceholder::asterisk(),
+    };
+
+    let mut builder = ConfigBuilder::new(
+        ByteFormatter::new(
+            byte_format.style.clone(),
+            groupping,
+            byte_format.byte_separator(),
+            false,
+            Separators::new(" ", " "),
+        )
+        .with_affixes(&byte_format.prefix, &byte_format.suffix),
+    )
+    .with_address(
+        AddressFormatter::new(address_format.style, Separators::new("", " "))
+            .with_prefix(address_format.prefix),
+    )
+    .with_placeholder(placeholder)
+    .with_max_dedup_period(max_dedup_period);
+
+    if let Some(char_formatter) = char_formatter {
+        builder = builder.with_text(char_formatter);
+    }
+
+    for column in AddressColumnFormat::all(settings)? {
+        builder = builder.with_address_column(
+            column.kind,
+            AddressFormatter::new(column.format.style, Separators::new("", " "))
+                .with_prefix(column.format.prefix),
         );
+    }
 
-        Ok(Self {
-            printer: Printer::new(stdout(), offset, config),
-        })
+    builder.build().map_err(|e| AppError::new(format!("{e}")))
+}
+
+impl HoleOutput for Output {
+    fn push_hole(&mut self, len: u64) -> std::io::Result<()> {
+        match self {
+            Output::Single(p) => p.push_hole(len),
+            Output::Parallel(p) => p.push_hole(len),
+        }
     }
 }
 
 impl Write for Output {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.printer.write(buf)
+        match self {
+            Output::Single(p) => p.write(buf),
+            Output::Parallel(p) => p.write(buf),
+        }
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        self.printer.flush()
+        match self {
+            Output::Single(p) => p.flush(),
+            Output::Parallel(p) => p.flush(),
+        }
     }
 }
 
@@ -124,4 +254,35 @@ impl From<std::io::Error> for AppError {
     fn from(value: std::io::Error) -> Self {
         AppError::new(format!("{value}"))
     }
-}
\ No newline at end of file
+}
+
+/// Copies `input` to `output` until the end of input or `n_bytes`. Read error ends the input
+pub(crate) fn stream(mut input: impl Read, mut output: impl Write, n_bytes: Option<u64>) -> std::io::Result<()> {
+    use std::cmp::min;
+
+    let mut buf = [0u8; 4096];
+    
+    let mut elapsed = 0;
+    let mut to_read = buf.len();
+
+    while to_read != 0 {
+        if let Some(n_bytes) = n_bytes {
+            let diff = n_bytes - min(n_bytes, elapsed);
+            to_read = min(to_read as u64, diff) as usize;
+        }
+
+        if let Ok(size) = input.read(&mut buf[..to_read]) {
+            if size == 0 {
+                break;
+            }
+            output.write_all(&buf[..size])?;
+
+            elapsed += size as u64;
+        } else {
+            break;
+        }
+
+    }
+
+    Ok(())
+}
@@ -0,0 +1,374 @@
+//! Section-aware dumping of object files
+
+use std::fs::File;
+use std::io::{self, stdout, BufWriter, Read, Seek, SeekFrom, Write};
+
+use super::binary::MAX_RECORD_LEN;
+pub(crate) use super::binary::{invalid, Endian};
+use super::*;
+
+/// Region of object file
+pub(crate) struct Section {
+    pub(crate) name: String,
+    /// Offset in the file
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    /// Virtual address of the first byte, if the section is loaded
+    pub(crate) address: Option<u64>,
+    /// Section occupies no space in the file, like `.bss`
+    pub(crate) is_zero_fill: bool,
+}
+
+/// Loaded part of the file
+pub(crate) struct Segment {
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    pub(crate) address: u64,
+}
+
+/// Sections and segments of object file
+pub(crate) struct Layout {
+    pub(crate) sections: Vec<Section>,
+    /// Headers and tables of the format itself, like `<ELF header>`
+    pub(crate) structures: Vec<Section>,
+    pub(crate) segments: Vec<Segment>,
+}
+
+impl Layout {
+    /// Format is detected by the magic number
+    pub(crate) fn parse(file: &mut (impl Read + Seek)) -> io::Result<Self> {
+        let magic = read_at(file, 0, 4)?;
+
+        match magic.as_slice() {
+            elf::MAGIC => elf::parse(file),
+            m if m.starts_with(pe::MAGIC) => pe::parse(file),
+            m if macho::is_magic(m) => macho::parse(file),
+            _ => Err(invalid("Unknown object file format")),
+        }
+    }
+
+    /// The first section or structure named `name`. Sections of fat Mach-O files are prefixed
+    /// by the architecture, like `arm64:__TEXT,__text`. Without the prefix, the first slice is used
+    pub(crate) fn section(&self, name: &str) -> Option<&Section> {
+        let mut regions = self.sections.iter().chain(self.structures.iter());
+
+        regions.clone().find(|s| s.name == name).or_else(|| {
+            regions.find(|s| s.name.split_once(':').is_some_and(|(_, unprefixed)| unprefixed == name))
+        })
+    }
+
+    /// Virtual address of the byte at file `offset`. Addresses beyond `u64::MAX` are treated as unknown
+    pub(crate) fn address_of(&self, offset: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .find(|s| offset >= s.offset && offset - s.offset < s.size)
+            .and_then(|s| s.address.checked_add(offset - s.offset))
+    }
+}
+
+/// Header or table of the format, which is not loaded
+pub(crate) fn structure(name: &str, offset: u64, size: u64) -> Section {
+    Section {
+        name: name.to_string(),
+        offset,
+        size,
+        address: None,
+        is_zero_fill: false,
+    }
+}
+
+/// Null terminated or null padded string
+pub(crate) fn c_string(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+/// Reads `len` bytes at `offset`
+pub(crate) fn read_at(file: &mut (impl Read + Seek), offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    if len > MAX_RECORD_LEN as u64 {
+        return Err(invalid("Table is too large"));
+    }
+
+    let mut buf = vec![0u8; len as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buf)?;
+
+    Ok(buf)
+}
+
+/// What `--section` or `--sections` dumps
+pub(crate) enum SectionMode {
+    /// Single section at its virtual address
+    Single(String),
+    /// Whole file with a header before every section
+    All,
+}
+
+/// Dump of object files from `--section` or `--sections`
+pub(crate) struct Sections {
+    files: Vec<String>,
+    mode: SectionMode,
+    config: AppDumpConfig,
+}
+
+impl Sections {
+    pub(super) fn new(settings: &Settings) -> AppResult<Self> {
+        let mode = match settings.opt_get::<String>(SECTION_LONG_NAME)? {
+            Some(name) => SectionMode::Single(name),
+            None => SectionMode::All,
+        };
+
+        let files = settings.free().to_vec();
+        if files.is_empty() {
+            return Err(AppError::new("Sections are dumped only from files".to_string()));
+        }
+
+        Ok(Self {
+            files,
+            mode,
+            config: dump_config(settings)?,
+        })
+    }
+
+    /// Unreadable files and files of unknown format are reported to stderr and skipped.
+    /// Fails only if writing of the dump fails
+    pub(crate) fn run(self) -> io::Result<()> {
+        let mut out = BufWriter::new(stdout());
+
+        for path in self.files.iter() {
+            let mut file = match File::open(path) {
+                Ok(f) => f,
+                Err(e) => {
+                    eprintln!("{path}: {e}");
+                    continue;
+                }
+            };
+            let layout = match Layout::parse(&mut file) {
+                Ok(l) => l,
+                Err(e) => {
+                    eprintln!("{path}: {e}");
+                    continue;
+                }
+            };
+
+            if self.files.len() > 1 {
+                writeln!(out, "{path}:")?;
+            }
+
+            match &self.mode {
+                SectionMode::Single(name) => match layout.section(name) {
+                    Some(section) => self.dump_section(&mut file, section, &mut out)?,
+                    None => eprintln!("{path}: {name}: No such section"),
+                },
+                SectionMode::All => self.dump_all(&mut file, &layout, &mut out)?,
+            }
+        }
+
+        out.flush()
+    }
+
+    /// Addresses are virtual addresses, if the section is loaded, otherwise offsets in the file
+    fn dump_section(&self, file: &mut (impl Read + Seek), section: &Section, out: &mut impl Write) -> io::Result<()> {
+        let address = section.address.unwrap_or(section.offset);
+
+        match section.is_zero_fill {
+            true => {
+                let mut printer = Printer::new(&mut *out, address, self.config.clone());
+                printer.push_hole(section.size)?;
+                printer.finish();
+                Ok(())
+            }
+            false => self.dump_region(file, section.offset, section.size, address, out),
+        }
+    }
+
+    /// Whole file, where every section and every region between them starts a new dump.
+    /// Addresses are offsets in the file
+    fn dump_all(&self, file: &mut (impl Read + Seek), layout: &Layout, out: &mut impl Write) -> io::Result<()> {
+        let file_len = file.seek(SeekFrom::End(0))?;
+
+        let mut regions: Vec<&Section> = layout
+            .sections
+            .iter()
+            .chain(layout.structures.iter())
+            .filter(|s| !s.is_zero_fill && s.size != 0 && s.offset < file_len)
+            .collect();
+        regions.sort_by_key(|s| s.offset);
+
+        let mut position = 0;
+        for region in regions {
+            if region.offset > position {
+                self.dump_gap(file, layout, position, region.offset, out)?;
+            }
+
+            // Overlapped part is already dumped
+            let start = std::cmp::max(region.offset, position);
+            let end = std::cmp::min(region.offset.saturating_add(region.size), file_len);
+            if end <= start {
+                continue;
+            }
+
+            write!(out, "== {}: offset {start:#x}, size {:#x}", region.name, end - start)?;
+            let address = match region.address {
+                Some(address) => address.checked_add(start - region.offset),
+                None => layout.address_of(start),
+            };
+            if let Some(address) = address {
+                write!(out, ", address {address:#x}")?;
+            }
+            writeln!(out)?;
+
+            self.dump_region(file, start, end - start, start, out)?;
+            position = end;
+        }
+
+        if position < file_len {
+            self.dump_gap(file, layout, position, file_len, out)?;
+        }
+
+        Ok(())
+    }
+
+    fn dump_gap(
+        &self,
+        file: &mut (impl Read + Seek),
+        layout: &Layout,
+        start: u64,
+        end: u64,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        write!(out, "== offset {start:#x}, size {:#x}", end - start)?;
+        if let Some(address) = layout.address_of(start) {
+            write!(out, ", address {address:#x}")?;
+        }
+        writeln!(out)?;
+
+        self.dump_region(file, start, end - start, start, out)
+    }
+
+    fn dump_region(
+        &self,
+        file: &mut (impl Read + Seek),
+        offset: u64,
+        size: u64,
+        address: u64,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut printer = Printer::new(&mut *out, address, self.config.clone());
+        stream(&mut *file, &mut printer, Some(size))?;
+        printer.finish();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn section(name: &str, offset: u64, size: u64, address: Option<u64>, is_zero_fill: bool) -> Section {
+        Section {
+            name: name.to_string(),
+            offset,
+            size,
+            address,
+            is_zero_fill,
+        }
+    }
+
+    #[test]
+    fn whole_file() {
+        let layout = Layout {
+            sections: vec![
+                section(".text", 0x8, 0xc, Some(0x1008), false),
+                section(".data", 0x10, 0x8, Some(0x1010), false),
+                section(".bss", 0x18, 0x100, Some(0x1018), true),
+            ],
+            structures: vec![section("<header>", 0, 0x6, None, false)],
+            segments: vec![Segment {
+                offset: 0,
+                size: 0x18,
+                address: 0x1000,
+            }],
+        };
+        let sections = Sections {
+            files: vec![],
+            mode: SectionMode::All,
+            config: AppDumpConfig::new(
+                Some(Default::default()),
+                ByteFormatter::new(Default::default(), Groupping::RowWide(8), "", false, Separators::new("", "")),
+                None,
+                true,
+            ),
+        };
+        let mut file = Cursor::new((0u8..0x1c).collect::<Vec<u8>>());
+
+        let mut out = vec![];
+        sections.dump_all(&mut file, &layout, &mut out).unwrap();
+        let expected = "== <header>: offset 0x0, size 0x6, address 0x1000\n\
+                        00000000 000102030405....\n\
+                        00000006 \n\
+                        == offset 0x6, size 0x2, address 0x1006\n\
+                        00000006 0607............\n\
+                        00000008 \n\
+                        == .text: offset 0x8, size 0xc, address 0x1008\n\
+                        00000008 08090a0b0c0d0e0f\n\
+                        00000010 10111213........\n\
+                        00000014 \n\
+                        == .data: offset 0x14, size 0x4, address 0x1014\n\
+                        00000014 14151617........\n\
+                        00000018 \n\
+                        == offset 0x18, size 0x4\n\
+                        00000018 18191a1b........\n\
+                        0000001c \n";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let mut out = vec![];
+        sections.dump_section(&mut file, layout.section(".data").unwrap(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "00001010 1011121314151617\n00001018 \n");
+    }
+
+    #[test]
+    fn overflowing_addresses() {
+        let layout = Layout {
+            sections: vec![section(".text", 0x4, 0x8, Some(u64::MAX - 1), false)],
+            structures: vec![section("<header>", 0, 0x6, None, false)],
+            segments: vec![Segment {
+                offset: 0,
+                size: 0x10,
+                address: u64::MAX - 4,
+            }],
+        };
+        assert_eq!(layout.address_of(4), Some(u64::MAX));
+        assert_eq!(layout.address_of(5), None);
+
+        let sections = Sections {
+            files: vec![],
+            mode: SectionMode::All,
+            config: AppDumpConfig::new(
+                Some(Default::default()),
+                ByteFormatter::new(Default::default(), Groupping::RowWide(8), "", false, Separators::new("", "")),
+                None,
+                true,
+            ),
+        };
+        let mut file = Cursor::new((0u8..0x10).collect::<Vec<u8>>());
+
+        let mut out = vec![];
+        sections.dump_all(&mut file, &layout, &mut out).unwrap();
+        let expected = "== <header>: offset 0x0, size 0x6, address 0xfffffffffffffffb\n\
+                        00000000 000102030405....\n\
+                        00000006 \n\
+                        == .text: offset 0x6, size 0x6\n\
+                        00000006 060708090a0b....\n\
+                        0000000c \n\
+                        == offset 0xc, size 0x4\n\
+                        0000000c 0c0d0e0f........\n\
+                        00000010 \n";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
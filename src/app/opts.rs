@@ -1,6 +1,11 @@
 use super::result::*;
+use super::settings::*;
 use getopts::*;
-use kex::{AddressStyle, ByteStyle, Group, Groupping};
+use kex::{AddressKind, AddressStyle, ByteStyle, ByteTable, Group, Groupping};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use super::table_file::*;
 
 use super::AppError;
 
@@ -13,6 +18,10 @@ use super::AppError;
 /// -a d8 - 09254854
 ///
 /// -a o8 - 07556577
+///
+/// -a H8 - 0002AFB0
+///
+/// -a #h8 - 0x00021aa0
 pub(super) const ADDR_FORMAT_SHORT_NAME: &str = "a";
 
 /// ### Examples
@@ -28,39 +37,237 @@ pub(super) const ADDR_FORMAT_SHORT_NAME: &str = "a";
 /// -b c - . y . w
 ///
 /// -b C - ^@  y  ^A  w
+///
+/// -b H - AF B2 AA E0
+///
+/// -b x - \\xaf\\xb2\\xaa\\xe0
+///
+/// -b p - %AF%B2%AA%E0
+///
+/// -b r - b"\\xafz\\xaa\\n"
+///
+/// -b h:0x{}, - 0xaf, 0xb2, 0xaa, 0xe0,
+///
+/// -b t - bytes from `--table` file
 pub(super) const BYTE_FORMAT_SHORT_NAME: &str = "b";
 
 /// -g 2/4 - ab ac  ad ae  af b0  af b1
 /// -g 8 - ab ac ad ae af b0 af b1
+/// -g 4|2,2|8 - ab ac ad ae | af b0  af b1 | b2 b3 b4 b5 b6 b7 b8 b9
+/// -g 2:4:8 - ab ac  ad ae   af b0  af b1
 pub(super) const GROUPPING_SHORT_NAME: &str = "g";
 
 pub(super) const SKIP_SHORT_NAME: &str = "s";
 pub(super) const N_BYTES_SHORT_NAME: &str = "n";
 
+/// --profile firmware - take defaults from `[profiles.firmware]` table of the configuration file
+pub(super) const PROFILE_LONG_NAME: &str = "profile";
+pub(super) const PRINT_CONFIG_LONG_NAME: &str = "print-config";
+
+/// --placeholder "* {rows} rows" - template of the duplicated rows placeholder
+pub(super) const PLACEHOLDER_LONG_NAME: &str = "placeholder";
+
+/// --dedup-period 4 - collapse patterns, repeating with a period up to 4 rows
+pub(super) const DEDUP_PERIOD_LONG_NAME: &str = "dedup-period";
+
+/// --table font.tbl - render text column (and byte column with `-b t`) with the byte table file
+pub(super) const TABLE_LONG_NAME: &str = "table";
+
+/// --text-gutters - separate outer groups of `-g 2:8:32` in the text column
+pub(super) const TEXT_GUTTERS_LONG_NAME: &str = "text-gutters";
+
+/// --column rel:d4 - additional address column after the `-a` one
+///
+/// --column va=0x400000:#h16 - virtual address, where the first byte is at 0x400000
+///
+/// --column row - index of the row
+pub(super) const COLUMN_LONG_NAME: &str = "column";
+
+/// --proxy listen=127.0.0.1:9000,upstream=127.0.0.1:8000 - forward connections and dump their traffic
+///
+/// --proxy listen=/tmp/kex.sock,upstream=/run/app.sock - the same with Unix sockets
+pub(super) const PROXY_LONG_NAME: &str = "proxy";
+
+/// --pcap - files are pcap or pcapng captures, every packet is dumped separately
+pub(super) const PCAP_LONG_NAME: &str = "pcap";
+
+/// --skip-link - dump packets without Ethernet (or other link-layer) headers
+pub(super) const SKIP_LINK_LONG_NAME: &str = "skip-link";
+
+/// --packets 10-20 - dump only packets from 10th to 20th
+pub(super) const PACKETS_LONG_NAME: &str = "packets";
+
+/// --section .rodata - dump only the section of object file at its virtual address
+pub(super) const SECTION_LONG_NAME: &str = "section";
+
+/// --sections - dump the whole object file with a header before every section
+pub(super) const SECTIONS_LONG_NAME: &str = "sections";
+
+/// -j 4 - format with 4 threads
+pub(super) const THREADS_SHORT_NAME: &str = "j";
+
 const DEF_GROUP_SIZE: usize = 8;
 const DEF_NGROUPS: usize = 2;
 
+/// Type of the option value in the configuration file
+#[derive(Clone, Copy)]
+pub(super) enum OptKind {
+    Str,
+    /// Non-negative integer
+    Int,
+    /// Flag without value, boolean in the file
+    Flag,
+    /// Option, which may be repeated, array of strings in the file
+    Multi,
+}
+
+impl OptKind {
+    pub(super) fn description(&self) -> &'static str {
+        match self {
+            OptKind::Str => "string",
+            OptKind::Int => "non-negative integer",
+            OptKind::Flag => "boolean",
+            OptKind::Multi => "array of strings",
+        }
+    }
+}
+
+/// Option, which value may be specified in the configuration file
+pub(super) struct ConfigurableOpt {
+    pub(super) name: &'static str,
+    /// Key in the configuration file
+    pub(super) key: &'static str,
+    /// Flags are not set and repeated options are empty by default
+    pub(super) default: Option<&'static str>,
+    pub(super) kind: OptKind,
+}
+
+pub(super) const CONFIGURABLE_OPTS: [ConfigurableOpt; 17] = [
+    ConfigurableOpt {
+        name: ADDR_FORMAT_SHORT_NAME,
+        key: "address",
+        default: Some("h8"),
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: BYTE_FORMAT_SHORT_NAME,
+        key: "bytes",
+        default: Some("h"),
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: GROUPPING_SHORT_NAME,
+        key: "group",
+        default: Some("8/2"),
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: SKIP_SHORT_NAME,
+        key: "skip",
+        default: Some("0"),
+        kind: OptKind::Int,
+    },
+    ConfigurableOpt {
+        name: N_BYTES_SHORT_NAME,
+        key: "length",
+        default: None,
+        kind: OptKind::Int,
+    },
+    ConfigurableOpt {
+        name: PLACEHOLDER_LONG_NAME,
+        key: "placeholder",
+        default: None,
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: DEDUP_PERIOD_LONG_NAME,
+        key: "dedup_period",
+        default: Some("1"),
+        kind: OptKind::Int,
+    },
+    ConfigurableOpt {
+        name: TABLE_LONG_NAME,
+        key: "table",
+        default: None,
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: TEXT_GUTTERS_LONG_NAME,
+        key: "text_gutters",
+        default: None,
+        kind: OptKind::Flag,
+    },
+    ConfigurableOpt {
+        name: COLUMN_LONG_NAME,
+        key: "column",
+        default: None,
+        kind: OptKind::Multi,
+    },
+    ConfigurableOpt {
+        name: THREADS_SHORT_NAME,
+        key: "threads",
+        default: Some("1"),
+        kind: OptKind::Int,
+    },
+    ConfigurableOpt {
+        name: PROXY_LONG_NAME,
+        key: "proxy",
+        default: None,
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: PCAP_LONG_NAME,
+        key: "pcap",
+        default: None,
+        kind: OptKind::Flag,
+    },
+    ConfigurableOpt {
+        name: SKIP_LINK_LONG_NAME,
+        key: "skip_link",
+        default: None,
+        kind: OptKind::Flag,
+    },
+    ConfigurableOpt {
+        name: PACKETS_LONG_NAME,
+        key: "packets",
+        default: None,
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: SECTION_LONG_NAME,
+        key: "section",
+        default: None,
+        kind: OptKind::Str,
+    },
+    ConfigurableOpt {
+        name: SECTIONS_LONG_NAME,
+        key: "sections",
+        default: None,
+        kind: OptKind::Flag,
+    },
+];
+
 pub(super) fn get_configured_opts() -> Options {
     let mut opts = Options::new();
 
     opts.optopt(
         ADDR_FORMAT_SHORT_NAME,
         "",
-        "-a h|b|d|o[min_width]",
+        "-a [#]h|H|b|d|o[min_width]\nH - uppercase hexadecimal\n# - prefix addresses with 0x, 0b or 0o",
         "address_format",
     );
 
     opts.optopt(
         BYTE_FORMAT_SHORT_NAME,
         "",
-        &format!("-b h|b|d|o|c|C\nh - hexadecimal\nb - binary\nd - decimal\no - octal\nc - ASCII characters\ncaret notation with ASCII characters"),
-        "byte_format",
+        "-b h|H|b|d|o|c|C|x|p|r|t[:template]\nh - hexadecimal\nH - uppercase hexadecimal\nb - binary\nd - decimal\no - octal\nc - ASCII characters\nC - caret notation with ASCII characters\nx - C escapes: \\xde\np - percent-encoding: %DE\nr - Rust byte string literals\nt - entries of --table file\ntemplate surrounds every byte, like 0x{},",
+        "byte_format[:template]",
     );
 
     opts.optopt(
         GROUPPING_SHORT_NAME,
         "",
-        "-g 4/4\n",
+        "-g 4/4\nor -g 4|2,2|8 - groups of the row, separated by ` | ` or double spaces\nor -g 2:8:32 - nested groups of 2 and 8 bytes in the row of 32 bytes",
         "group_size[/num_of_groups]",
     );
 
@@ -78,26 +285,120 @@ pub(super) fn get_configured_opts() -> Options {
         "POSITIVE_INTEGER",
     );
 
+    opts.optopt(
+        "",
+        PLACEHOLDER_LONG_NAME,
+        "Template of duplicated rows placeholder.\n{rows}, {bytes}, {start}, {end}, {period} and {repeats} are replaced by the collapsed region values",
+        "TEMPLATE",
+    );
+
+    opts.optopt(
+        "",
+        DEDUP_PERIOD_LONG_NAME,
+        "Maximum period of repeating pattern in rows. Defaults to 1 - only identical consecutive rows are collapsed",
+        "ROWS",
+    );
+
+    opts.optopt(
+        "",
+        TABLE_LONG_NAME,
+        "Byte table file with `byte=representation` lines. Used by the text column and by `-b t`",
+        "PATH",
+    );
+
+    opts.optflag(
+        "",
+        TEXT_GUTTERS_LONG_NAME,
+        "Separate outer groups of nested groupping in the text column",
+    );
+
+    opts.optmulti(
+        "",
+        COLUMN_LONG_NAME,
+        "Additional address column. May be repeated\nabs - absolute address\nrel - offset from the first byte\nva=BASE - BASE plus offset from the first byte\nrow - index of the row\nformat is the same as of -a",
+        "abs|rel|row|va=BASE[:format]",
+    );
+
+    opts.optopt(
+        THREADS_SHORT_NAME,
+        "",
+        "Number of formatting threads. Output is the same for any number",
+        "POSITIVE_INTEGER",
+    );
+
+    opts.optopt(
+        "",
+        PROXY_LONG_NAME,
+        "Forward connections from `listen` to `upstream` and dump traffic of both directions.\nAddresses are host:port or Unix socket paths, containing /",
+        "listen=ADDR,upstream=ADDR",
+    );
+
+    opts.optflag(
+        "",
+        PCAP_LONG_NAME,
+        "Input is pcap or pcapng capture. Every packet is dumped after the header line, addresses are offsets in the packet",
+    );
+
+    opts.optflag(
+        "",
+        SKIP_LINK_LONG_NAME,
+        "Skip link-layer headers of packets (Ethernet, Linux cooked capture, BSD loopback)",
+    );
+
+    opts.optopt(
+        "",
+        PACKETS_LONG_NAME,
+        "Dump only packets in the range. Indexes start from 1",
+        "FIRST[-LAST]",
+    );
+
+    opts.optopt(
+        "",
+        SECTION_LONG_NAME,
+        "Dump only the section of ELF, PE or Mach-O file. Addresses are virtual addresses of loaded sections",
+        "NAME",
+    );
+
+    opts.optflag(
+        "",
+        SECTIONS_LONG_NAME,
+        "Dump the whole ELF, PE or Mach-O file with a header before every section and every region between them",
+    );
+
+    opts.optopt(
+        "",
+        PROFILE_LONG_NAME,
+        "Profile from the configuration file",
+        "NAME",
+    );
+
+    opts.optflag(
+        "",
+        PRINT_CONFIG_LONG_NAME,
+        "Print effective configuration and exit",
+    );
+
     opts
 }
 
-pub(super) trait FromMatches {
-    fn new(matches: &Matches) -> AppResult<Self>
+pub(super) trait FromSettings {
+    fn new(settings: &Settings) -> AppResult<Self>
     where
         Self: Sized;
 }
 
-impl FromMatches for AddressStyle {
-    fn new(matches: &Matches) -> AppResult<Self>
+/// Address style with optional radix prefix
+pub(super) struct AddressFormat {
+    pub(super) style: AddressStyle,
+    pub(super) prefix: &'static str,
+}
+
+impl FromSettings for AddressFormat {
+    fn new(settings: &Settings) -> AppResult<Self>
     where
         Self: Sized,
     {
-        let fmt_str = match matches.opt_get_default(ADDR_FORMAT_SHORT_NAME, "h8".to_string()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(AppError::new(format!("{e}")));
-            }
-        };
+        let fmt_str = settings.opt_str(ADDR_FORMAT_SHORT_NAME);
 
         Self::from_arg_str(fmt_str)
     }
@@ -109,15 +410,23 @@ trait FromArgStr {
         Self: Sized;
 }
 
-impl FromArgStr for AddressStyle {
+impl FromArgStr for AddressFormat {
     fn from_arg_str(fmt_str: String) -> AppResult<Self> {
+        const PREFIX_CHAR: char = '#';
+
+        let (with_prefix, fmt_str) = match fmt_str.strip_prefix(PREFIX_CHAR) {
+            Some(rem) => (true, rem),
+            None => (false, fmt_str.as_str()),
+        };
+
         let mut fmt_chars = fmt_str.chars();
-        let fmt_name = fmt_chars
-            .next()
-            .expect("That's not possible to have an empty argument");
+        let fmt_name = match fmt_chars.next() {
+            Some(c) => c,
+            None => return Err(AppError::new("Empty address format".to_string())),
+        };
 
         let rem = String::from_iter(fmt_chars);
-        let min_width = if rem.len() != 0 {
+        let min_width = if !rem.is_empty() {
             match rem.parse::<usize>() {
                 Ok(i) => i,
                 Err(e) => {
@@ -128,74 +437,186 @@ impl FromArgStr for AddressStyle {
             8
         };
 
-        match fmt_name {
-            'h' => Ok(AddressStyle::Hex(min_width)),
-            'b' => Ok(AddressStyle::Bin(min_width)),
-            'd' => Ok(AddressStyle::Dec(min_width)),
-            'o' => Ok(AddressStyle::Oct(min_width)),
+        let (style, prefix) = match fmt_name {
+            'h' => (AddressStyle::Hex(min_width), "0x"),
+            'H' => (AddressStyle::UpperHex(min_width), "0x"),
+            'b' => (AddressStyle::Bin(min_width), "0b"),
+            'd' => (AddressStyle::Dec(min_width), ""),
+            'o' => (AddressStyle::Oct(min_width), "0o"),
             _ => {
-                return Err(AppError::new(format!("{fmt_name}: Unknown address format")));
+                return Err(AppError::new(format!("{fmt_name}: Unknown address format")))
             }
+        };
+
+        if let Err(e) = style.validate() {
+            return Err(AppError::new(format!("{fmt_str}: {e}")));
         }
+
+        Ok(Self {
+            style,
+            prefix: if with_prefix { prefix } else { "" },
+        })
     }
 }
 
-impl FromMatches for ByteStyle {
-    fn new(matches: &Matches) -> AppResult<Self>
-    where
-        Self: Sized,
-    {
-        let fmt_str = match matches.opt_get_default(BYTE_FORMAT_SHORT_NAME, "h".to_string()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(AppError::new(format!("{e}")));
-            }
+/// Additional address column from `--column`
+pub(super) struct AddressColumnFormat {
+    pub(super) kind: AddressKind,
+    pub(super) format: AddressFormat,
+}
+
+impl AddressColumnFormat {
+    pub(super) fn all(settings: &Settings) -> AppResult<Vec<Self>> {
+        settings
+            .opt_strs(COLUMN_LONG_NAME)
+            .into_iter()
+            .map(Self::from_arg_str)
+            .collect()
+    }
+}
+
+impl FromArgStr for AddressColumnFormat {
+    fn from_arg_str(fmt_str: String) -> AppResult<Self> {
+        const BASE_DELIMITER: char = '=';
+
+        let (kind_str, format) = match fmt_str.split_once(':') {
+            Some((k, f)) => (k, AddressFormat::from_arg_str(f.to_string())?),
+            None => (
+                fmt_str.as_str(),
+                AddressFormat::from_arg_str(String::from("h8"))?,
+            ),
         };
 
-        Self::from_arg_str(fmt_str)
+        let kind = match kind_str.split_once(BASE_DELIMITER) {
+            Some(("va", base)) => AddressKind::Based(parse_base(base)?),
+            Some(_) => return Err(AppError::new(format!("{kind_str}: Unknown column"))),
+            None => match kind_str {
+                "abs" => AddressKind::Absolute,
+                "rel" => AddressKind::Relative,
+                "row" => AddressKind::RowIndex,
+                _ => return Err(AppError::new(format!("{kind_str}: Unknown column"))),
+            },
+        };
+
+        Ok(Self { kind, format })
     }
 }
 
-impl FromArgStr for ByteStyle {
-    fn from_arg_str(fmt_str: String) -> AppResult<Self>
+/// Hexadecimal with `0x` prefix or decimal
+fn parse_base(base: &str) -> AppResult<u64> {
+    let parsed = match base.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => base.parse::<u64>(),
+    };
+
+    parsed.map_err(|e| AppError::new(format!("{base}: {e}")))
+}
+
+/// Byte style with text around every byte
+pub(super) struct ByteFormat {
+    pub(super) style: ByteStyle,
+    pub(super) prefix: String,
+    pub(super) suffix: String,
+    /// Table from `--table`, used by the text column
+    pub(super) table: Option<ByteTable>,
+}
+
+impl ByteFormat {
+    /// Escaped bytes are not separated, like in the source code
+    pub(super) fn byte_separator(&self) -> &'static str {
+        match self.style {
+            ByteStyle::CEscape | ByteStyle::Percent | ByteStyle::RustLiteral => "",
+            _ => " ",
+        }
+    }
+}
+
+impl FromSettings for ByteFormat {
+    fn new(settings: &Settings) -> AppResult<Self>
     where
         Self: Sized,
     {
-        let mut fmt_chars = fmt_str.chars();
-        let fmt_name = fmt_chars
-            .next()
-            .expect("That's not possible to have an empty argument");
-
-        match fmt_name {
-            'h' => Ok(Self::Hex),
-            'b' => Ok(Self::Bin),
-            'd' => Ok(Self::Dec),
-            'o' => Ok(Self::Oct),
-            'c' => Ok(Self::Ascii),
-            'C' => Ok(Self::CaretAscii),
+        let fmt_str = settings.opt_str(BYTE_FORMAT_SHORT_NAME);
+
+        Self::parse(&fmt_str, load_configured_table(settings)?)
+    }
+}
+
+impl ByteFormat {
+    fn parse(fmt_str: &str, table: Option<ByteTable>) -> AppResult<Self> {
+        const TEMPLATE_DELIMITER: char = ':';
+        const BYTE_MARKER: &str = "{}";
+
+        let (fmt_name, template) = match fmt_str.split_once(TEMPLATE_DELIMITER) {
+            Some((name, template)) => (name, Some(template)),
+            None => (fmt_str, None),
+        };
+
+        let style = match fmt_name {
+            "h" => ByteStyle::Hex,
+            "H" => ByteStyle::UpperHex,
+            "b" => ByteStyle::Bin,
+            "d" => ByteStyle::Dec,
+            "o" => ByteStyle::Oct,
+            "c" => ByteStyle::Ascii,
+            "C" => ByteStyle::CaretAscii,
+            "x" => ByteStyle::CEscape,
+            "p" => ByteStyle::Percent,
+            "r" => ByteStyle::RustLiteral,
+            "t" => match &table {
+                Some(table) => ByteStyle::Table(table.clone()),
+                None => {
+                    return Err(AppError::new(format!(
+                        "{fmt_name}: Byte format requires --{TABLE_LONG_NAME}"
+                    )))
+                }
+            },
             _ => {
-                return Err(AppError::new(format!("{fmt_name}: Unknown address format")));
+                return Err(AppError::new(format!("{fmt_name}: Unknown byte format")))
             }
-        }
+        };
+
+        let (prefix, suffix) = match template {
+            Some(template) => match template.split_once(BYTE_MARKER) {
+                Some((prefix, suffix)) if !suffix.contains(BYTE_MARKER) => (prefix, suffix),
+                _ => {
+                    return Err(AppError::new(format!(
+                        "{template}: Byte template must contain single {BYTE_MARKER}"
+                    )))
+                }
+            },
+            None => ("", ""),
+        };
+
+        Ok(Self {
+            style,
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            table,
+        })
     }
 }
 
-impl FromMatches for Groupping {
-    fn new(matches: &Matches) -> AppResult<Self>
+/// Byte table file is optional
+fn load_configured_table(settings: &Settings) -> AppResult<Option<ByteTable>> {
+    match settings.opt_get::<String>(TABLE_LONG_NAME)? {
+        Some(path) => Ok(Some(load_table(Path::new(&path))?)),
+        None => Ok(None),
+    }
+}
+
+impl FromSettings for Groupping {
+    fn new(settings: &Settings) -> AppResult<Self>
     where
         Self: Sized,
     {
-        let fmt_str = match matches.opt_get_default(
-            GROUPPING_SHORT_NAME,
-            format!("{DEF_GROUP_SIZE}/{DEF_NGROUPS}"),
-        ) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(AppError::new(format!("{e}")));
-            }
-        };
+        let fmt_str = settings.opt_str(GROUPPING_SHORT_NAME);
 
-        Self::from_arg_str(fmt_str)
+        let groupping = Self::from_arg_str(fmt_str.clone())?;
+        match groupping.validate() {
+            Ok(_) => Ok(groupping),
+            Err(e) => Err(AppError::new(format!("{fmt_str}: {e}"))),
+        }
     }
 }
 
@@ -206,7 +627,11 @@ impl FromArgStr for Groupping {
     {
         const DELIMITER_CHAR: char = '/';
 
-        if fmt_str.contains(DELIMITER_CHAR) {
+        if fmt_str.contains(NESTED_DELIMITER) {
+            parse_nested_groups(&fmt_str)
+        } else if fmt_str.contains(|c| pattern_separator(c).is_some()) {
+            parse_group_pattern(&fmt_str)
+        } else if fmt_str.contains(DELIMITER_CHAR) {
             let mut split = fmt_str.split(DELIMITER_CHAR);
             let group_size = split
                 .next()
@@ -244,38 +669,200 @@ impl FromArgStr for Groupping {
             match fmt_str.parse::<usize>() {
                 Ok(i) => Ok(Groupping::RowWide(i)),
                 Err(e) => {
-                    return Err(AppError::new(format!("{e}")));
+                    Err(AppError::new(format!("{e}")))
                 }
             }
         }
     }
 }
 
+/// Separator after group in `-g 4|2,2|8`
+fn pattern_separator(delimiter: char) -> Option<&'static str> {
+    match delimiter {
+        ',' => Some("  "),
+        '|' => Some(" | "),
+        _ => None,
+    }
+}
+
+fn parse_group_pattern(fmt_str: &str) -> AppResult<Groupping> {
+    let mut groups = vec![];
+
+    let mut size_start = 0;
+    let delimiters = fmt_str
+        .char_indices()
+        .filter_map(|(idx, c)| pattern_separator(c).map(|sep| (idx, c.len_utf8(), sep)))
+        .chain([(fmt_str.len(), 0, "")]);
+
+    for (idx, len, separator) in delimiters {
+        let size = match fmt_str[size_start..idx].parse::<usize>() {
+            Ok(i) => i,
+            Err(e) => {
+                return Err(AppError::new(format!("{fmt_str}: {e}")));
+            }
+        };
+
+        groups.push(Group::new(size, separator));
+        size_start = idx + len;
+    }
+
+    Ok(Groupping::Pattern(groups))
+}
+
+const NESTED_DELIMITER: char = ':';
+
+/// `-g 2:8:32`. Separators are widened by a space on every level
+fn parse_nested_groups(fmt_str: &str) -> AppResult<Groupping> {
+    let mut sizes = vec![];
+    for size in fmt_str.split(NESTED_DELIMITER) {
+        match size.parse::<usize>() {
+            Ok(0) => {
+                return Err(AppError::new(format!("{fmt_str}: Group size must be positive")));
+            }
+            Ok(i) => sizes.push(i),
+            Err(e) => {
+                return Err(AppError::new(format!("{fmt_str}: {e}")));
+            }
+        }
+    }
+
+    if sizes.windows(2).any(|w| !w[1].is_multiple_of(w[0])) {
+        return Err(AppError::new(format!(
+            "{fmt_str}: Size of every level must be a multiple of the previous one"
+        )));
+    }
+
+    let row = sizes.pop().unwrap_or_default();
+    let outer_size = match sizes.last() {
+        Some(s) => *s,
+        None => return Ok(Groupping::RowWide(row)),
+    };
+
+    let levels = sizes
+        .iter()
+        .enumerate()
+        .map(|(idx, size)| Group::new(*size, &" ".repeat(idx + 2)))
+        .collect();
+
+    Ok(Groupping::Nested(levels, row / outer_size))
+}
+
 #[derive(Clone)]
 pub(crate) struct ContentRange {
-    pub(crate) skip: usize,
-    pub(crate) len: Option<usize>,
+    pub(crate) skip: u64,
+    pub(crate) len: Option<u64>,
 }
 
-impl FromMatches for ContentRange {
-    fn new(matches: &Matches) -> AppResult<Self>
+impl FromSettings for ContentRange {
+    fn new(settings: &Settings) -> AppResult<Self>
     where
         Self: Sized,
     {
-        let skip = match matches.opt_get_default(SKIP_SHORT_NAME, 0) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(AppError::new(format!("{e}")));
+        let skip = settings.opt_get(SKIP_SHORT_NAME)?.unwrap_or_default();
+        let len: Option<u64> = settings.opt_get(N_BYTES_SHORT_NAME)?;
+
+        Ok(Self { skip, len })
+    }
+}
+
+/// Socket address of `--proxy`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Endpoint {
+    /// `host:port`
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "{addr}"),
+            Endpoint::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl FromArgStr for Endpoint {
+    fn from_arg_str(fmt_str: String) -> AppResult<Self> {
+        if fmt_str.contains('/') {
+            if cfg!(unix) {
+                Ok(Endpoint::Unix(PathBuf::from(fmt_str)))
+            } else {
+                Err(AppError::new(format!("{fmt_str}: Unix sockets are not supported")))
             }
-        };
+        } else if fmt_str.contains(':') {
+            Ok(Endpoint::Tcp(fmt_str))
+        } else {
+            Err(AppError::new(format!("{fmt_str}: Address must be host:port or a Unix socket path")))
+        }
+    }
+}
 
-        let len: Option<usize> = match matches.opt_get(N_BYTES_SHORT_NAME) {
-            Ok(val) => val,
-            Err(e) => {
-                return Err(AppError::new(format!("{e}")));
+/// Addresses of `--proxy`
+pub(crate) struct ProxyAddrs {
+    pub(crate) listen: Endpoint,
+    pub(crate) upstream: Endpoint,
+}
+
+impl ProxyAddrs {
+    /// `None` without `--proxy`
+    pub(super) fn new(settings: &Settings) -> AppResult<Option<Self>> {
+        match settings.opt_get::<String>(PROXY_LONG_NAME)? {
+            Some(s) => Self::from_arg_str(s).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl FromArgStr for ProxyAddrs {
+    fn from_arg_str(fmt_str: String) -> AppResult<Self> {
+        let mut listen = None;
+        let mut upstream = None;
+
+        for pair in fmt_str.split(',') {
+            let (slot, value) = match pair.split_once('=') {
+                Some(("listen", value)) => (&mut listen, value),
+                Some(("upstream", value)) => (&mut upstream, value),
+                _ => return Err(AppError::new(format!("{pair}: Expected listen=ADDR or upstream=ADDR"))),
+            };
+
+            if slot.is_some() {
+                return Err(AppError::new(format!("{pair}: Address is repeated")));
             }
-        };
+            *slot = Some(Endpoint::from_arg_str(value.to_string())?);
+        }
 
-        Ok(Self { skip, len })
+        match (listen, upstream) {
+            (Some(listen), Some(upstream)) => Ok(Self { listen, upstream }),
+            _ => Err(AppError::new(format!("{fmt_str}: Both listen and upstream addresses are required"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_addrs() {
+        let addrs = ProxyAddrs::from_arg_str("listen=127.0.0.1:9000,upstream=localhost:8000".to_string()).unwrap();
+        assert_eq!(addrs.listen, Endpoint::Tcp("127.0.0.1:9000".to_string()));
+        assert_eq!(addrs.upstream, Endpoint::Tcp("localhost:8000".to_string()));
+
+        #[cfg(unix)]
+        {
+            let addrs = ProxyAddrs::from_arg_str("upstream=/run/app.sock,listen=./kex.sock".to_string()).unwrap();
+            assert_eq!(addrs.listen, Endpoint::Unix(PathBuf::from("./kex.sock")));
+            assert_eq!(addrs.upstream, Endpoint::Unix(PathBuf::from("/run/app.sock")));
+        }
+
+        for invalid in [
+            "listen=127.0.0.1:9000",
+            "listen=127.0.0.1:9000,upstream=8000",
+            "listen=127.0.0.1:9000,listen=127.0.0.1:9001,upstream=127.0.0.1:8000",
+            "from=127.0.0.1:9000,upstream=127.0.0.1:8000",
+        ] {
+            assert!(ProxyAddrs::from_arg_str(invalid.to_string()).is_err(), "{invalid}");
+        }
     }
 }
@@ -0,0 +1,271 @@
+//! Layout of PE images, PE32 and PE32+
+
+use std::io::{self, Read, Seek};
+
+use super::object::*;
+
+pub(super) const MAGIC: &[u8] = b"MZ";
+
+const SIGNATURE: &[u8] = b"PE\0\0";
+const PE32: u16 = 0x10b;
+const PE32_PLUS: u16 = 0x20b;
+const SECTION_HEADER_LEN: u64 = 40;
+/// Length of COFF symbol, the string table follows the symbol table
+const SYMBOL_LEN: u64 = 18;
+/// Index of the certificate table, which is located by the file offset instead of RVA
+const CERTIFICATE_TABLE: usize = 4;
+
+const DIRECTORIES: [&str; 16] = [
+    "<export table>",
+    "<import table>",
+    "<resource table>",
+    "<exception table>",
+    "<certificate table>",
+    "<base relocation table>",
+    "<debug>",
+    "<architecture>",
+    "<global ptr>",
+    "<TLS table>",
+    "<load config table>",
+    "<bound import>",
+    "<IAT>",
+    "<delay import descriptor>",
+    "<CLR runtime header>",
+    "<reserved>",
+];
+
+/// Section header, addresses are RVAs
+struct SectionHeader {
+    name: String,
+    virtual_size: u64,
+    virtual_address: u64,
+    raw_size: u64,
+    raw_offset: u64,
+}
+
+pub(super) fn parse(file: &mut (impl Read + Seek)) -> io::Result<Layout> {
+    // PE is always little endian
+    let e = Endian { big: false };
+
+    let dos_header = read_at(file, 0, 0x40)?;
+    let pe = e.u32(&dos_header[0x3c..]) as u64;
+
+    let coff = read_at(file, pe, 24)?;
+    if &coff[..4] != SIGNATURE {
+        return Err(invalid("Invalid PE signature"));
+    }
+    let sections_num = e.u16(&coff[6..]) as u64;
+    let symbols_offset = e.u32(&coff[12..]) as u64;
+    let symbols_num = e.u32(&coff[16..]) as u64;
+    let optional_len = e.u16(&coff[20..]) as u64;
+
+    let optional = read_at(file, pe + 24, optional_len)?;
+    if optional.len() < 2 {
+        return Err(invalid("PE optional header is missing"));
+    }
+    let (image_base, directories_num_at) = match e.u16(&optional) {
+        PE32 if optional.len() >= 96 => (e.u32(&optional[28..]) as u64, 92),
+        PE32_PLUS if optional.len() >= 112 => (e.u64(&optional[24..]), 108),
+        _ => return Err(invalid("Invalid PE optional header")),
+    };
+    let headers_len = e.u32(&optional[60..]) as u64;
+
+    let section_table = pe + 24 + optional_len;
+    let table = read_at(file, section_table, sections_num * SECTION_HEADER_LEN)?;
+    let strings = match symbols_offset {
+        0 => vec![],
+        offset => string_table(file, offset + symbols_num * SYMBOL_LEN).unwrap_or_default(),
+    };
+
+    let headers: Vec<SectionHeader> = table
+        .chunks_exact(SECTION_HEADER_LEN as usize)
+        .map(|header| SectionHeader {
+            name: section_name(&header[..8], &strings),
+            virtual_size: e.u32(&header[8..]) as u64,
+            virtual_address: e.u32(&header[12..]) as u64,
+            raw_size: e.u32(&header[16..]) as u64,
+            raw_offset: e.u32(&header[20..]) as u64,
+        })
+        .collect();
+
+    let mut layout = Layout {
+        sections: vec![],
+        structures: vec![
+            structure("<DOS header>", 0, 0x40),
+            structure("<PE header>", pe, 24),
+            structure("<optional header>", pe + 24, optional_len),
+            structure("<section headers>", section_table, sections_num * SECTION_HEADER_LEN),
+        ],
+        segments: vec![Segment {
+            offset: 0,
+            size: headers_len,
+            address: image_base,
+        }],
+    };
+
+    for header in headers.iter() {
+        // Virtual size is the size of data, raw size is rounded up to the file alignment
+        let size = match header.virtual_size {
+            0 => header.raw_size,
+            virtual_size if header.raw_size == 0 => virtual_size,
+            virtual_size => std::cmp::min(virtual_size, header.raw_size),
+        };
+        let address = image_base.wrapping_add(header.virtual_address);
+
+        layout.sections.push(Section {
+            name: header.name.clone(),
+            offset: header.raw_offset,
+            size,
+            address: Some(address),
+            is_zero_fill: header.raw_size == 0,
+        });
+        if header.raw_size != 0 {
+            layout.segments.push(Segment {
+                offset: header.raw_offset,
+                size: header.raw_size,
+                address,
+            });
+        }
+    }
+
+    let directories_num = std::cmp::min(e.u32(&optional[directories_num_at..]) as usize, DIRECTORIES.len());
+    let directories = optional.get(directories_num_at + 4..).unwrap_or_default();
+    for (index, directory) in directories.chunks_exact(8).take(directories_num).enumerate() {
+        let rva = e.u32(directory) as u64;
+        let size = e.u32(&directory[4..]) as u64;
+        if rva == 0 || size == 0 {
+            continue;
+        }
+
+        let name = DIRECTORIES[index];
+        if index == CERTIFICATE_TABLE {
+            layout.structures.push(structure(name, rva, size));
+            continue;
+        }
+
+        if let Some(offset) = offset_of(&headers, headers_len, rva) {
+            layout.structures.push(Section {
+                address: Some(image_base.wrapping_add(rva)),
+                ..structure(name, offset, size)
+            });
+        }
+    }
+
+    Ok(layout)
+}
+
+/// File offset of RVA
+fn offset_of(headers: &[SectionHeader], headers_len: u64, rva: u64) -> Option<u64> {
+    if rva < headers_len {
+        return Some(rva);
+    }
+
+    headers
+        .iter()
+        .find(|h| rva >= h.virtual_address && rva - h.virtual_address < h.raw_size)
+        .map(|h| h.raw_offset + (rva - h.virtual_address))
+}
+
+/// Long names are stored in the string table as `/offset`
+fn section_name(name: &[u8], strings: &[u8]) -> String {
+    let name = c_string(name);
+
+    match name.strip_prefix('/').and_then(|offset| offset.parse::<usize>().ok()) {
+        Some(offset) if offset < strings.len() => c_string(&strings[offset..]),
+        _ => name,
+    }
+}
+
+/// COFF string table. Its length includes the length field itself
+fn string_table(file: &mut (impl Read + Seek), offset: u64) -> io::Result<Vec<u8>> {
+    let len = Endian { big: false }.u32(&read_at(file, offset, 4)?) as u64;
+
+    read_at(file, offset, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// PE32+ image with `.text`, `.rdata`, `.bss` and `.debug_info` (long name) sections
+    fn pe() -> Vec<u8> {
+        let mut pe = vec![0u8; 0x80];
+        pe[..2].copy_from_slice(MAGIC);
+        pe[0x3c] = 0x80;
+
+        pe.extend(SIGNATURE);
+        pe.extend(0x8664u16.to_le_bytes());
+        pe.extend(4u16.to_le_bytes());
+        pe.extend(0u32.to_le_bytes());
+        pe.extend(0x620u32.to_le_bytes());
+        pe.extend(0u32.to_le_bytes());
+        pe.extend(240u16.to_le_bytes());
+        pe.extend(0x22u16.to_le_bytes());
+
+        let mut optional = vec![0u8; 240];
+        optional[..2].copy_from_slice(&PE32_PLUS.to_le_bytes());
+        optional[24..32].copy_from_slice(&0x140000000u64.to_le_bytes());
+        optional[60..64].copy_from_slice(&0x200u32.to_le_bytes());
+        optional[108..112].copy_from_slice(&16u32.to_le_bytes());
+        // Import table in `.rdata`
+        optional[120..124].copy_from_slice(&0x2010u32.to_le_bytes());
+        optional[124..128].copy_from_slice(&0x28u32.to_le_bytes());
+        // Certificate table at the end of file
+        optional[144..148].copy_from_slice(&0x600u32.to_le_bytes());
+        optional[148..152].copy_from_slice(&0x10u32.to_le_bytes());
+        pe.extend(optional);
+
+        let section = |name: &[u8], virtual_size: u32, virtual_address: u32, raw_size: u32, raw_offset: u32| {
+            let mut header = name.to_vec();
+            header.resize(8, 0);
+            for v in [virtual_size, virtual_address, raw_size, raw_offset, 0, 0, 0, 0] {
+                header.extend(v.to_le_bytes());
+            }
+            header
+        };
+        pe.extend(section(b".text", 0x1f0, 0x1000, 0x200, 0x200));
+        pe.extend(section(b".rdata", 0x80, 0x2000, 0x200, 0x400));
+        pe.extend(section(b".bss", 0x100, 0x3000, 0, 0));
+        pe.extend(section(b"/4", 0x10, 0x4000, 0x10, 0x610));
+
+        pe.resize(0x600, 0);
+        pe.extend([0xccu8; 0x10]);
+        pe.extend([0x11u8; 0x10]);
+        // String table after no symbols
+        pe.extend(16u32.to_le_bytes());
+        pe.extend(b".debug_info\0");
+        pe
+    }
+
+    #[test]
+    fn sections_and_directories() {
+        let layout = parse(&mut Cursor::new(pe())).unwrap();
+
+        let sections: Vec<_> = layout
+            .sections
+            .iter()
+            .map(|s| (s.name.as_str(), s.offset, s.size, s.address, s.is_zero_fill))
+            .collect();
+        let expected = [
+            (".text", 0x200, 0x1f0, Some(0x140001000), false),
+            (".rdata", 0x400, 0x80, Some(0x140002000), false),
+            (".bss", 0, 0x100, Some(0x140003000), true),
+            (".debug_info", 0x610, 0x10, Some(0x140004000), false),
+        ];
+        assert_eq!(sections, expected);
+
+        let import = layout.section("<import table>").unwrap();
+        assert_eq!((import.offset, import.size, import.address), (0x410, 0x28, Some(0x140002010)));
+        let certificate = layout.section("<certificate table>").unwrap();
+        assert_eq!((certificate.offset, certificate.address), (0x600, None));
+
+        assert_eq!(layout.address_of(0x3c), Some(0x14000003c));
+        assert_eq!(layout.address_of(0x5ff), Some(0x1400021ff));
+        assert_eq!(layout.address_of(0x600), None);
+
+        let mut invalid = pe();
+        invalid[0x81] = b'X';
+        assert!(parse(&mut Cursor::new(invalid)).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! Forwarding of local connections with the dump of their traffic
+
+use std::io::{self, stdout, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::*;
+
+/// Delay after the first failed `accept`, which is doubled by every consequent failure
+const MIN_ACCEPT_DELAY: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_DELAY: Duration = Duration::from_secs(1);
+
+/// Proxy of `--proxy`. Every accepted connection is forwarded to a new upstream connection.
+///
+/// Traffic of each connection is dumped as [`Conversation`], labeled with the connection id:
+/// `>` for bytes, sent by the client, `<` for bytes, received from upstream
+pub(crate) struct Proxy {
+    addrs: ProxyAddrs,
+    config: AppDumpConfig,
+}
+
+impl Proxy {
+    pub(super) fn new(addrs: ProxyAddrs, config: AppDumpConfig) -> Self {
+        Self { addrs, config }
+    }
+
+    /// Serves connections until the process is terminated
+    pub(crate) fn run(self) -> AppResult<()> {
+        let listener = match Listener::bind(&self.addrs.listen) {
+            Ok(l) => l,
+            Err(e) => return Err(AppError::new(format!("{}: {e}", self.addrs.listen))),
+        };
+
+        #[cfg(target_os = "linux")]
+        if let Endpoint::Unix(path) = &self.addrs.listen {
+            remove_on_termination(path);
+        }
+
+        eprintln!("Forwarding {} to {}", self.addrs.listen, self.addrs.upstream);
+        self.serve(listener, stdout)
+    }
+
+    /// `out` gives the output for every connection
+    fn serve<O: Write + Send + 'static>(&self, listener: Listener, out: impl Fn() -> O) -> AppResult<()> {
+        let mut id = 0u64;
+        let mut delay = Duration::ZERO;
+
+        loop {
+            let (client, peer) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    eprintln!("{}: {e}", self.addrs.listen);
+                    delay = accept_delay(&e, delay);
+                    thread::sleep(delay);
+                    continue;
+                }
+            };
+            delay = Duration::ZERO;
+
+            id += 1;
+            let upstream = self.addrs.upstream.clone();
+            let config = self.config.clone();
+            let (events, dump) = (out(), out());
+
+            thread::spawn(move || handle_connection(id, client, &peer, &upstream, config, events, dump));
+        }
+    }
+}
+
+/// Delay before the next `accept` after `error`. Errors of single connections are retried at once,
+/// other ones, like exhausted file descriptors, would repeat until connections are closed
+fn accept_delay(error: &io::Error, previous: Duration) -> Duration {
+    use io::ErrorKind::*;
+
+    match error.kind() {
+        ConnectionAborted | ConnectionReset | Interrupted => Duration::ZERO,
+        _ => (previous * 2).clamp(MIN_ACCEPT_DELAY, MAX_ACCEPT_DELAY),
+    }
+}
+
+fn handle_connection<O: Write + Send + 'static>(
+    id: u64,
+    client: Connection,
+    peer: &str,
+    upstream: &Endpoint,
+    config: AppDumpConfig,
+    mut events: O,
+    dump: O,
+) {
+    let label = format!("#{id}");
+
+    let upstream = match Connection::connect(upstream) {
+        Ok(c) => c,
+        Err(e) => {
+            _ = writeln!(events, "{label} {peer}: {upstream}: {e}");
+            return;
+        }
+    };
+    _ = writeln!(events, "{label} connected {peer}");
+
+    let conversation = Conversation::new(dump, config).with_label(&label);
+    if let Err(e) = forward_both(client, upstream, conversation) {
+        _ = writeln!(events, "{label} {e}");
+    }
+
+    _ = writeln!(events, "{label} closed");
+}
+
+/// Forwards both directions, until both are closed
+fn forward_both<O: Write + Send + 'static>(
+    client: Connection,
+    upstream: Connection,
+    conversation: Conversation<O, AddressFormatter, ByteFormatter, CharFormatter>,
+) -> io::Result<()> {
+    let conversation = Arc::new(Mutex::new(conversation));
+
+    let client_input = client.try_clone()?;
+    let upstream_output = upstream.try_clone()?;
+    let requests = InspectWriter::new(upstream.try_clone()?, conversation.clone());
+    let sending = thread::spawn(move || forward(client_input, requests, &upstream_output));
+
+    let responses = InspectReader::new(upstream, conversation);
+    forward(responses, client.try_clone()?, &client);
+
+    match sending.join() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(io::Error::other("Forwarding thread panicked")),
+    }
+}
+
+/// Copies one direction, then closes it at `destination`.
+/// Failure of either side, like reset of the connection, closes both directions
+fn forward(mut input: impl Read, mut output: impl Write, destination: &Connection) {
+    let how = match io::copy(&mut input, &mut output) {
+        Ok(_) => Shutdown::Write,
+        Err(_) => Shutdown::Both,
+    };
+
+    _ = destination.shutdown(how);
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn connect(endpoint: &Endpoint) -> io::Result<Self> {
+        match endpoint {
+            Endpoint::Tcp(addr) => TcpStream::connect(addr.as_str()).map(Connection::Tcp),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => UnixStream::connect(path).map(Connection::Unix),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err(io::ErrorKind::Unsupported.into()),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Connection::Tcp(s) => s.try_clone().map(Connection::Tcp),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.try_clone().map(Connection::Unix),
+        }
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Connection::Tcp(s) => s.shutdown(how),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.shutdown(how),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.flush(),
+        }
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(SocketListener),
+}
+
+impl Listener {
+    fn bind(endpoint: &Endpoint) -> io::Result<Self> {
+        match endpoint {
+            Endpoint::Tcp(addr) => TcpListener::bind(addr.as_str()).map(Listener::Tcp),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                remove_stale_socket(path)?;
+                let listener = UnixListener::bind(path)?;
+
+                Ok(Listener::Unix(SocketListener {
+                    listener,
+                    path: path.clone(),
+                }))
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err(io::ErrorKind::Unsupported.into()),
+        }
+    }
+
+    /// Accepted connection with the description of peer
+    fn accept(&self) -> io::Result<(Connection, String)> {
+        match self {
+            Listener::Tcp(l) => l.accept().map(|(s, addr)| (Connection::Tcp(s), addr.to_string())),
+            #[cfg(unix)]
+            Listener::Unix(l) => l.listener.accept().map(|(s, _)| (Connection::Unix(s), "unix client".to_string())),
+        }
+    }
+}
+
+/// Listener of Unix socket. Socket file is removed with the listener
+#[cfg(unix)]
+struct SocketListener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Drop for SocketListener {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Removes the socket file, left by the previous run. Socket, which is still listened, is kept
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {}
+        _ => return Ok(()),
+    }
+
+    match UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(io::ErrorKind::AddrInUse, "Socket is listened by another process")),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Removes the socket file, when the process is interrupted or terminated
+#[cfg(target_os = "linux")]
+fn remove_on_termination(path: &Path) {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::sync::OnceLock;
+
+    static SOCKET_PATH: OnceLock<CString> = OnceLock::new();
+
+    extern "C" fn handler(signal: libc::c_int) {
+        // Only async-signal-safe calls. Default action terminates the process with the same signal
+        unsafe {
+            if let Some(path) = SOCKET_PATH.get() {
+                libc::unlink(path.as_ptr());
+            }
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return;
+    };
+    if SOCKET_PATH.set(path).is_err() {
+        return;
+    }
+
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        unsafe {
+            libc::signal(signal, handler as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Output, shared by all connections
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    /// Upstream answers `pong` to everything
+    fn serve_pong(upstream: Listener) {
+        thread::spawn(move || loop {
+            let (mut stream, _) = upstream.accept().unwrap();
+            let mut request = vec![];
+            stream.read_to_end(&mut request).unwrap();
+            stream.write_all(b"pong").unwrap();
+        });
+    }
+
+    /// Sends `ping` through the proxy. Returns the dump and the client connection
+    fn ping(listener: Listener, addrs: ProxyAddrs) -> (String, Connection) {
+        let config = AppDumpConfig::new(
+            Some(Default::default()),
+            ByteFormatter::new(Default::default(), Groupping::RowWide(4), "", false, Default::default()),
+            Some(Default::default()),
+            true,
+        );
+        let listen = addrs.listen.clone();
+        let proxy = Proxy::new(addrs, config);
+
+        let out = Shared::default();
+        let proxy_out = out.clone();
+        thread::spawn(move || proxy.serve(listener, move || proxy_out.clone()));
+
+        let mut client = Connection::connect(&listen).unwrap();
+        client.write_all(b"ping").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut response = vec![];
+        client.read_to_end(&mut response).unwrap();
+        assert_eq!(response, b"pong");
+
+        let started = Instant::now();
+        while !out.text().contains("#1 closed") {
+            assert!(started.elapsed() < Duration::from_secs(10), "Connection is not closed");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        (out.text(), client)
+    }
+
+    fn expected_dump(peer: &str) -> String {
+        format!(
+            "#1 connected {peer}\n\
+             #1 > length=4 from=0 to=3\n\
+             00000000 70696e67 |ping|\n\
+             #1 < length=4 from=0 to=3\n\
+             00000000 706f6e67 |pong|\n\
+             #1 closed\n"
+        )
+    }
+
+    #[test]
+    fn loopback() {
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        serve_pong(Listener::Tcp(upstream));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addrs = ProxyAddrs {
+            listen: Endpoint::Tcp(listener.local_addr().unwrap().to_string()),
+            upstream: Endpoint::Tcp(upstream_addr.to_string()),
+        };
+
+        let (dump, client) = ping(Listener::Tcp(listener), addrs);
+        let Connection::Tcp(client) = client else {
+            unreachable!()
+        };
+        assert_eq!(dump, expected_dump(&client.local_addr().unwrap().to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_loopback() {
+        let socket = |name: &str| std::env::temp_dir().join(format!("kex-{name}-{}.sock", std::process::id()));
+        let (upstream, listen) = (Endpoint::Unix(socket("upstream")), Endpoint::Unix(socket("proxy")));
+
+        serve_pong(Listener::bind(&upstream).unwrap());
+
+        // Socket file of the interrupted run is replaced
+        let Endpoint::Unix(path) = &listen else {
+            unreachable!()
+        };
+        drop(UnixListener::bind(path).unwrap());
+        assert!(path.exists());
+        let listener = Listener::bind(&listen).unwrap();
+
+        // Socket, which is listened, is kept
+        let busy = socket("busy");
+        let _busy_listener = UnixListener::bind(&busy).unwrap();
+        let in_use = Listener::bind(&Endpoint::Unix(busy.clone())).err().map(|e| e.kind());
+        assert_eq!(in_use, Some(io::ErrorKind::AddrInUse));
+        assert!(busy.exists());
+        _ = std::fs::remove_file(&busy);
+
+        let (dump, _) = ping(listener, ProxyAddrs { listen, upstream });
+        assert_eq!(dump, expected_dump("unix client"));
+    }
+
+    #[test]
+    fn reset_closes_both_directions() {
+        struct Reset;
+
+        impl Read for Reset {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::ConnectionReset.into())
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (destination, _) = listener.accept().unwrap();
+        destination.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+
+        forward(Reset, io::sink(), &Connection::Tcp(destination.try_clone().unwrap()));
+
+        // Peer has sent nothing, so reading ends at once only if the reading side is closed
+        assert_eq!((&destination).read(&mut [0u8; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn accept_backoff() {
+        let reset = io::Error::from(io::ErrorKind::ConnectionAborted);
+        assert_eq!(accept_delay(&reset, MAX_ACCEPT_DELAY), Duration::ZERO);
+
+        let exhausted = io::Error::other("Too many open files");
+        let mut delay = Duration::ZERO;
+        let mut delays = vec![];
+        for _ in 0..9 {
+            delay = accept_delay(&exhausted, delay);
+            delays.push(delay.as_millis());
+        }
+        assert_eq!(delays, [10, 20, 40, 80, 160, 320, 640, 1000, 1000]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket_file_removed() {
+        let path = std::env::temp_dir().join(format!("kex-removed-{}.sock", std::process::id()));
+
+        let listener = Listener::bind(&Endpoint::Unix(path.clone())).unwrap();
+        assert!(path.exists());
+        drop(listener);
+        assert!(!path.exists());
+    }
+}
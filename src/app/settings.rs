@@ -0,0 +1,299 @@
+//! Resolution of option values from the command line, configuration file and builtin defaults
+
+use getopts::Matches;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use super::config_file::*;
+use super::opts::*;
+use super::result::*;
+
+/// Where the effective value of option came from
+enum Source {
+    CommandLine,
+    Profile,
+    File,
+    Default,
+}
+
+impl Source {
+    fn description(&self, profile: &Option<String>) -> String {
+        match self {
+            Source::CommandLine => "command line".to_string(),
+            Source::Profile => format!("profile `{}`", profile.as_deref().unwrap_or_default()),
+            Source::File => "config file".to_string(),
+            Source::Default => "default".to_string(),
+        }
+    }
+}
+
+/// Option values, resolved in order: command line, selected profile,
+/// root table of the configuration file, builtin default
+pub(super) struct Settings {
+    matches: Matches,
+    file: ConfigFile,
+    profile: Option<String>,
+}
+
+impl Settings {
+    pub(super) fn new(matches: Matches, file: ConfigFile) -> AppResult<Self> {
+        let path = match &file.path {
+            Some(p) => p.display().to_string(),
+            None => String::new(),
+        };
+
+        if let Err(e) = validate_table(&file.root) {
+            return Err(AppError::new(format!("{path}: {e}")));
+        }
+        for (name, table) in file.profiles.iter() {
+            if let Err(e) = validate_table(table) {
+                return Err(AppError::new(format!("{path}: profile `{name}`: {e}")));
+            }
+        }
+
+        let profile = match matches.opt_str(PROFILE_LONG_NAME) {
+            Some(p) => Some(p),
+            None => std::env::var(PROFILE_ENV).ok().filter(|p| !p.is_empty()),
+        };
+
+        if let Some(name) = &profile {
+            file.profile(name)?;
+        }
+
+        Ok(Self {
+            matches,
+            file,
+            profile,
+        })
+    }
+
+    pub(super) fn free(&self) -> &[String] {
+        &self.matches.free
+    }
+
+    /// Flag is set or option has a value at any level. Options, which are not configurable,
+    /// are looked up only in the command line
+    pub(super) fn opt_present(&self, name: &str) -> bool {
+        if configurable_opt(name).is_none() {
+            return self.matches.opt_present(name);
+        }
+
+        match self.value(name) {
+            Some((Value::Bool(b), _)) => b,
+            Some((Value::List(items), _)) => !items.is_empty(),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// All values of the option, which may be repeated
+    pub(super) fn opt_strs(&self, name: &str) -> Vec<String> {
+        match self.value(name) {
+            Some((Value::List(items), _)) => items,
+            _ => vec![],
+        }
+    }
+
+    /// Resolved value of configurable option. Returns `None` if option has no value at any level
+    pub(super) fn opt_get<T: FromStr>(&self, name: &str) -> AppResult<Option<T>>
+    where
+        T::Err: std::fmt::Display,
+    {
+        match self.value(name) {
+            Some((v, _)) => match v.to_arg().parse::<T>() {
+                Ok(parsed) => Ok(Some(parsed)),
+                Err(e) => Err(AppError::new(format!("{}: {e}", v.to_arg()))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Resolved value of configurable option, which always has a builtin default
+    pub(super) fn opt_str(&self, name: &str) -> String {
+        self.value(name)
+            .map(|(v, _)| v.to_arg())
+            .expect("Option must have a builtin default")
+    }
+
+    fn value(&self, name: &str) -> Option<(Value, Source)> {
+        let opt = configurable_opt(name).expect("Option is not configurable");
+
+        let arg = match opt.kind {
+            OptKind::Flag => self.matches.opt_present(opt.name).then_some(Value::Bool(true)),
+            OptKind::Multi => Some(self.matches.opt_strs(opt.name)).filter(|v| !v.is_empty()).map(Value::List),
+            OptKind::Str | OptKind::Int => self.matches.opt_str(opt.name).map(Value::Str),
+        };
+        if let Some(v) = arg {
+            return Some((v, Source::CommandLine));
+        }
+
+        if let Some(profile) = &self.profile {
+            let table = self.file.profile(profile).expect("Profile was validated");
+            if let Some(v) = table.get(opt.key) {
+                return Some((v.clone(), Source::Profile));
+            }
+        }
+
+        if let Some(v) = self.file.root.get(opt.key) {
+            return Some((v.clone(), Source::File));
+        }
+
+        match opt.kind {
+            OptKind::Flag => Some((Value::Bool(false), Source::Default)),
+            OptKind::Multi => Some((Value::List(vec![]), Source::Default)),
+            OptKind::Str | OptKind::Int => opt.default.map(|d| (Value::Str(d.to_string()), Source::Default)),
+        }
+    }
+
+    /// Effective configuration in the configuration file format
+    pub(super) fn describe(&self) -> String {
+        let mut result = String::new();
+
+        let path = match &self.file.path {
+            Some(p) => p.display().to_string(),
+            None => "none".to_string(),
+        };
+        _ = writeln!(result, "# Configuration file: {path}");
+        _ = writeln!(
+            result,
+            "# Profile: {}",
+            self.profile.as_deref().unwrap_or("none")
+        );
+
+        for opt in CONFIGURABLE_OPTS.iter() {
+            match self.value(opt.name) {
+                Some((value, source)) => {
+                    // Integers of the command line are strings
+                    let value = match opt.kind {
+                        OptKind::Int => value.to_arg(),
+                        _ => value.to_string(),
+                    };
+                    _ = writeln!(
+                        result,
+                        "{} = {value} # {}",
+                        opt.key,
+                        source.description(&self.profile)
+                    );
+                }
+                None => {
+                    _ = writeln!(result, "# {} is not set", opt.key);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+fn validate_table(table: &Table) -> AppResult<()> {
+    for (key, value) in table.iter() {
+        let opt = match CONFIGURABLE_OPTS.iter().find(|opt| opt.key == key) {
+            Some(o) => o,
+            None => {
+                return Err(AppError::new(format!("{key}: Unknown option")));
+            }
+        };
+
+        let is_valid = matches!(
+            (opt.kind, value),
+            (OptKind::Str, Value::Str(_))
+                | (OptKind::Int, Value::Int(_))
+                | (OptKind::Flag, Value::Bool(_))
+                | (OptKind::Multi, Value::List(_))
+        );
+        if !is_valid {
+            return Err(AppError::new(format!("{key}: Expected {}", opt.kind.description())));
+        }
+    }
+
+    Ok(())
+}
+
+fn configurable_opt(name: &str) -> Option<&'static ConfigurableOpt> {
+    CONFIGURABLE_OPTS
+        .iter()
+        .find(|opt| opt.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every configurable option with a value, which differs from the default
+    const FILE: &str = r##"address = "#h16"
+bytes = "H"
+group = "4/4"
+skip = 16
+length = 32
+placeholder = "* {rows}"
+dedup_period = 2
+table = "font.tbl"
+text_gutters = true
+column = ["rel", "row"]
+threads = 4
+proxy = "listen=127.0.0.1:9000,upstream=127.0.0.1:8000"
+pcap = true
+skip_link = true
+packets = "10-20"
+section = ".rodata"
+sections = true
+"##;
+
+    fn resolve(args: &[&str], text: &str) -> AppResult<Settings> {
+        let matches = get_configured_opts().parse(args).expect("Valid arguments");
+        Settings::new(matches, ConfigFile::parse(text)?)
+    }
+
+    #[test]
+    fn every_option() {
+        let file = ConfigFile::parse(FILE).unwrap();
+        for opt in CONFIGURABLE_OPTS.iter() {
+            assert!(file.root.contains_key(opt.key), "{} is not tested", opt.key);
+        }
+
+        let settings = resolve(&[], FILE).unwrap();
+        let expected: String = FILE.lines().map(|l| format!("{l} # config file\n")).collect();
+        assert_eq!(
+            settings.describe(),
+            format!("# Configuration file: none\n# Profile: none\n{expected}")
+        );
+        assert_eq!(ConfigFile::parse(&settings.describe()).unwrap().root, file.root);
+
+        assert_eq!(settings.opt_str(ADDR_FORMAT_SHORT_NAME), "#h16");
+        assert_eq!(settings.opt_get::<u64>(SKIP_SHORT_NAME).unwrap(), Some(16));
+        assert_eq!(settings.opt_strs(COLUMN_LONG_NAME), ["rel", "row"]);
+        assert!(settings.opt_present(PCAP_LONG_NAME));
+        assert!(settings.opt_present(SECTION_LONG_NAME));
+        assert!(!settings.opt_present(PRINT_CONFIG_LONG_NAME));
+    }
+
+    #[test]
+    fn command_line_and_profiles() {
+        let text = "pcap = true\ncolumn = [\"rel\"]\n[profiles.plain]\npcap = false\n";
+
+        let settings = resolve(&["-a", "h4", "--column", "abs", "--sections"], text).unwrap();
+        assert_eq!(settings.opt_str(ADDR_FORMAT_SHORT_NAME), "h4");
+        assert_eq!(settings.opt_strs(COLUMN_LONG_NAME), ["abs"]);
+        assert!(settings.opt_present(PCAP_LONG_NAME));
+        assert!(settings.opt_present(SECTIONS_LONG_NAME));
+
+        let settings = resolve(&["--profile", "plain"], text).unwrap();
+        assert!(!settings.opt_present(PCAP_LONG_NAME));
+        assert!(settings.describe().contains("pcap = false # profile `plain`\n"));
+        assert!(settings.describe().contains("skip_link = false # default\n"));
+
+        let cases = [
+            ("pcap = \"yes\"", "pcap: Expected boolean"),
+            ("skip = \"16\"", "skip: Expected non-negative integer"),
+            ("column = \"rel\"", "column: Expected array of strings"),
+            ("[profiles.a]\naddress = 8", "profile `a`: address: Expected string"),
+            ("color = true", "color: Unknown option"),
+        ];
+        for (text, expected) in cases {
+            match resolve(&[], text) {
+                Ok(_) => panic!("{text:?} must not be accepted"),
+                Err(e) => assert!(e.to_string().ends_with(expected), "{e}"),
+            }
+        }
+    }
+}
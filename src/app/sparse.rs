@@ -0,0 +1,152 @@
+//! Detection of holes in sparse files
+
+use std::cmp::min;
+use std::fs::File;
+use std::io::{Result, Seek, SeekFrom, Write};
+
+use super::{stream, ContentRange};
+
+/// Output, which accounts zero bytes of holes without reading them
+pub(crate) trait HoleOutput: Write {
+    /// Prints `len` zero bytes of file hole
+    fn push_hole(&mut self, len: u64) -> Result<()>;
+}
+
+/// Dumps `range` of the regular file with `size` bytes, reading only regions with data
+pub(crate) fn stream_sparse(file: &mut File, output: &mut impl HoleOutput, range: &ContentRange, size: u64) -> Result<()> {
+    let mut offset = range.skip;
+    let end = match range.len {
+        Some(len) => min(size, offset.saturating_add(len)),
+        None => size,
+    };
+
+    while offset < end {
+        let (data, hole) = next_data(file, offset)?.unwrap_or((end, end));
+
+        let data = min(data, end);
+        if data > offset {
+            output.push_hole(data - offset)?;
+        }
+
+        file.seek(SeekFrom::Start(data))?;
+
+        let data_end = min(hole, end);
+        stream(&mut *file, &mut *output, Some(data_end - data))?;
+
+        offset = data_end;
+    }
+
+    Ok(())
+}
+
+/// Finds the next region with data, starting from `offset`.
+///
+/// Returns start of data and start of the following hole, or `None` if there is no data until the end of file.
+/// On platforms without `SEEK_DATA`/`SEEK_HOLE` (or on file systems without their support) the whole file is data.
+///
+/// File position is undefined after the call
+#[cfg(target_os = "linux")]
+pub(crate) fn next_data(file: &File, offset: u64) -> Result<Option<(u64, u64)>> {
+    use std::io::Error;
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+
+    let data = unsafe { libc::lseek(fd, offset as libc::off_t, libc::SEEK_DATA) };
+    if data < 0 {
+        let err = Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENXIO) => Ok(None),
+            Some(libc::EINVAL) => Ok(Some((offset, u64::MAX))),
+            _ => Err(err),
+        };
+    }
+
+    let hole = unsafe { libc::lseek(fd, data, libc::SEEK_HOLE) };
+    if hole < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(Some((data as u64, hole as u64)))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn next_data(_file: &File, offset: u64) -> Result<Option<(u64, u64)>> {
+    Ok(Some((offset, u64::MAX)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kex::*;
+
+    type TestPrinter = Printer<Vec<u8>, AddressFormatter, ByteFormatter, CharFormatter>;
+
+    impl HoleOutput for TestPrinter {
+        fn push_hole(&mut self, len: u64) -> Result<()> {
+            Printer::push_hole(self, len)
+        }
+    }
+
+    /// File of 5 pages with data only in the second and the fourth pages
+    struct SparseFile(std::path::PathBuf);
+
+    impl SparseFile {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("kex-sparse-{}", std::process::id()));
+            let mut file = File::create(&path).unwrap();
+            file.set_len(0x5000).unwrap();
+            for offset in [0x1000, 0x3000] {
+                file.seek(SeekFrom::Start(offset)).unwrap();
+                file.write_all(&[0x11; 0x1000]).unwrap();
+            }
+
+            Self(path)
+        }
+    }
+
+    impl Drop for SparseFile {
+        fn drop(&mut self) {
+            _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn ranges_in_holes() {
+        let sparse = SparseFile::new();
+        let mut file = File::open(&sparse.0).unwrap();
+        let config = Config::new(
+            Some(Default::default()),
+            ByteFormatter::new(Default::default(), Groupping::RowWide(16), "", false, Separators::new("", "")),
+            None,
+            true,
+        );
+
+        // Starts in the first hole and ends in the second one
+        let range = ContentRange {
+            skip: 0x800,
+            len: Some(0x2000),
+        };
+        let mut printer = TestPrinter::new(vec![], range.skip, config);
+        stream_sparse(&mut file, &mut printer, &range, 0x5000).unwrap();
+        let text = String::from_utf8(printer.finish()).unwrap();
+
+        let (data, _) = next_data(&file, 0).unwrap().unwrap();
+        let expected = match data {
+            // File system without holes
+            0 => "00000800 00000000000000000000000000000000\n\
+                  *\n\
+                  00001000 11111111111111111111111111111111\n\
+                  *\n\
+                  00002000 00000000000000000000000000000000\n\
+                  *\n\
+                  00002800 \n",
+            _ => "* hole, 2048 bytes, 00000800..00001000\n\
+                  00001000 11111111111111111111111111111111\n\
+                  *\n\
+                  * hole, 2048 bytes, 00002000..00002800\n\
+                  00002800 \n",
+        };
+        assert_eq!(text, expected);
+    }
+}
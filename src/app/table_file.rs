@@ -0,0 +1,128 @@
+//! Byte table file, used by `--table`.
+//!
+//! Every line maps hexadecimal byte value to its representation, like in table files of ROM editors.
+//! Representation is taken as is, after the first `=`. `*` sets representation of unlisted bytes,
+//! otherwise they are shown as dots of the same display width. Lines, starting with `#`, are comments.
+//!
+//! ```text
+//! # Digits and capital letters of the game font
+//! 00=0
+//! 0a=A
+//! 24=-
+//! *=?
+//! ```
+
+use std::path::Path;
+
+use kex::{ByteTable, BYTE_TABLE_LEN};
+use unicode_width::UnicodeWidthStr;
+
+use super::result::*;
+
+const DEFAULT_KEY: &str = "*";
+const COMMENT_CHAR: char = '#';
+const PLACEHOLDER: &str = ".";
+
+pub(super) fn load_table(path: &Path) -> AppResult<ByteTable> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            return Err(AppError::new(format!("{}: {e}", path.display())));
+        }
+    };
+
+    match parse_table(&text) {
+        Ok(table) => Ok(table),
+        Err(e) => Err(AppError::new(format!("{}: {e}", path.display()))),
+    }
+}
+
+pub(super) fn parse_table(text: &str) -> AppResult<ByteTable> {
+    let mut entries: Vec<Option<String>> = vec![None; BYTE_TABLE_LEN];
+    let mut default: Option<String> = None;
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_num = idx + 1;
+
+        if line.trim().is_empty() || line.starts_with(COMMENT_CHAR) {
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((k, v)) => (k.trim(), v.to_string()),
+            None => {
+                return Err(line_error(line_num, "Expected `byte=representation`"));
+            }
+        };
+
+        let entry = match key {
+            DEFAULT_KEY => &mut default,
+            _ => match u8::from_str_radix(key, 16) {
+                Ok(byte) => &mut entries[byte as usize],
+                Err(_) => {
+                    return Err(line_error(line_num, &format!("{key}: Invalid byte")));
+                }
+            },
+        };
+
+        if entry.is_some() {
+            return Err(line_error(line_num, &format!("{key}: Duplicate byte")));
+        }
+        *entry = Some(value);
+    }
+
+    let default = match default {
+        Some(d) => d,
+        None => {
+            let width = entries.iter().flatten().next().map_or(1, |e| e.as_str().width());
+            PLACEHOLDER.repeat(width)
+        }
+    };
+
+    let entries: Vec<String> = entries
+        .into_iter()
+        .map(|e| e.unwrap_or_else(|| default.clone()))
+        .collect();
+
+    ByteTable::new(&entries).map_err(|e| AppError::new(format!("{e}")))
+}
+
+fn line_error(line_num: usize, description: &str) -> AppError {
+    AppError::new(format!("line {line_num}: {description}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let table = parse_table("# font\n00=0\n0A=A\n24= \n").expect("Valid table");
+        assert_eq!(table.get(0x00), b"0");
+        assert_eq!(table.get(0x0a), b"A");
+        assert_eq!(table.get(0x24), b" ");
+        assert_eq!(table.get(0x25), b".");
+
+        let table = parse_table("41=^A\n*=..").expect("Valid table");
+        assert_eq!(table.get(0x41), b"^A");
+        assert_eq!(table.get(0x42), b"..");
+
+        let table = parse_table("41=あ\n").expect("Valid table");
+        assert_eq!(table.get(0x41), "あ".as_bytes());
+        assert_eq!(table.get(0x42), b"..");
+
+        let cases = [
+            ("41", "line 1: Expected `byte=representation`"),
+            ("\n100=a", "line 2: 100: Invalid byte"),
+            ("41=a\n41=b", "line 2: 41: Duplicate byte"),
+            ("41=ab\n42=c", "Entries of bytes 42 differ from width 2 of other entries"),
+        ];
+
+        for (text, expected) in cases {
+            match parse_table(text) {
+                Ok(_) => panic!("{text:?} must not be parsed"),
+                Err(e) => assert_eq!(e.to_string(), expected),
+            }
+        }
+    }
+}
@@ -1,20 +1,34 @@
 //! Module with printing configuration structure
 
 use super::format::*;
+use std::{error::Error, fmt::Display};
+
+/// Maximum of [`Config::with_max_dedup_period`]. Rows of twice the period are kept in memory
+pub const MAX_DEDUP_PERIOD: usize = 4096;
 
 /// Configuration of formatting
+///
+/// With `serde` feature deserialized config is checked by [`Config::validate`]
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "crate::serialization::ConfigRepr<A, B, C>"))]
 pub struct Config<
     A: AddressFormatting + Clone,
     B: ByteFormatting + Clone,
     C: CharFormatting + Clone,
 > {
-    
-    pub(super) addr: Option<A>,
+    /// Address columns in order of printing
+    #[cfg_attr(feature = "serde", serde(rename = "address"))]
+    pub(super) addr: Vec<AddressColumn<A>>,
+    #[cfg_attr(feature = "serde", serde(rename = "bytes"))]
     pub(super) byte: B,
     pub(super) text: Option<C>,
 
     pub(super) dedup_enabled: bool,
+    pub(super) placeholder: DedupPlaceholder,
+    pub(super) hole_placeholder: DedupPlaceholder,
+    pub(super) max_dedup_period: usize,
+    pub(super) immediate_output: bool,
 }
 
 impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone>
@@ -23,6 +37,7 @@ impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting
     /// Create a new config.
     ///
     /// `address_format` - address formatter, used for printing address in the start of each row.
+    /// More columns may be added with [`Config::with_address_column`].
     /// 
     /// `byte_format` - byte formatter, used for printing raw data .
     /// 
@@ -36,11 +51,83 @@ impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting
         dedup_enabled: bool,
     ) -> Self {
         Self {
-            addr: address_format,
+            addr: address_format
+                .map(|fmt| AddressColumn::new(AddressKind::Absolute, fmt))
+                .into_iter()
+                .collect(),
             byte: byte_format,
             text: text_format,
             dedup_enabled,
+            placeholder: Default::default(),
+            hole_placeholder: DedupPlaceholder::hole(),
+            max_dedup_period: 1,
+            immediate_output: false,
+        }
+    }
+
+    /// Checks the whole configuration. [`crate::Printer`] panics on configuration, rejected here
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for col in self.addr.iter() {
+            col.fmt.validate()?;
+        }
+
+        self.byte.groupping().validate()?;
+        if self.byte.bytes_per_row() == 0 {
+            return Err(ConfigError::EmptyRow);
+        }
+        if let Some(text) = &self.text {
+            text.validate(self.byte.bytes_per_row())?;
+        }
+
+        if self.max_dedup_period == 0 {
+            return Err(ConfigError::EmptyDedupPeriod);
         }
+        if self.max_dedup_period > MAX_DEDUP_PERIOD {
+            return Err(ConfigError::DedupPeriod(self.max_dedup_period));
+        }
+
+        Ok(())
+    }
+
+    /// Add address column after the existing ones, e.g. offset relative to the start along with absolute address.
+    /// Addresses of placeholders are shown by the first column
+    pub fn with_address_column(mut self, kind: AddressKind, address_format: A) -> Self {
+        self.addr.push(AddressColumn::new(kind, address_format));
+        self
+    }
+
+    /// Replace placeholder of duplicated rows. Classic `*` is used by default
+    pub fn with_placeholder(mut self, placeholder: DedupPlaceholder) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    /// Replace placeholder of zero bytes, declared by [`crate::Printer::push_hole`].
+    /// `* hole, {bytes} bytes, {start}..{end}` is used by default
+    pub fn with_hole_placeholder(mut self, placeholder: DedupPlaceholder) -> Self {
+        self.hole_placeholder = placeholder;
+        self
+    }
+
+    /// Collapse patterns, that repeat with a period up to `max_period` rows (`1` by default).
+    /// Must not exceed [`MAX_DEDUP_PERIOD`].
+    ///
+    /// First occurrence of pattern is printed, all consequent repetitions are replaced by single placeholder.
+    /// Up to `2 * max_period` rows are kept in memory, and printing of up to `max_period` rows may be postponed
+    /// until it is known whether they repeat. Has no effect if deduplication is disabled
+    pub fn with_max_dedup_period(mut self, max_period: usize) -> Self {
+        self.max_dedup_period = max_period;
+        self
+    }
+
+    /// Print bytes of incomplete row as soon as they are pushed (`false` by default).
+    ///
+    /// Otherwise every row is assembled in the internal buffer and written to output with a single write,
+    /// which is much faster for unbuffered outputs, like `Stdout` or sockets.
+    /// Rows, which may be replaced by placeholder, are never printed before they are complete
+    pub fn with_immediate_output(mut self, immediate: bool) -> Self {
+        self.immediate_output = immediate;
+        self
     }
 }
 
@@ -52,10 +139,194 @@ impl<
 {
     fn default() -> Config<A, B, C> {
         Self {
-            addr: Some(A::default()),
+            addr: vec![AddressColumn::new(AddressKind::Absolute, A::default())],
             byte: B::default(),
             text: Some(C::default()),
             dedup_enabled: true,
+            placeholder: Default::default(),
+            hole_placeholder: DedupPlaceholder::hole(),
+            max_dedup_period: 1,
+            immediate_output: false,
         }
     }
 }
+
+/// Configuration of formatters, chosen at runtime. See [`crate::DynPrinter`]
+pub type DynConfig = Config<DynAddressFormatter, DynByteFormatter, DynCharFormatter>;
+
+/// Builder of [`Config`], which validates it in [`ConfigBuilder::build`].
+///
+/// Starts with the only column of raw bytes, deduplication is enabled
+pub struct ConfigBuilder<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> {
+    config: Config<A, B, C>,
+}
+
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> ConfigBuilder<A, B, C> {
+    pub fn new(byte_format: B) -> Self {
+        Self {
+            config: Config::new(None, byte_format, None, true),
+        }
+    }
+
+    /// Add absolute address column. See [`Config::with_address_column`] for other kinds
+    pub fn with_address(self, address_format: A) -> Self {
+        self.with_address_column(AddressKind::Absolute, address_format)
+    }
+
+    pub fn with_address_column(mut self, kind: AddressKind, address_format: A) -> Self {
+        self.config = self.config.with_address_column(kind, address_format);
+        self
+    }
+
+    /// Add text column after raw bytes
+    pub fn with_text(mut self, text_format: C) -> Self {
+        self.config.text = Some(text_format);
+        self
+    }
+
+    /// Replace duplicated rows by placeholder (`true` by default)
+    pub fn with_dedup(mut self, dedup_enabled: bool) -> Self {
+        self.config.dedup_enabled = dedup_enabled;
+        self
+    }
+
+    pub fn with_placeholder(mut self, placeholder: DedupPlaceholder) -> Self {
+        self.config = self.config.with_placeholder(placeholder);
+        self
+    }
+
+    pub fn with_hole_placeholder(mut self, placeholder: DedupPlaceholder) -> Self {
+        self.config = self.config.with_hole_placeholder(placeholder);
+        self
+    }
+
+    pub fn with_max_dedup_period(mut self, max_period: usize) -> Self {
+        self.config = self.config.with_max_dedup_period(max_period);
+        self
+    }
+
+    pub fn with_immediate_output(mut self, immediate_output: bool) -> Self {
+        self.config = self.config.with_immediate_output(immediate_output);
+        self
+    }
+
+    pub fn build(self) -> Result<Config<A, B, C>, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Reason of [`Config`] rejection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Row has no bytes
+    EmptyRow,
+    /// Group with index has no bytes
+    EmptyGroup(usize),
+    /// Size of [`Groupping::Nested`] level with `index` is not a multiple of the `inner` level size
+    UnalignedLevel { index: usize, size: usize, inner: usize },
+    /// Address width exceeds [`MAX_ADDRESS_WIDTH`]
+    AddressWidth(usize),
+    /// Maximum dedup period is zero rows
+    EmptyDedupPeriod,
+    /// Maximum dedup period exceeds [`MAX_DEDUP_PERIOD`]
+    DedupPeriod(usize),
+    /// Groupping of [`CharFormatter::with_gutters`] has `gutters` bytes per row, but the byte formatter has `row`
+    GuttersRow { gutters: usize, row: usize },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::EmptyRow => write!(f, "Row must have at least one byte"),
+            ConfigError::EmptyGroup(idx) => write!(f, "Group {idx} must have at least one byte"),
+            ConfigError::UnalignedLevel { index, size, inner } => write!(
+                f,
+                "Size {size} of level {index} must be a multiple of the previous level size {inner}"
+            ),
+            ConfigError::AddressWidth(width) => {
+                write!(f, "Address width {width} exceeds maximum of {MAX_ADDRESS_WIDTH}")
+            }
+            ConfigError::EmptyDedupPeriod => write!(f, "Maximum dedup period must be at least one row"),
+            ConfigError::DedupPeriod(period) => {
+                write!(f, "Maximum dedup period {period} exceeds maximum of {MAX_DEDUP_PERIOD} rows")
+            }
+            ConfigError::GuttersRow { gutters, row } => {
+                write!(f, "Text gutters are set for {gutters} bytes per row, but the row has {row} bytes")
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Builder = ConfigBuilder<AddressFormatter, ByteFormatter, CharFormatter>;
+
+    fn byte_fmt(groupping: Groupping) -> ByteFormatter {
+        ByteFormatter::new(Default::default(), groupping, "", false, Default::default())
+    }
+
+    #[test]
+    fn validation() {
+        let cases = [
+            (Groupping::RowWide(0), ConfigError::EmptyRow),
+            (Groupping::RepeatingGroup(Group::new(0, " "), 4), ConfigError::EmptyGroup(0)),
+            (Groupping::RepeatingGroup(Group::new(4, " "), 0), ConfigError::EmptyRow),
+            (
+                Groupping::Pattern(vec![Group::new(4, " "), Group::new(0, " ")]),
+                ConfigError::EmptyGroup(1),
+            ),
+            (Groupping::Nested(vec![], 2), ConfigError::EmptyRow),
+            (
+                Groupping::Nested(vec![Group::new(2, " "), Group::new(8, "  "), Group::new(12, "   ")], 1),
+                ConfigError::UnalignedLevel { index: 2, size: 12, inner: 8 },
+            ),
+        ];
+
+        for (groupping, expected) in cases {
+            assert_eq!(Builder::new(byte_fmt(groupping)).build().err(), Some(expected));
+        }
+
+        let wide = AddressFormatter::new(AddressStyle::Hex(10000), Default::default());
+        assert_eq!(
+            Builder::new(Default::default()).with_address(wide).build().err(),
+            Some(ConfigError::AddressWidth(10000))
+        );
+
+        assert_eq!(
+            Builder::new(Default::default()).with_max_dedup_period(0).build().err(),
+            Some(ConfigError::EmptyDedupPeriod)
+        );
+        assert_eq!(
+            Builder::new(Default::default()).with_max_dedup_period(100_000_000_000).build().err(),
+            Some(ConfigError::DedupPeriod(100_000_000_000))
+        );
+        assert!(Builder::new(Default::default()).with_max_dedup_period(MAX_DEDUP_PERIOD).build().is_ok());
+
+        let nested = Groupping::Nested(vec![Group::new(2, " "), Group::new(4, "  ")], 2);
+        let gutters = |groupping| CharFormatter::default().with_gutters(groupping, " ");
+        assert_eq!(
+            Builder::new(byte_fmt(Groupping::RowWide(16))).with_text(gutters(nested.clone())).build().err(),
+            Some(ConfigError::GuttersRow { gutters: 8, row: 16 })
+        );
+        assert_eq!(
+            Builder::new(byte_fmt(nested.clone())).with_text(gutters(Groupping::RowWide(0))).build().err(),
+            Some(ConfigError::EmptyRow)
+        );
+        let dynamic = DynConfig::new(
+            None,
+            DynByteFormatter::new(byte_fmt(Groupping::RowWide(4))),
+            Some(DynCharFormatter::new(gutters(nested.clone()))),
+            true,
+        );
+        assert_eq!(dynamic.validate().err(), Some(ConfigError::GuttersRow { gutters: 8, row: 4 }));
+        assert!(Builder::new(byte_fmt(nested.clone())).with_text(gutters(nested)).build().is_ok());
+
+        let config = Builder::new(byte_fmt(Groupping::RowWide(4))).with_dedup(false).build().unwrap();
+        assert!(crate::Printer::try_new(Vec::<u8>::new(), 0, config).is_ok());
+    }
+}
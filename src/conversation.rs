@@ -0,0 +1,198 @@
+//! Interleaved dump of both directions of a conversation.
+//!
+//! Every pushed chunk is printed as a separate block after the header line with the direction marker,
+//! like `socat -x`:
+//!
+//! ```text
+//! > 1760000000.000000 length=5 from=0 to=4
+//! 00000000 68656c6c 6f...... ........ ........ |hello           |
+//! < 1760000000.250000 length=3 from=0 to=2
+//! 00000000 6f6b0a.. ........ ........ ........ |ok.             |
+//! ```
+//!
+//! `>` marks written (sent) bytes and `<` marks read (received) bytes. Each direction has its own offset.
+//! Rows of a block never contain bytes of other chunks. Every block is written with a single write,
+//! so conversations may share the output, like `Stdout`, distinguished by their labels
+
+use std::io::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+
+/// Dump of bytes, pushed from two directions
+pub struct Conversation<O: Write, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> {
+    out: O,
+    config: Config<A, B, C>,
+    renderer: RowRenderer<A, B, C>,
+
+    /// Offsets of the next byte, indexed by [`Conversation::index`]
+    offsets: [u64; 2],
+    timestamps: bool,
+    label: Option<String>,
+
+    /// Text of the current block
+    block: Vec<u8>,
+}
+
+impl<O: Write, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone>
+    Conversation<O, A, B, C>
+{
+    /// Fails with [`ConfigError`], if `config` is rejected by [`Config::validate`]
+    pub fn try_new(out: O, config: Config<A, B, C>) -> std::result::Result<Conversation<O, A, B, C>, ConfigError> {
+        config.validate()?;
+
+        Ok(Conversation {
+            out,
+            renderer: RowRenderer::new(config.clone(), 0),
+            config,
+            offsets: [0; 2],
+            timestamps: false,
+            label: None,
+            block: vec![],
+        })
+    }
+
+    /// Same as [`Conversation::try_new`], for configs known to be valid, like built by [`ConfigBuilder::build`](crate::ConfigBuilder::build)
+    ///
+    /// ## Panics
+    /// If `config` is rejected by [`Config::validate`]
+    #[track_caller]
+    pub fn new(out: O, config: Config<A, B, C>) -> Conversation<O, A, B, C> {
+        match Self::try_new(out, config) {
+            Ok(conversation) => conversation,
+            Err(e) => panic!("Invalid config: {e}"),
+        }
+    }
+
+    /// Print time of every chunk in the header, as seconds since the Unix epoch with microseconds
+    pub fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Text before the direction marker of every header, like `#1`
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Offset of the next byte of `direction`
+    pub fn offset(&self, direction: Direction) -> u64 {
+        self.offsets[Self::index(direction)]
+    }
+
+    /// Prints `bytes` as a single chunk. Empty chunks are ignored
+    pub fn push(&mut self, direction: Direction, bytes: &[u8]) -> Result<()> {
+        self.push_at(direction, SystemTime::now(), bytes)
+    }
+
+    /// Same as [`Conversation::push`], but with the given time of the chunk
+    pub fn push_at(&mut self, direction: Direction, time: SystemTime, bytes: &[u8]) -> Result<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let index = Self::index(direction);
+        let from = self.offsets[index];
+        let to = streamer::advance_address(from, bytes.len() as u64)?;
+
+        self.block.clear();
+        let block = &mut self.block;
+
+        if let Some(label) = &self.label {
+            write!(block, "{label} ")?;
+        }
+        write!(block, "{}", direction.marker())?;
+        if self.timestamps {
+            let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+            write!(block, " {}.{:06}", since_epoch.as_secs(), since_epoch.subsec_micros())?;
+        }
+        writeln!(block, " length={} from={} to={}", bytes.len(), from, to - 1)?;
+
+        for row in Rows::new(bytes, from, &self.config) {
+            match row? {
+                DumpRow::End(_) => {}
+                row => self.renderer.render(&row, block)?,
+            }
+        }
+
+        self.out.write_all(&self.block)?;
+
+        self.offsets[index] = to;
+        Ok(())
+    }
+
+    /// Turns back the output
+    pub fn into_inner(self) -> O {
+        self.out
+    }
+
+    fn index(direction: Direction) -> usize {
+        match direction {
+            Direction::Write => 0,
+            Direction::Read => 1,
+        }
+    }
+}
+
+/// Chunks are timed on arrival. Address of the call is ignored, offsets are tracked by the conversation
+impl<O: Write, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> InspectSink
+    for Conversation<O, A, B, C>
+{
+    fn inspect(&mut self, direction: Direction, _: u64, bytes: &[u8]) -> Result<()> {
+        self.push(direction, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Fmt = Config<AddressFormatter, ByteFormatter, CharFormatter>;
+
+    #[test]
+    fn interleaved() {
+        let config = Fmt::new(
+            Some(Default::default()),
+            ByteFormatter::new(Default::default(), Groupping::RowWide(4), "", false, Default::default()),
+            Some(Default::default()),
+            true,
+        );
+        let at = |millis: u64| UNIX_EPOCH + Duration::from_millis(1_760_000_000_000 + millis);
+
+        let mut conversation = Conversation::new(vec![], config.clone()).with_timestamps(true);
+        conversation.push_at(Direction::Write, at(0), b"GET /\n").unwrap();
+        conversation.push_at(Direction::Read, at(250), b"ok\n").unwrap();
+        conversation.push_at(Direction::Write, at(1500), b"").unwrap();
+        conversation.push_at(Direction::Write, at(1500), b"bye").unwrap();
+
+        let expected = "> 1760000000.000000 length=6 from=0 to=5\n\
+                        00000000 47455420 |GET |\n\
+                        00000004 2f0a.... |/.  |\n\
+                        < 1760000000.250000 length=3 from=0 to=2\n\
+                        00000000 6f6b0a.. |ok. |\n\
+                        > 1760000001.500000 length=3 from=6 to=8\n\
+                        00000006 627965.. |bye |\n";
+        assert_eq!(String::from_utf8(conversation.into_inner()).unwrap(), expected);
+
+        // Both wrappers share the conversation
+        let conversation = Arc::new(Mutex::new(Conversation::new(vec![], config).with_label("#1")));
+        let mut writer = InspectWriter::new(sink(), conversation.clone());
+        let mut reader = InspectReader::new(&b"pong"[..], conversation.clone());
+        writer.write_all(b"ping").unwrap();
+        reader.read_exact(&mut [0u8; 4]).unwrap();
+        drop((writer, reader));
+
+        let conversation = Arc::into_inner(conversation).unwrap().into_inner().unwrap();
+        assert_eq!(conversation.offset(Direction::Write), 4);
+        assert_eq!(conversation.offset(Direction::Read), 4);
+
+        let expected = "#1 > length=4 from=0 to=3\n\
+                        00000000 70696e67 |ping|\n\
+                        #1 < length=4 from=0 to=3\n\
+                        00000000 706f6e67 |pong|\n";
+        assert_eq!(String::from_utf8(conversation.into_inner()).unwrap(), expected);
+    }
+}
@@ -2,12 +2,18 @@
 //! Module with builtin offset formatter
 
 use super::*;
+use crate::ConfigError;
+use std::io::{Error, ErrorKind};
 
 /// Builtin address formatter
-#[derive(Clone)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct AddressFormatter {
     pub style: AddressStyle,
     pub separators: Separators,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
+    prefix: Vec<u8>,
 }
 
 impl AddressFormatter {
@@ -15,28 +21,38 @@ impl AddressFormatter {
         Self {
             style,
             separators,
+            prefix: vec![],
         }
     }
-}
 
-impl Default for AddressFormatter {
-    fn default() -> Self {
-        Self { style: Default::default(), separators: Default::default() }
+    /// Print `prefix` before every address, like `0x`
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Vec::from(prefix);
+        self
     }
 }
 
 impl AddressFormatting for AddressFormatter {
-    fn format<O: Write>(&self, addr: usize, out: &mut O) -> Result<()> {
-        let result = self.style.format(addr);
-        out.write_all(result.as_bytes())
+    fn format<O: Write>(&self, addr: u64, out: &mut O) -> Result<()> {
+        out.write_all(&self.prefix)?;
+        self.style.format(addr, out)
     }
 
     fn separators(&self) -> &Separators {
         &self.separators
     }
+
+    fn validate(&self) -> std::result::Result<(), ConfigError> {
+        self.style.validate()
+    }
 }
 
+/// Maximum width of [`AddressStyle`]. Binary `u64` fits in it
+pub const MAX_ADDRESS_WIDTH: usize = 64;
+
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum AddressStyle {
     /// Present address as decimal with specified minimum width. Formated address
     /// will be padded with spaces
@@ -44,6 +60,8 @@ pub enum AddressStyle {
     /// Present address as hexadecimal with specified minimum width. Formated address
     /// will be padded with zeros
     Hex(usize),
+    /// Same as [`AddressStyle::Hex`], but with uppercase digits
+    UpperHex(usize),
     /// Present address as binary with specified minimum width. Formated address
     /// will be padded with zeros
     Bin(usize),
@@ -53,19 +71,97 @@ pub enum AddressStyle {
 }
 
 impl AddressStyle {
-    fn format(&self, addr: usize) -> String {
+    /// Checks, that width does not exceed [`MAX_ADDRESS_WIDTH`]
+    pub fn validate(&self) -> std::result::Result<(), ConfigError> {
+        let width = match self {
+            AddressStyle::Dec(w)
+            | AddressStyle::Hex(w)
+            | AddressStyle::UpperHex(w)
+            | AddressStyle::Bin(w)
+            | AddressStyle::Oct(w) => *w,
+        };
+
+        match width > MAX_ADDRESS_WIDTH {
+            true => Err(ConfigError::AddressWidth(width)),
+            false => Ok(()),
+        }
+    }
+
+    fn format<O: Write>(&self, addr: u64, out: &mut O) -> Result<()> {
         match self {
-            AddressStyle::Dec(w) => format!("{:width$}", addr, width = w),
-            AddressStyle::Hex(w) => format!("{:0width$x}", addr, width = w),
-            AddressStyle::Bin(w) => format!("{:0width$b}", addr, width = w),
-            AddressStyle::Oct(w) => format!("{:0width$o}", addr, width = w),
-            
+            AddressStyle::Dec(w) => write!(out, "{:width$}", addr, width = w),
+            AddressStyle::Hex(w) => write!(out, "{:0width$x}", addr, width = w),
+            AddressStyle::UpperHex(w) => write!(out, "{:0width$X}", addr, width = w),
+            AddressStyle::Bin(w) => write!(out, "{:0width$b}", addr, width = w),
+            AddressStyle::Oct(w) => write!(out, "{:0width$o}", addr, width = w),
         }
     }
 }
 
+/// What address column shows
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AddressKind {
+    /// Address of the first byte of the row. Starts from `start_address` of the printer
+    #[default]
+    Absolute,
+    /// Offset of the row from the first printed byte
+    Relative,
+    /// `base` plus offset from the first printed byte, like virtual address of mapped file region
+    Based(u64),
+    /// Number of the row, starting from zero
+    RowIndex,
+}
+
+impl AddressKind {
+    /// Value of column for `address`, when the first byte has `first_address`
+    pub(crate) fn resolve(&self, address: u64, first_address: u64, bytes_per_row: usize) -> Result<u64> {
+        let offset = address - first_address;
+        match self {
+            AddressKind::Absolute => Ok(address),
+            AddressKind::Relative => Ok(offset),
+            AddressKind::Based(base) => match base.checked_add(offset) {
+                Some(a) => Ok(a),
+                None => Err(Error::new(ErrorKind::InvalidInput, "Based address exceeds u64 range")),
+            },
+            AddressKind::RowIndex => Ok(offset / bytes_per_row as u64),
+        }
+    }
+}
+
+/// Address column with its own formatter
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddressColumn<A: AddressFormatting> {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) kind: AddressKind,
+    #[cfg_attr(feature = "serde", serde(rename = "format"))]
+    pub(crate) fmt: A,
+}
+
+impl<A: AddressFormatting> AddressColumn<A> {
+    pub fn new(kind: AddressKind, fmt: A) -> Self {
+        Self { kind, fmt }
+    }
+}
+
 impl Default for AddressStyle {
     fn default() -> Self {
         Self::Hex(8)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix() {
+        let fmt = AddressFormatter::new(AddressStyle::UpperHex(4), Default::default()).with_prefix("0x");
+
+        let mut out = Vec::new();
+        fmt.format(0xbeef, &mut out).unwrap();
+
+        assert_eq!(out, b"0xBEEF");
+    }
+}
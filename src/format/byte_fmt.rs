@@ -8,14 +8,26 @@ const PLACEHOLDER: &[u8; 1] = b".";
 const SPACE: u8 = b' ';
 const CARET: u8 = b'^';
 
+const PADDING: [u8; 64] = [b'.'; 64];
+
+const RUST_LITERAL_START: &[u8] = b"b\"";
+const RUST_LITERAL_END: &[u8] = b"\"";
+
 /// Builtin byte formatter (used for `second` column by default)
-#[derive(Clone)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ByteFormatter {
     pub(super) style: ByteStyle,
     pub(super) groupping: Groupping,
     pub(super) is_little_endian: bool,
 
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
     byte_separator: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
+    prefix: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
+    suffix: Vec<u8>,
     pub(super) separators: Separators,
 }
 
@@ -32,21 +44,48 @@ impl ByteFormatter {
             groupping,
             is_little_endian,
             byte_separator: Vec::from(byte_separator),
+            prefix: vec![],
+            suffix: vec![],
             separators,
         }
     }
-}
 
-impl Default for ByteFormatter {
-    fn default() -> Self {
-        Self {
-            style: Default::default(),
-            groupping: Default::default(),
-            is_little_endian: false,
-            byte_separator: vec![],
-            separators: Default::default(),
+    /// Surround every byte with `prefix` and `suffix`, like `0xde,`.
+    /// Ignored by [`ByteStyle::RustLiteral`]
+    pub fn with_affixes(mut self, prefix: &str, suffix: &str) -> Self {
+        self.prefix = Vec::from(prefix);
+        self.suffix = Vec::from(suffix);
+        self
+    }
+
+    fn is_literal(&self) -> bool {
+        matches!(self.style, ByteStyle::RustLiteral)
+    }
+
+    /// Separators are not allowed inside of literal
+    fn byte_separator(&self) -> &[u8] {
+        match self.is_literal() {
+            true => &[],
+            false => &self.byte_separator,
         }
     }
+
+    fn group_separator(&self, byte_number: usize) -> &[u8] {
+        match self.is_literal() {
+            true => &[],
+            false => self.groupping.separator_before_group(byte_number),
+        }
+    }
+
+    fn push_byte<O: Write>(&self, byte: u8, buf: &mut RenderBuffer<O>) -> Result<()> {
+        if self.is_literal() {
+            return buf.push(self.style.render(byte));
+        }
+
+        buf.push(&self.prefix)?;
+        buf.push(self.style.render(byte))?;
+        buf.push(&self.suffix)
+    }
 }
 
 impl ByteFormatting for ByteFormatter {
@@ -69,89 +108,99 @@ impl ByteFormatting for ByteFormatter {
         out: &mut O,
     ) -> Result<usize> {
         let gr = &self.groupping;
-        let gr_size = gr.max_group_size();
 
-        let sep = gr.separator();
+        let byte_separator = self.byte_separator();
 
+        // Whole group is rendered at once, when nothing is placed between bytes
+        let is_contiguous = byte_separator.is_empty()
+            && !self.is_little_endian
+            && (self.is_literal() || (self.prefix.is_empty() && self.suffix.is_empty()));
+
+        let mut buf = RenderBuffer::new(out);
         let mut tmp = bytes;
 
+        if self.is_literal() && byte_number_in_row == 0 && !bytes.is_empty() {
+            buf.push(RUST_LITERAL_START)?;
+        }
+
         let mut byte_number = byte_number_in_row;
-        while tmp.len() != 0 {
-            let bytes_left_in_group = gr.bytes_left_in_group_after(byte_number);
+        // Only the first group may be started before
+        let mut bytes_left_in_group = gr.bytes_left_in_group_after(byte_number);
+        let mut num = byte_number - gr.group_start(byte_number);
+        while !tmp.is_empty() {
             let to_format = min(tmp.len(), bytes_left_in_group);
 
-            let needs_separator = byte_number != 0 && gr.is_aligned_at(byte_number);
-            if needs_separator {
-                out.write_all(&sep[..])?;
+            if num == 0 && byte_number != 0 {
+                buf.push(self.group_separator(byte_number))?;
             }
 
-            byte_number += to_format;
-
-            if to_format != 0 {
-                let mut num = gr_size - bytes_left_in_group;
-
-                if self.is_little_endian {
-                    for byte in tmp[..to_format].iter().rev() {
-                        if num != 0 {
-                            out.write_all(&self.byte_separator[..])?;
-                        }
-                        self.style.format_byte(*byte, out)?;
-                        num += 1;
+            if is_contiguous {
+                self.style.render_all(&tmp[..to_format], &mut buf)?;
+            } else if self.is_little_endian {
+                for byte in tmp[..to_format].iter().rev() {
+                    if num != 0 {
+                        buf.push(byte_separator)?;
                     }
-                } else {
-                    for byte in &tmp[..to_format] {
-                        if num != 0 {
-                            out.write_all(&self.byte_separator[..])?;
-                        }
-                        self.style.format_byte(*byte, out)?;
-                        num += 1;
+                    self.push_byte(*byte, &mut buf)?;
+                    num += 1;
+                }
+            } else {
+                for byte in &tmp[..to_format] {
+                    if num != 0 {
+                        buf.push(byte_separator)?;
                     }
+                    self.push_byte(*byte, &mut buf)?;
+                    num += 1;
                 }
             }
 
-            tmp = &tmp[to_format..]
+            byte_number += to_format;
+            tmp = &tmp[to_format..];
+            if !tmp.is_empty() {
+                bytes_left_in_group = gr.group_size_at(byte_number);
+            }
+            num = 0;
         }
 
+        buf.flush()?;
+
         Ok(bytes.len())
     }
 
     fn format_padding<O: Write>(&self, byte_number_in_row: usize, out: &mut O) -> Result<()> {
-        let padding = b"..";
         let gr = &self.groupping;
-        let gr_size = gr.max_group_size();
-
-        let sep = gr.separator();
 
-        let mut tmp = gr.bytes_per_row() - byte_number_in_row;
-        let mut byte_number = byte_number_in_row;
-        while tmp != 0 {
-            let bytes_left_in_group = gr.bytes_left_in_group_after(byte_number);
-            let to_format = min(tmp, bytes_left_in_group);
-
-            let needs_separator = byte_number != 0 && gr.is_aligned_at(byte_number);
-            if needs_separator {
-                out.write_all(&sep)?;
-            }
+        let mut buf = RenderBuffer::new(out);
 
-            byte_number += to_format;
-
-            if to_format != 0 {
-                let mut num = gr_size - bytes_left_in_group;
-
-                for _ in 0..to_format {
-                    if num != 0 {
-                        out.write_all(&self.byte_separator)?;
-                    }
-                    out.write_all(padding)?;
-
-                    num += 1;
+        // Width of literal rows differs anyway
+        let width = match self.style.width() {
+            Some(w) => self.prefix.len() + w + self.suffix.len(),
+            None => {
+                if byte_number_in_row == 0 {
+                    buf.push(RUST_LITERAL_START)?;
                 }
+                buf.push(RUST_LITERAL_END)?;
+                return buf.flush();
+            }
+        };
+
+        let bpr = gr.bytes_per_row();
+        for byte_number in byte_number_in_row..bpr {
+            if gr.is_aligned_at(byte_number) {
+                buf.push(gr.separator_before(byte_number))?;
+            } else {
+                buf.push(&self.byte_separator)?;
             }
 
-            tmp -= to_format;
+            let mut rem = width;
+            while rem != 0 {
+                let to_push = min(rem, PADDING.len());
+                buf.push(&PADDING[..to_push])?;
+                rem -= to_push;
+            }
         }
 
-        Ok(())
+        buf.flush()
     }
 
     fn separators(&self) -> &Separators {
@@ -159,10 +208,6 @@ impl ByteFormatting for ByteFormatter {
     }
 }
 
-const LOWER_HEX: [u8; 16] = [
-    b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'a', b'b', b'c', b'd', b'e', b'f',
-];
-
 const CARET_NOTATION_LUT: [u8; 32] = [
     b'@', b'A', b'B', b'C', b'D', b'E', b'F', b'G', b'H', b'I', b'J', b'K', b'L', b'M', b'N', b'O',
     b'P', b'Q', b'R', b'S', b'T', b'U', b'V', b'W', b'X', b'Y', b'Z', b'[', b'\\', b']', b'^',
@@ -171,100 +216,145 @@ const CARET_NOTATION_LUT: [u8; 32] = [
 
 const CARET_NOTATION_DEL: u8 = b'?';
 
-#[derive(Clone)]
+static ASCII_LUT: [[u8; 1]; 256] = ascii_lut();
+static CARET_ASCII_LUT: [[u8; 2]; 256] = caret_ascii_lut();
+static RUST_LITERAL_LUT: [([u8; 4], usize); 256] = rust_literal_lut();
+
+/// Same as [`AsciiChar::is_ascii_printable`]
+const fn is_printable(byte: u8) -> bool {
+    byte.wrapping_sub(b' ') < 0x5f
+}
+
+const fn ascii_lut() -> [[u8; 1]; 256] {
+    let mut lut = [*PLACEHOLDER; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = i as u8;
+        if is_printable(byte) {
+            lut[i] = [byte];
+        }
+        i += 1;
+    }
+    lut
+}
+
+const fn caret_ascii_lut() -> [[u8; 2]; 256] {
+    let mut lut = [[SPACE, PLACEHOLDER[0]]; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = i as u8;
+        if i < CARET_NOTATION_LUT.len() {
+            lut[i] = [CARET, CARET_NOTATION_LUT[i]];
+        } else if byte == b'?' {
+            lut[i] = [CARET, CARET_NOTATION_DEL];
+        } else if is_printable(byte) {
+            lut[i] = [SPACE, byte];
+        }
+        i += 1;
+    }
+    lut
+}
+
+/// Byte string literal escapes with used length
+const fn rust_literal_lut() -> [([u8; 4], usize); 256] {
+    let mut lut = [([0u8; 4], 0); 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = i as u8;
+        lut[i] = match byte {
+            b'"' | b'\\' => ([b'\\', byte, 0, 0], 2),
+            b'\n' => (*b"\\n\0\0", 2),
+            b'\r' => (*b"\\r\0\0", 2),
+            b'\t' => (*b"\\t\0\0", 2),
+            b'\0' => (*b"\\0\0\0", 2),
+            _ if is_printable(byte) => ([byte, 0, 0, 0], 1),
+            _ => (C_ESCAPE_LUT[i], 4),
+        };
+        i += 1;
+    }
+    lut
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ByteStyle {
+    /// `deadbeef`
+    #[default]
     Hex,
+    /// `DEADBEEF`
+    UpperHex,
     Bin,
     Dec,
     Oct,
     Ascii,
     CaretAscii,
+    /// C escape sequences: `\xde\xad`
+    CEscape,
+    /// URL percent-encoding: `%DE%AD`
+    Percent,
+    /// Rows of Rust byte string literals: `b"\xde\xadabc"`. Printable characters stay literal.
+    /// Separators are not printed inside of literal
+    RustLiteral,
+    /// User-defined representations
+    Table(ByteTable),
 }
 
 impl ByteStyle {
+    /// Printable representation of `byte`
     #[inline(always)]
-    pub(super) fn format_byte<O: Write>(&self, byte: u8, out: &mut O) -> Result<()> {
+    pub(super) fn render(&self, byte: u8) -> &[u8] {
+        let idx = byte as usize;
         match self {
-            ByteStyle::Hex => self.fmt_hex(byte, out),
-            ByteStyle::Bin => self.fmt_bin(byte, out),
-            ByteStyle::Dec => self.fmt_dec(byte, out),
-            ByteStyle::Oct => self.fmt_oct(byte, out),
-            ByteStyle::Ascii => self.fmt_ascii(byte, out),
-            ByteStyle::CaretAscii => self.fmt_caret_ascii(byte, out),
+            ByteStyle::Hex => &HEX_LUT[idx],
+            ByteStyle::UpperHex => &UPPER_HEX_LUT[idx],
+            ByteStyle::Bin => &BIN_LUT[idx],
+            ByteStyle::Dec => &DEC_LUT[idx],
+            ByteStyle::Oct => &OCT_LUT[idx],
+            ByteStyle::Ascii => &ASCII_LUT[idx],
+            ByteStyle::CaretAscii => &CARET_ASCII_LUT[idx],
+            ByteStyle::CEscape => &C_ESCAPE_LUT[idx],
+            ByteStyle::Percent => &PERCENT_LUT[idx],
+            ByteStyle::RustLiteral => {
+                let (chars, len) = &RUST_LITERAL_LUT[idx];
+                &chars[..*len]
+            }
+            ByteStyle::Table(table) => table.get(byte),
         }
     }
 
-    pub(super) fn fmt_hex<O: Write>(&self, byte: u8, out: &mut O) -> Result<()> {
-        let mut buf: [u8; 2] = [0, 0];
-        buf[0] = LOWER_HEX[(byte >> 4) as usize];
-        buf[1] = LOWER_HEX[(byte & 0x0f) as usize];
-
-        out.write_all(&buf)?;
-
-        Ok(())
-    }
-
-    pub(super) fn fmt_bin<O: Write>(&self, byte: u8, out: &mut O) -> Result<()> {
-        let s = format!("{:08b}", byte);
-        out.write_all(s.as_bytes())?;
-
-        Ok(())
-    }
-
-    pub(super) fn fmt_dec<O: Write>(&self, byte: u8, out: &mut O) -> Result<()> {
-        let s = format!("{:3}", byte);
-        out.write_all(s.as_bytes())?;
-
-        Ok(())
-    }
-
-    pub(super) fn fmt_oct<O: Write>(&self, byte: u8, out: &mut O) -> Result<()> {
-        let s = format!("{:03o}", byte);
-        out.write_all(s.as_bytes())?;
-
-        Ok(())
-    }
-
-    pub(super) fn fmt_ascii<O: Write>(&self, byte: u8, out: &mut O) -> Result<()> {
-        let chr = unsafe { byte.to_ascii_char_unchecked() };
-
-        if chr.is_ascii_printable() {
-            out.write_all(&vec![byte])?;
-        } else {
-            out.write(PLACEHOLDER)?;
+    /// Width of every rendered byte. `None` if width depends on byte
+    pub(super) fn width(&self) -> Option<usize> {
+        match self {
+            ByteStyle::Hex | ByteStyle::UpperHex | ByteStyle::CaretAscii => Some(2),
+            ByteStyle::Bin => Some(8),
+            ByteStyle::Dec | ByteStyle::Oct | ByteStyle::Percent => Some(3),
+            ByteStyle::Ascii => Some(1),
+            ByteStyle::CEscape => Some(4),
+            ByteStyle::RustLiteral => None,
+            ByteStyle::Table(table) => Some(table.width()),
         }
-
-        Ok(())
     }
 
-    pub(super) fn fmt_caret_ascii<O: Write>(&self, byte: u8, out: &mut O) -> Result<()> {
-        let mut buf: [u8; 2] = [0, 0];
-
-        let chr = unsafe { byte.to_ascii_char_unchecked() };
-
-        if (byte as usize) < CARET_NOTATION_LUT.len() {
-            buf[0] = CARET;
-            buf[1] = CARET_NOTATION_LUT[byte as usize];
-        } else if byte == b'?' {
-            buf[0] = CARET;
-            buf[1] = CARET_NOTATION_DEL;
-        } else if chr.is_ascii_printable() {
-            buf[0] = SPACE;
-            buf[1] = byte;
-        } else {
-            buf[0] = SPACE;
-            buf[1] = PLACEHOLDER[0];
+    /// Pushes representations of `bytes` without separators
+    fn render_all<O: Write>(&self, bytes: &[u8], buf: &mut RenderBuffer<O>) -> Result<()> {
+        match self {
+            ByteStyle::Hex => buf.push_hex(bytes, HexCase::Lower),
+            ByteStyle::UpperHex => buf.push_hex(bytes, HexCase::Upper),
+            ByteStyle::Bin => buf.push_table(bytes, &BIN_LUT),
+            ByteStyle::Dec => buf.push_table(bytes, &DEC_LUT),
+            ByteStyle::Oct => buf.push_table(bytes, &OCT_LUT),
+            ByteStyle::Ascii => buf.push_table(bytes, &ASCII_LUT),
+            ByteStyle::CaretAscii => buf.push_table(bytes, &CARET_ASCII_LUT),
+            ByteStyle::CEscape => buf.push_table(bytes, &C_ESCAPE_LUT),
+            ByteStyle::Percent => buf.push_table(bytes, &PERCENT_LUT),
+            ByteStyle::RustLiteral | ByteStyle::Table(_) => {
+                for byte in bytes {
+                    buf.push(self.render(*byte))?;
+                }
+                Ok(())
+            }
         }
-
-        out.write_all(&buf)?;
-
-        Ok(())
-    }
-}
-
-impl Default for ByteStyle {
-    fn default() -> Self {
-        Self::Hex
     }
 }
 
@@ -285,6 +375,7 @@ mod tests {
             }
         }
 
+        #[allow(clippy::needless_borrow)]
         fn run(&self, fmt: &ByteFormatter) {
             const WRITE_ERROR_MSG: &str = "Formatting error";
 
@@ -355,6 +446,129 @@ mod tests {
         }
     }
 
+    /// Letters are kept, other bytes are replaced by the lowest digit of their number
+    fn digits_table() -> ByteTable {
+        let entries: Vec<String> = (0..=255u8)
+            .map(|b| match b.is_ascii_graphic() {
+                true => (b as char).to_string(),
+                false => (b % 10).to_string(),
+            })
+            .collect();
+        ByteTable::new(&entries).unwrap()
+    }
+
+    #[test]
+    fn test_styles() {
+        let bytes = vec![0x00u8, b'?', b'A', 0x7f, 0xff];
+        let cases = [
+            (ByteStyle::Hex, "00 3f 41 7f ff"),
+            (ByteStyle::Bin, "00000000 00111111 01000001 01111111 11111111"),
+            (ByteStyle::Dec, "  0  63  65 127 255"),
+            (ByteStyle::Oct, "000 077 101 177 377"),
+            (ByteStyle::Ascii, ". ? A . ."),
+            (ByteStyle::CaretAscii, "^@ ^?  A  .  ."),
+            (ByteStyle::UpperHex, "00 3F 41 7F FF"),
+            (ByteStyle::CEscape, r"\x00 \x3f \x41 \x7f \xff"),
+            (ByteStyle::Percent, "%00 %3F %41 %7F %FF"),
+            (ByteStyle::Table(digits_table()), "0 ? A 7 5"),
+        ];
+
+        for (style, result) in cases {
+            let fmt = ByteFormatter::new(style, Groupping::RowWide(5), " ", false, Default::default());
+            Case::new(vec![bytes.clone()], result).run(&fmt);
+        }
+    }
+
+    #[test]
+    fn test_affixes() {
+        let fmt = ByteFormatter::new(
+            Default::default(),
+            Groupping::RepeatingGroup(Group::new(2, "  "), 2),
+            " ",
+            false,
+            Default::default(),
+        )
+        .with_affixes("0x", ",");
+        let cases = vec![
+            Case::new(vec![vec![0xde, 0xad, 0xbe, 0xef]], "0xde, 0xad,  0xbe, 0xef,"),
+            Case::new(vec![vec![0xde], vec![0xad, 0xbe]], "0xde, 0xad,  0xbe, ....."),
+        ];
+        for case in cases {
+            case.run(&fmt);
+        }
+
+        // Padding keeps width of rendered bytes
+        let fmt = ByteFormatter::new(ByteStyle::Bin, Groupping::RowWide(2), " ", false, Default::default());
+        Case::new(vec![vec![0x01]], "00000001 ........").run(&fmt);
+    }
+
+    #[test]
+    fn test_rust_literal() {
+        let fmt = ByteFormatter::new(
+            ByteStyle::RustLiteral,
+            Groupping::RepeatingGroup(Group::new(2, " "), 4),
+            " ",
+            false,
+            Default::default(),
+        )
+        .with_affixes("0x", ",");
+        let cases = vec![
+            Case::new(
+                vec![b"ab\"\\\n\0".to_vec(), vec![0xde, 0x7f]],
+                r#"b"ab\"\\\n\0\xde\x7f""#,
+            ),
+            Case::new(vec![b"a".to_vec(), b"b".to_vec()], r#"b"ab""#),
+            Case::new(vec![], r#"b"""#),
+        ];
+        for case in cases {
+            case.run(&fmt);
+        }
+    }
+
+    #[test]
+    fn test_pattern() {
+        let groupping = Groupping::Pattern(vec![
+            Group::new(4, " | "),
+            Group::new(2, " "),
+            Group::new(2, " | "),
+            Group::new(2, "-"),
+        ]);
+
+        let fmt = ByteFormatter::new(Default::default(), groupping.clone(), "", false, Default::default());
+        let cases = vec![
+            Case::new(vec![(0..10).collect()], "00010203 | 0405 0607 | 0809"),
+            Case::new(vec![vec![0, 1, 2], vec![3, 4, 5, 6]], "00010203 | 0405 06.. | ...."),
+            Case::new(vec![vec![0], vec![1, 2, 3, 4]], "00010203 | 04.. .... | ...."),
+        ];
+        for case in cases {
+            case.run(&fmt);
+        }
+
+        let fmt = ByteFormatter::new(Default::default(), groupping, "", true, Default::default());
+        let cases = vec![
+            Case::new(vec![(0..4).collect(), (4..10).collect()], "03020100 | 0504 0706 | 0908"),
+            Case::new(vec![(0..6).collect()], "03020100 | 0504 .... | ...."),
+        ];
+        for case in cases {
+            case.run(&fmt);
+        }
+    }
+
+    #[test]
+    fn test_nested() {
+        let groupping = Groupping::Nested(vec![Group::new(2, " "), Group::new(4, " | ")], 2);
+
+        let fmt = ByteFormatter::new(Default::default(), groupping, "", false, Default::default());
+        let cases = vec![
+            Case::new(vec![(0..8).collect()], "0001 0203 | 0405 0607"),
+            Case::new(vec![vec![0, 1, 2], vec![3, 4]], "0001 0203 | 04.. ...."),
+            Case::new(vec![vec![0, 1, 2]], "0001 02.. | .... ...."),
+        ];
+        for case in cases {
+            case.run(&fmt);
+        }
+    }
+
     #[test]
     fn test_little_endian() {
         let fmt = ByteFormatter::new(
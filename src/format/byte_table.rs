@@ -0,0 +1,153 @@
+//! Module with user-defined byte rendering table
+
+use std::{error::Error, fmt::Display, sync::Arc};
+
+use unicode_width::UnicodeWidthStr;
+
+/// Number of entries in [`ByteTable`]
+pub const BYTE_TABLE_LEN: usize = 256;
+
+/// Custom representation of every byte value, used by [`super::ByteStyle::Table`] and [`super::CharFormatter::with_table`].
+///
+/// All entries must have the same display width (number of terminal columns, wide characters like CJK
+/// or emoji take two), otherwise padding of incomplete rows would not be aligned
+#[derive(Clone, Debug)]
+pub struct ByteTable {
+    entries: Arc<[Box<[u8]>]>,
+    width: usize,
+}
+
+impl ByteTable {
+    /// Create table from `entries`, where `entries[byte]` is representation of `byte`
+    pub fn new<S: AsRef<str>>(entries: &[S]) -> Result<Self, ByteTableError> {
+        if entries.len() != BYTE_TABLE_LEN {
+            return Err(ByteTableError::WrongLength(entries.len()));
+        }
+
+        let widths: Vec<usize> = entries.iter().map(|e| e.as_ref().width()).collect();
+
+        // The most common width is considered correct
+        let mut counts = [0usize; BYTE_TABLE_LEN];
+        let mut width = 0;
+        for w in widths.iter() {
+            if *w >= BYTE_TABLE_LEN {
+                continue;
+            }
+
+            counts[*w] += 1;
+            if counts[*w] > counts[width] || (counts[*w] == counts[width] && *w < width) {
+                width = *w;
+            }
+        }
+
+        let inconsistent: Vec<u8> = (0..BYTE_TABLE_LEN)
+            .filter(|i| widths[*i] != width)
+            .map(|i| i as u8)
+            .collect();
+        if !inconsistent.is_empty() {
+            return Err(ByteTableError::InconsistentWidth {
+                width,
+                bytes: inconsistent,
+            });
+        }
+
+        if width == 0 {
+            return Err(ByteTableError::EmptyEntries);
+        }
+
+        Ok(Self {
+            entries: entries
+                .iter()
+                .map(|e| Box::from(e.as_ref().as_bytes()))
+                .collect(),
+            width,
+        })
+    }
+
+    /// Representation of `byte`
+    #[inline(always)]
+    pub fn get(&self, byte: u8) -> &[u8] {
+        &self.entries[byte as usize]
+    }
+
+    /// Display width of every entry
+    pub fn width(&self) -> usize {
+        self.width
+    }
+}
+
+/// Reason of [`ByteTable`] rejection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ByteTableError {
+    /// Table has other number of entries, than [`BYTE_TABLE_LEN`]
+    WrongLength(usize),
+    /// Widths of `bytes` differ from `width` of the most entries
+    InconsistentWidth { width: usize, bytes: Vec<u8> },
+    /// All entries are empty
+    EmptyEntries,
+}
+
+impl Display for ByteTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ByteTableError::WrongLength(len) => {
+                write!(f, "Byte table must have {BYTE_TABLE_LEN} entries, got {len}")
+            }
+            ByteTableError::InconsistentWidth { width, bytes } => {
+                write!(f, "Entries of bytes")?;
+                for byte in bytes {
+                    write!(f, " {byte:02x}")?;
+                }
+                write!(f, " differ from width {width} of other entries")
+            }
+            ByteTableError::EmptyEntries => write!(f, "Byte table entries are empty"),
+        }
+    }
+}
+
+impl Error for ByteTableError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation() {
+        let mut entries: Vec<String> = (0..BYTE_TABLE_LEN).map(|i| format!("{i:02x}")).collect();
+        let table = ByteTable::new(&entries).unwrap();
+        assert_eq!(table.width(), 2);
+        assert_eq!(table.get(0xab), b"ab");
+
+        entries[0x10] = "€€".to_string();
+        assert_eq!(ByteTable::new(&entries).unwrap().get(0x10), "€€".as_bytes());
+
+        // Wide characters take two columns
+        entries[0x11] = "字".to_string();
+        entries[0x12] = "👍".to_string();
+        assert_eq!(ByteTable::new(&entries).unwrap().width(), 2);
+        entries[0x13] = "字x".to_string();
+        assert_eq!(
+            ByteTable::new(&entries).unwrap_err(),
+            ByteTableError::InconsistentWidth {
+                width: 2,
+                bytes: vec![0x13]
+            }
+        );
+        entries[0x13] = "13".to_string();
+
+        entries[0x20] = "x".to_string();
+        entries[0xff] = "xyz".to_string();
+        assert_eq!(
+            ByteTable::new(&entries).unwrap_err(),
+            ByteTableError::InconsistentWidth {
+                width: 2,
+                bytes: vec![0x20, 0xff]
+            }
+        );
+
+        assert_eq!(
+            ByteTable::new(&entries[1..]).unwrap_err(),
+            ByteTableError::WrongLength(255)
+        );
+    }
+}
@@ -6,8 +6,14 @@ use super::*;
 
 /// Builtin ascii characters formatter
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct CharFormatter {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
     placeholder: Vec<u8>,
+    table: Option<ByteTable>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::gutters"))]
+    gutters: Option<(Groupping, Vec<u8>)>,
     pub(super) separators: Separators,
 }
 
@@ -17,13 +23,34 @@ impl CharFormatter {
 
         Self {
             placeholder,
+            table: None,
+            gutters: None,
             separators,
         }
     }
-}
 
-impl CharFormatting for CharFormatter {
-    fn format<O: Write>(&self, bytes: &[u8], out: &mut O) -> Result<usize> {
+    /// Render characters with user-defined `table` instead of ASCII. Placeholder is not used
+    pub fn with_table(mut self, table: ByteTable) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Print `separator` between outer groups of [`Groupping::Nested`], like in the byte column.
+    /// `groupping` must be the same, as groupping of bytes. [`crate::Config::validate`] rejects other row lengths
+    pub fn with_gutters(mut self, groupping: Groupping, separator: &str) -> Self {
+        self.gutters = Some((groupping, Vec::from(separator)));
+        self
+    }
+
+    fn format_chars<O: Write>(&self, bytes: &[u8], out: &mut O) -> Result<()> {
+        if let Some(table) = &self.table {
+            for byte in bytes {
+                out.write_all(table.get(*byte))?;
+            }
+
+            return Ok(());
+        }
+
         let placeholder = &self.placeholder[..];
 
         for i in 0..bytes.len() {
@@ -41,16 +68,90 @@ impl CharFormatting for CharFormatter {
             }
         }
 
+        Ok(())
+    }
+
+    fn format_spaces<O: Write>(&self, byte_count: usize, out: &mut O) -> Result<()> {
+        const SPACES: [u8; 64] = [b' '; 64];
+
+        let width = self.table.as_ref().map_or(1, |t| t.width());
+
+        let mut rem = byte_count * width;
+        while rem != 0 {
+            let to_write = min(rem, SPACES.len());
+            out.write_all(&SPACES[..to_write])?;
+            rem -= to_write;
+        }
+
+        Ok(())
+    }
+}
+
+impl CharFormatting for CharFormatter {
+    fn format<O: Write>(&self, bytes: &[u8], out: &mut O) -> Result<usize> {
+        let (groupping, separator) = match &self.gutters {
+            Some(g) => g,
+            None => {
+                self.format_chars(bytes, out)?;
+                return Ok(bytes.len());
+            }
+        };
+
+        // Text of the whole row is formatted at once
+        let mut start = 0;
+        for number in 1..bytes.len() {
+            if groupping.is_outer_group_start(number) {
+                self.format_chars(&bytes[start..number], out)?;
+                out.write_all(separator)?;
+                start = number;
+            }
+        }
+        self.format_chars(&bytes[start..], out)?;
+
         Ok(bytes.len())
     }
 
     fn format_padding<O: Write>(&self, byte_count: usize, out: &mut O) -> Result<()> {
-        out.write_all(&b" ".repeat(byte_count))
+        let (groupping, separator) = match &self.gutters {
+            Some(g) => g,
+            None => return self.format_spaces(byte_count, out),
+        };
+
+        let bpr = groupping.bytes_per_row();
+        let first = bpr - min(byte_count, bpr);
+
+        // Gutter before the first missing byte is not printed by `format`
+        let mut start = first;
+        for number in first..bpr {
+            if groupping.is_outer_group_start(number) {
+                self.format_spaces(number - start, out)?;
+                out.write_all(separator)?;
+                start = number;
+            }
+        }
+        self.format_spaces(bpr - start, out)
     }
 
     fn separators(&self) -> &Separators {
         &self.separators
     }
+
+    fn validate(&self, bytes_per_row: usize) -> std::result::Result<(), crate::ConfigError> {
+        let (groupping, _) = match &self.gutters {
+            Some(g) => g,
+            None => return Ok(()),
+        };
+
+        groupping.validate()?;
+        if groupping.bytes_per_row() != bytes_per_row {
+            return Err(crate::ConfigError::GuttersRow {
+                gutters: groupping.bytes_per_row(),
+                row: bytes_per_row,
+            });
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for CharFormatter {
@@ -58,3 +159,28 @@ impl Default for CharFormatter {
         Self::new(".".to_string(), Separators::new("|", "|"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gutters() {
+        let groupping = Groupping::Nested(vec![Group::new(2, " "), Group::new(4, "  ")], 2);
+        let fmt = CharFormatter::default().with_gutters(groupping, " ");
+
+        let cases: [(&[u8], &str); 3] = [
+            (b"abcdefgh", "abcd efgh"),
+            (b"abcde", "abcd e   "),
+            (b"abcd", "abcd     "),
+        ];
+
+        for (bytes, expected) in cases {
+            let mut out = vec![];
+            fmt.format(bytes, &mut out).unwrap();
+            fmt.format_padding(8 - bytes.len(), &mut out).unwrap();
+
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+    }
+}
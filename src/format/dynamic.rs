@@ -0,0 +1,211 @@
+//! Object-safe formatting traits and type-erased formatters.
+//!
+//! [`AddressFormatting`], [`ByteFormatting`] and [`CharFormatting`] have generic methods,
+//! so they can not be used as trait objects. Every formatter also implements the object-safe
+//! counterpart, which writes to `&mut dyn Write`. [`DynAddressFormatter`], [`DynByteFormatter`] and
+//! [`DynCharFormatter`] wrap any formatter into a single type, which is accepted by [`crate::Printer`].
+//!
+//! Prefer static formatters, when they are known at compile time: every write of dynamic formatter
+//! goes through a virtual call
+
+use super::*;
+use std::sync::Arc;
+
+/// Object-safe [`AddressFormatting`]. Implemented for every address formatter
+pub trait DynAddressFormatting {
+    fn dyn_format(&self, addr: u64, out: &mut dyn Write) -> Result<()>;
+
+    fn dyn_separators(&self) -> &Separators;
+
+    fn dyn_validate(&self) -> std::result::Result<(), crate::ConfigError>;
+}
+
+impl<T: AddressFormatting> DynAddressFormatting for T {
+    fn dyn_format(&self, addr: u64, mut out: &mut dyn Write) -> Result<()> {
+        self.format(addr, &mut out)
+    }
+
+    fn dyn_separators(&self) -> &Separators {
+        self.separators()
+    }
+
+    fn dyn_validate(&self) -> std::result::Result<(), crate::ConfigError> {
+        self.validate()
+    }
+}
+
+/// Object-safe [`ByteFormatting`]. Implemented for every byte formatter
+pub trait DynByteFormatting {
+    fn dyn_byte_order(&self) -> GroupAtomicity;
+
+    fn dyn_groupping(&self) -> Groupping;
+
+    fn dyn_bytes_per_row(&self) -> usize;
+
+    fn dyn_format(&self, bytes: &[u8], byte_number_in_row: usize, out: &mut dyn Write) -> Result<usize>;
+
+    fn dyn_format_padding(&self, byte_number_in_row: usize, out: &mut dyn Write) -> Result<()>;
+
+    fn dyn_separators(&self) -> &Separators;
+}
+
+impl<T: ByteFormatting> DynByteFormatting for T {
+    fn dyn_byte_order(&self) -> GroupAtomicity {
+        self.byte_order()
+    }
+
+    fn dyn_groupping(&self) -> Groupping {
+        self.groupping()
+    }
+
+    fn dyn_bytes_per_row(&self) -> usize {
+        self.bytes_per_row()
+    }
+
+    fn dyn_format(&self, bytes: &[u8], byte_number_in_row: usize, mut out: &mut dyn Write) -> Result<usize> {
+        self.format(bytes, byte_number_in_row, &mut out)
+    }
+
+    fn dyn_format_padding(&self, byte_number_in_row: usize, mut out: &mut dyn Write) -> Result<()> {
+        self.format_padding(byte_number_in_row, &mut out)
+    }
+
+    fn dyn_separators(&self) -> &Separators {
+        self.separators()
+    }
+}
+
+/// Object-safe [`CharFormatting`]. Implemented for every char formatter
+pub trait DynCharFormatting {
+    fn dyn_format(&self, bytes: &[u8], out: &mut dyn Write) -> Result<usize>;
+
+    fn dyn_format_padding(&self, byte_count: usize, out: &mut dyn Write) -> Result<()>;
+
+    fn dyn_separators(&self) -> &Separators;
+
+    fn dyn_validate(&self, bytes_per_row: usize) -> std::result::Result<(), crate::ConfigError>;
+}
+
+impl<T: CharFormatting> DynCharFormatting for T {
+    fn dyn_format(&self, bytes: &[u8], mut out: &mut dyn Write) -> Result<usize> {
+        self.format(bytes, &mut out)
+    }
+
+    fn dyn_format_padding(&self, byte_count: usize, mut out: &mut dyn Write) -> Result<()> {
+        self.format_padding(byte_count, &mut out)
+    }
+
+    fn dyn_separators(&self) -> &Separators {
+        self.separators()
+    }
+
+    fn dyn_validate(&self, bytes_per_row: usize) -> std::result::Result<(), crate::ConfigError> {
+        self.validate(bytes_per_row)
+    }
+}
+
+/// Address formatter, chosen at runtime. Cloning shares the wrapped formatter
+#[derive(Clone)]
+pub struct DynAddressFormatter(Arc<dyn DynAddressFormatting + Send + Sync>);
+
+impl DynAddressFormatter {
+    pub fn new(fmt: impl AddressFormatting + Send + Sync + 'static) -> Self {
+        Self(Arc::new(fmt))
+    }
+}
+
+impl AddressFormatting for DynAddressFormatter {
+    fn format<O: Write>(&self, addr: u64, out: &mut O) -> Result<()> {
+        self.0.dyn_format(addr, out)
+    }
+
+    fn separators(&self) -> &Separators {
+        self.0.dyn_separators()
+    }
+
+    fn validate(&self) -> std::result::Result<(), crate::ConfigError> {
+        self.0.dyn_validate()
+    }
+}
+
+impl Default for DynAddressFormatter {
+    fn default() -> Self {
+        Self::new(AddressFormatter::default())
+    }
+}
+
+/// Byte formatter, chosen at runtime. Cloning shares the wrapped formatter
+#[derive(Clone)]
+pub struct DynByteFormatter(Arc<dyn DynByteFormatting + Send + Sync>);
+
+impl DynByteFormatter {
+    pub fn new(fmt: impl ByteFormatting + Send + Sync + 'static) -> Self {
+        Self(Arc::new(fmt))
+    }
+}
+
+impl ByteFormatting for DynByteFormatter {
+    fn byte_order(&self) -> GroupAtomicity {
+        self.0.dyn_byte_order()
+    }
+
+    fn groupping(&self) -> Groupping {
+        self.0.dyn_groupping()
+    }
+
+    fn bytes_per_row(&self) -> usize {
+        self.0.dyn_bytes_per_row()
+    }
+
+    fn format<O: Write>(&self, bytes: &[u8], byte_number_in_row: usize, out: &mut O) -> Result<usize> {
+        self.0.dyn_format(bytes, byte_number_in_row, out)
+    }
+
+    fn format_padding<O: Write>(&self, byte_number_in_row: usize, out: &mut O) -> Result<()> {
+        self.0.dyn_format_padding(byte_number_in_row, out)
+    }
+
+    fn separators(&self) -> &Separators {
+        self.0.dyn_separators()
+    }
+}
+
+impl Default for DynByteFormatter {
+    fn default() -> Self {
+        Self::new(ByteFormatter::default())
+    }
+}
+
+/// Char formatter, chosen at runtime. Cloning shares the wrapped formatter
+#[derive(Clone)]
+pub struct DynCharFormatter(Arc<dyn DynCharFormatting + Send + Sync>);
+
+impl DynCharFormatter {
+    pub fn new(fmt: impl CharFormatting + Send + Sync + 'static) -> Self {
+        Self(Arc::new(fmt))
+    }
+}
+
+impl CharFormatting for DynCharFormatter {
+    fn format<O: Write>(&self, bytes: &[u8], out: &mut O) -> Result<usize> {
+        self.0.dyn_format(bytes, out)
+    }
+
+    fn format_padding<O: Write>(&self, byte_count: usize, out: &mut O) -> Result<()> {
+        self.0.dyn_format_padding(byte_count, out)
+    }
+
+    fn separators(&self) -> &Separators {
+        self.0.dyn_separators()
+    }
+
+    fn validate(&self, bytes_per_row: usize) -> std::result::Result<(), crate::ConfigError> {
+        self.0.dyn_validate(bytes_per_row)
+    }
+}
+
+impl Default for DynCharFormatter {
+    fn default() -> Self {
+        Self::new(CharFormatter::default())
+    }
+}
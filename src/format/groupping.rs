@@ -1,5 +1,7 @@
 //! Module contains structures, that describing a byte groupping
 
+use crate::ConfigError;
+
 pub const DEFAULT_BYTES_PER_ROW: usize = 16;
 pub const DEFAULT_GROUP_SIZE: usize = 4;
 pub const DEFAULT_NUMBER_OF_GROUPS: usize = 4;
@@ -15,18 +17,101 @@ pub enum GroupAtomicity {
 
 /// Represents info how bytes will be groupped
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialization::GrouppingRepr", into = "crate::serialization::GrouppingRepr")
+)]
 pub enum Groupping {
     /// Single group with bytes count
     RowWide(usize),
     /// Group with repeat count. `bytes per row` = `group size` * `number of groups`
     RepeatingGroup(Group, usize),
+    /// Groups of the row in order, e.g. `4 | 2 2 | 8`. Separator of each group is printed after it,
+    /// separator of the last group is not used. `bytes per row` = sum of group sizes
+    Pattern(Vec<Group>),
+    /// Nested groups with repeat count of the outermost level, e.g. groups of 2 bytes, inside groups of 8 bytes.
+    /// Levels start from the innermost, size of each level must be a multiple of the previous one.
+    /// Separator of the outermost level, starting at byte, is printed before it.
+    /// `bytes per row` = `outermost level size` * `number of groups`
+    Nested(Vec<Group>, usize),
 }
 
 impl Groupping {
-    pub(crate) fn separator(&self) -> Vec<u8> {
+    /// Checks, that every group is non-empty and levels of [`Groupping::Nested`] are multiples of each other
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let groups: &[Group] = match self {
+            Groupping::RowWide(_) => &[],
+            Groupping::RepeatingGroup(g, _) => std::slice::from_ref(g),
+            Groupping::Pattern(groups) | Groupping::Nested(groups, _) => groups,
+        };
+
+        if let Some(index) = groups.iter().position(|g| g.size == 0) {
+            return Err(ConfigError::EmptyGroup(index));
+        }
+
+        if let Groupping::Nested(levels, _) = self {
+            if let Some(index) = levels.windows(2).position(|w| !w[1].size.is_multiple_of(w[0].size)) {
+                return Err(ConfigError::UnalignedLevel {
+                    index: index + 1,
+                    size: levels[index + 1].size,
+                    inner: levels[index].size,
+                });
+            }
+        }
+
+        match self.bytes_per_row() {
+            0 => Err(ConfigError::EmptyRow),
+            _ => Ok(()),
+        }
+    }
+
+    /// Separator, printed before byte `number`. Empty if group does not start at `number`
+    pub(crate) fn separator_before(&self, number: usize) -> &[u8] {
+        match number != 0 && self.is_aligned_at(number) {
+            true => self.separator_before_group(number),
+            false => &[],
+        }
+    }
+
+    /// Separator, printed before group, starting at non-zero `number`
+    #[inline]
+    pub(crate) fn separator_before_group(&self, number: usize) -> &[u8] {
+        match self {
+            Groupping::RowWide(_) => &[],
+            Groupping::RepeatingGroup(g, _) => &g.separator,
+            Groupping::Pattern(groups) => {
+                let idx = self.group_of_byte(number);
+                &groups[idx - 1].separator
+            }
+            Groupping::Nested(levels, _) => levels
+                .iter()
+                .rev()
+                .find(|l| number.is_multiple_of(l.size))
+                .map_or(&[], |l| &l.separator),
+        }
+    }
+
+    /// Whether group of level above the innermost starts at non-zero `number`. Used for gutters of text column
+    pub(crate) fn is_outer_group_start(&self, number: usize) -> bool {
+        match self {
+            Groupping::Nested(levels, _) => {
+                number != 0
+                    && number < self.bytes_per_row()
+                    && levels.iter().skip(1).any(|l| number.is_multiple_of(l.size))
+            }
+            _ => false,
+        }
+    }
+
+    /// Size of group, starting at `number`
+    #[inline]
+    pub(crate) fn group_size_at(&self, number: usize) -> usize {
         match self {
-            Groupping::RowWide(_) => vec![],
-            Groupping::RepeatingGroup(g, _) => g.separator.clone(),
+            Groupping::RowWide(r) => *r,
+            Groupping::RepeatingGroup(g, _) => g.size,
+            Groupping::Pattern(_) => self.bytes_left_in_group_after(number),
+            Groupping::Nested(..) => self.inner_group_size(),
         }
     }
 
@@ -39,7 +124,9 @@ impl Groupping {
 
         match self {
             Groupping::RowWide(_) => number == 0,
-            Groupping::RepeatingGroup(g, _) => number % g.size == 0,
+            Groupping::RepeatingGroup(g, _) => number.is_multiple_of(g.size),
+            Groupping::Pattern(_) => self.group_bounds(number).0 == number,
+            Groupping::Nested(..) => number.is_multiple_of(self.inner_group_size()),
         }
     }
 
@@ -47,6 +134,8 @@ impl Groupping {
         match self {
             Groupping::RowWide(r) => *r,
             Groupping::RepeatingGroup(g, rep) => g.size * rep,
+            Groupping::Pattern(groups) => groups.iter().map(|g| g.size).sum(),
+            Groupping::Nested(levels, rep) => levels.last().map_or(0, |l| l.size * rep),
         }
     }
 
@@ -56,12 +145,31 @@ impl Groupping {
             "group_of_byte():Trying to exceed maximum row length"
         );
         match self {
-            Groupping::RowWide(_) => 0,
+            Groupping::RowWide(r) => number / r,
             Groupping::RepeatingGroup(g, _) => {
                 let group_size = g.size;
                 let rem = number % group_size;
                 (number - rem) / group_size
             }
+            Groupping::Pattern(groups) => {
+                let mut end = 0;
+                for (idx, g) in groups.iter().enumerate() {
+                    end += g.size;
+                    if number < end {
+                        return idx;
+                    }
+                }
+                groups.len()
+            }
+            Groupping::Nested(..) => number / self.inner_group_size(),
+        }
+    }
+
+    /// Offset of the first byte of group, containing byte `number`. Row length for `number` equal to it
+    pub(crate) fn group_start(&self, number: usize) -> usize {
+        match number == self.bytes_per_row() {
+            true => number,
+            false => self.group_bounds(number).0,
         }
     }
 
@@ -76,13 +184,51 @@ impl Groupping {
                 let group_num = self.group_of_byte(number);
                 g.size - (number - g.size * group_num)
             }
+            Groupping::Pattern(groups) => match number == self.bytes_per_row() {
+                // Next row is started
+                true => groups.first().map_or(0, |g| g.size),
+                false => {
+                    let (_, end) = self.group_bounds(number);
+                    end - number
+                }
+            },
+            Groupping::Nested(..) => {
+                let size = self.inner_group_size();
+                size - number % size
+            }
         }
     }
 
-    pub(crate) fn max_group_size(&self) -> usize {
+    /// Start and end offsets of group, containing byte `number`
+    fn group_bounds(&self, number: usize) -> (usize, usize) {
         match self {
-            Groupping::RowWide(r) => *r,
-            Groupping::RepeatingGroup(g, _) => g.size,
+            Groupping::RowWide(r) => (0, *r),
+            Groupping::RepeatingGroup(g, _) => {
+                let start = number - number % g.size;
+                (start, start + g.size)
+            }
+            Groupping::Pattern(groups) => {
+                let mut start = 0;
+                for g in groups {
+                    if number < start + g.size {
+                        return (start, start + g.size);
+                    }
+                    start += g.size;
+                }
+                (start, start)
+            }
+            Groupping::Nested(..) => {
+                let size = self.inner_group_size();
+                let start = number - number % size;
+                (start, start + size)
+            }
+        }
+    }
+
+    fn inner_group_size(&self) -> usize {
+        match self {
+            Groupping::Nested(levels, _) => levels.first().map_or(0, |l| l.size),
+            _ => self.group_size_at(0),
         }
     }
 }
@@ -95,9 +241,11 @@ impl Default for Groupping {
 
 /// Group of bytes, separated by arbitary printable characters
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Group {
     /// Number of bytes in the group
     pub(super) size: usize,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
     pub(super) separator: Vec<u8>,
 }
 
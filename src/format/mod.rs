@@ -9,19 +9,36 @@ pub use address_fmt::*;
 pub mod byte_fmt;
 pub use byte_fmt::*;
 
+pub mod byte_table;
+pub use byte_table::*;
+
 pub mod char_fmt;
 pub use char_fmt::*;
 
+pub mod dynamic;
+pub use dynamic::*;
+
 pub mod groupping;
 pub use groupping::*;
 
+pub mod placeholder;
+pub use placeholder::*;
+
+mod render;
+use render::*;
+
 use std::io::Result;
 
 /// Used for row offset formatting
 pub trait AddressFormatting {
-    fn format<O: Write>(&self, addr: usize, out: &mut O) -> Result<()>;
+    fn format<O: Write>(&self, addr: u64, out: &mut O) -> Result<()>;
 
     fn separators(&self) -> &Separators;
+
+    /// Checks settings of the formatter before printing. See [`crate::ConfigBuilder`]
+    fn validate(&self) -> std::result::Result<(), crate::ConfigError> {
+        Ok(())
+    }
 }
 
 /// Used for raw bytes formatting
@@ -65,12 +82,22 @@ pub trait CharFormatting {
     fn format_padding<O: Write>(&self, byte_count: usize, out: &mut O) -> Result<()>;
 
     fn separators(&self) -> &Separators;
+
+    /// Checks settings of the formatter against rows of `bytes_per_row` bytes of the byte formatter.
+    /// See [`crate::ConfigBuilder`]
+    fn validate(&self, _bytes_per_row: usize) -> std::result::Result<(), crate::ConfigError> {
+        Ok(())
+    }
 }
 
 /// Column separators, used by [crate::Printer]
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Separators {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
     pub(crate) trailing: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::utf8"))]
     pub(crate) leading: Vec<u8>,
 }
 
@@ -0,0 +1,180 @@
+//! Module with the placeholder of deduplicated rows
+
+use super::*;
+
+const ROWS_FIELD: &str = "{rows}";
+const BYTES_FIELD: &str = "{bytes}";
+const START_FIELD: &str = "{start}";
+const END_FIELD: &str = "{end}";
+const PERIOD_FIELD: &str = "{period}";
+const REPEATS_FIELD: &str = "{repeats}";
+
+const ASTERISK_TEMPLATE: &str = "*";
+const DETAILED_TEMPLATE: &str = "* {rows} rows, {bytes} bytes, {start}..{end}";
+const PERIODIC_TEMPLATE: &str = "* {period}-byte pattern repeated {repeats} times, {start}..{end}";
+const HOLE_TEMPLATE: &str = "* hole, {bytes} bytes, {start}..{end}";
+
+/// Region of duplicated rows, collapsed into a single placeholder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DuplicateRun {
+    /// Number of collapsed rows
+    pub rows: u64,
+    /// Number of collapsed bytes
+    pub bytes: u64,
+    /// Address of the first collapsed byte
+    pub start: u64,
+    /// Address following the last collapsed byte
+    pub end: u64,
+    /// Length of the shortest pattern, which repeats over collapsed bytes
+    pub period: usize,
+    /// Number of pattern repetitions in collapsed bytes
+    pub repeats: u64,
+}
+
+#[derive(Clone)]
+enum Token {
+    Text(Vec<u8>),
+    Rows,
+    Bytes,
+    Start,
+    End,
+    Period,
+    Repeats,
+}
+
+/// Placeholder, printed instead of duplicated rows.
+///
+/// Built from a template, where `{rows}`, `{bytes}`, `{start}`, `{end}`, `{period}` and `{repeats}` are replaced
+/// by the values of [`DuplicateRun`]. Addresses are formatted with the active address formatter.
+///
+/// Default placeholder is a classic `*`, compatible with `hexdump`
+#[derive(Clone)]
+pub struct DedupPlaceholder {
+    tokens: Vec<Token>,
+}
+
+impl DedupPlaceholder {
+    /// Create placeholder from template. Unknown `{...}` sequences are printed as is
+    pub fn new(template: &str) -> Self {
+        let mut tokens = vec![];
+        let mut text = String::new();
+
+        let mut rem = template;
+        while !rem.is_empty() {
+            let field = [
+                (ROWS_FIELD, Token::Rows),
+                (BYTES_FIELD, Token::Bytes),
+                (START_FIELD, Token::Start),
+                (END_FIELD, Token::End),
+                (PERIOD_FIELD, Token::Period),
+                (REPEATS_FIELD, Token::Repeats),
+            ]
+            .into_iter()
+            .find(|(name, _)| rem.starts_with(name));
+
+            match field {
+                Some((name, token)) => {
+                    if !text.is_empty() {
+                        tokens.push(Token::Text(Vec::from(text.as_bytes())));
+                        text.clear();
+                    }
+                    tokens.push(token);
+                    rem = &rem[name.len()..];
+                }
+                None => {
+                    let chr = rem.chars().next().expect("Remaining template is not empty");
+                    text.push(chr);
+                    rem = &rem[chr.len_utf8()..];
+                }
+            }
+        }
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(Vec::from(text.as_bytes())));
+        }
+
+        Self { tokens }
+    }
+
+    /// Classic `*`
+    pub fn asterisk() -> Self {
+        Self::new(ASTERISK_TEMPLATE)
+    }
+
+    /// `* {rows} rows, {bytes} bytes, {start}..{end}`
+    pub fn detailed() -> Self {
+        Self::new(DETAILED_TEMPLATE)
+    }
+
+    /// `* {period}-byte pattern repeated {repeats} times, {start}..{end}`
+    pub fn periodic() -> Self {
+        Self::new(PERIODIC_TEMPLATE)
+    }
+
+    /// `* hole, {bytes} bytes, {start}..{end}`
+    pub fn hole() -> Self {
+        Self::new(HOLE_TEMPLATE)
+    }
+
+    /// Template of the placeholder
+    pub fn template(&self) -> String {
+        let mut template = String::new();
+        for token in self.tokens.iter() {
+            match token {
+                Token::Text(t) => template.push_str(&String::from_utf8_lossy(t)),
+                Token::Rows => template.push_str(ROWS_FIELD),
+                Token::Bytes => template.push_str(BYTES_FIELD),
+                Token::Start => template.push_str(START_FIELD),
+                Token::End => template.push_str(END_FIELD),
+                Token::Period => template.push_str(PERIOD_FIELD),
+                Token::Repeats => template.push_str(REPEATS_FIELD),
+            }
+        }
+
+        template
+    }
+
+    /// Placeholder, that does not depend on [`DuplicateRun`], is printed as soon as the first duplicate
+    /// is found. Otherwise it is printed after the whole run is collapsed
+    pub(crate) fn is_static(&self) -> bool {
+        self.tokens.iter().all(|t| matches!(t, Token::Text(_)))
+    }
+
+    pub(crate) fn format<O: Write, A: AddressFormatting>(
+        &self,
+        run: &DuplicateRun,
+        addr_fmt: Option<&A>,
+        out: &mut O,
+    ) -> Result<()> {
+        for token in self.tokens.iter() {
+            match token {
+                Token::Text(t) => out.write_all(t)?,
+                Token::Rows => write!(out, "{}", run.rows)?,
+                Token::Bytes => write!(out, "{}", run.bytes)?,
+                Token::Start => Self::format_address(run.start, addr_fmt, out)?,
+                Token::End => Self::format_address(run.end, addr_fmt, out)?,
+                Token::Period => write!(out, "{}", run.period)?,
+                Token::Repeats => write!(out, "{}", run.repeats)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn format_address<O: Write, A: AddressFormatting>(
+        addr: u64,
+        addr_fmt: Option<&A>,
+        out: &mut O,
+    ) -> Result<()> {
+        match addr_fmt {
+            Some(fmt) => fmt.format(addr, out),
+            None => AddressFormatter::default().format(addr, out),
+        }
+    }
+}
+
+impl Default for DedupPlaceholder {
+    fn default() -> Self {
+        Self::asterisk()
+    }
+}
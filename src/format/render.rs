@@ -0,0 +1,364 @@
+//! Table-driven rendering of bytes into a stack buffer
+
+use std::io::*;
+
+const RENDER_BUFFER_LEN: usize = 256;
+
+/// Hexadecimal encoding of input is accelerated with SIMD from this length
+const SIMD_MIN_LEN: usize = 16;
+
+const LOWER_HEX: &[u8; 16] = b"0123456789abcdef";
+const UPPER_HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+pub(super) static HEX_LUT: [[u8; 2]; 256] = hex_lut(LOWER_HEX);
+pub(super) static UPPER_HEX_LUT: [[u8; 2]; 256] = hex_lut(UPPER_HEX);
+pub(super) static C_ESCAPE_LUT: [[u8; 4]; 256] = escape_lut(b"\\x", LOWER_HEX);
+pub(super) static PERCENT_LUT: [[u8; 3]; 256] = escape_lut(b"%", UPPER_HEX);
+pub(super) static BIN_LUT: [[u8; 8]; 256] = bin_lut();
+pub(super) static DEC_LUT: [[u8; 3]; 256] = dec_lut();
+pub(super) static OCT_LUT: [[u8; 3]; 256] = oct_lut();
+
+const fn hex_lut(alphabet: &[u8; 16]) -> [[u8; 2]; 256] {
+    escape_lut(b"", alphabet)
+}
+
+/// Hexadecimal representation after `P` bytes of `prefix`
+const fn escape_lut<const P: usize, const N: usize>(prefix: &[u8; P], alphabet: &[u8; 16]) -> [[u8; N]; 256] {
+    assert!(N == P + 2, "Wrong escape sequence length");
+
+    let mut lut = [[0u8; N]; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut j = 0;
+        while j < P {
+            lut[i][j] = prefix[j];
+            j += 1;
+        }
+        lut[i][P] = alphabet[i >> 4];
+        lut[i][P + 1] = alphabet[i & 0x0f];
+        i += 1;
+    }
+    lut
+}
+
+const fn bin_lut() -> [[u8; 8]; 256] {
+    let mut lut = [[0u8; 8]; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut bit = 0;
+        while bit < 8 {
+            lut[i][bit] = b'0' + ((i >> (7 - bit)) & 1) as u8;
+            bit += 1;
+        }
+        i += 1;
+    }
+    lut
+}
+
+/// Right-aligned, padded with spaces
+const fn dec_lut() -> [[u8; 3]; 256] {
+    let mut lut = [[b' '; 3]; 256];
+    let mut i = 0;
+    while i < 256 {
+        lut[i][2] = b'0' + (i % 10) as u8;
+        if i >= 10 {
+            lut[i][1] = b'0' + (i / 10 % 10) as u8;
+        }
+        if i >= 100 {
+            lut[i][0] = b'0' + (i / 100) as u8;
+        }
+        i += 1;
+    }
+    lut
+}
+
+/// Padded with zeros
+const fn oct_lut() -> [[u8; 3]; 256] {
+    let mut lut = [[0u8; 3]; 256];
+    let mut i = 0;
+    while i < 256 {
+        lut[i] = [
+            b'0' + (i >> 6) as u8,
+            b'0' + ((i >> 3) & 7) as u8,
+            b'0' + (i & 7) as u8,
+        ];
+        i += 1;
+    }
+    lut
+}
+
+/// Collects rendered bytes and writes them to output by large portions
+pub(super) struct RenderBuffer<'a, O: Write> {
+    out: &'a mut O,
+    buf: [u8; RENDER_BUFFER_LEN],
+    len: usize,
+}
+
+impl<'a, O: Write> RenderBuffer<'a, O> {
+    pub(super) fn new(out: &'a mut O) -> Self {
+        Self {
+            out,
+            buf: [0u8; RENDER_BUFFER_LEN],
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub(super) fn push(&mut self, bytes: &[u8]) -> Result<()> {
+        match bytes {
+            [] => Ok(()),
+            [byte] => {
+                self.reserve(1)?[0] = *byte;
+                Ok(())
+            }
+            _ if bytes.len() > RENDER_BUFFER_LEN => {
+                self.flush()?;
+                self.out.write_all(bytes)
+            }
+            _ => {
+                self.reserve(bytes.len())?.copy_from_slice(bytes);
+                Ok(())
+            }
+        }
+    }
+
+    /// Pushes hexadecimal representation of `bytes` without separators
+    #[inline]
+    pub(super) fn push_hex(&mut self, bytes: &[u8], case: HexCase) -> Result<()> {
+        if bytes.len() < SIMD_MIN_LEN {
+            return self.push_table(bytes, case.lut());
+        }
+
+        for part in bytes.chunks(RENDER_BUFFER_LEN / 2) {
+            let dst = self.reserve(2 * part.len())?;
+            encode_hex(part, dst, case);
+        }
+
+        Ok(())
+    }
+
+    /// Pushes representation of each byte from the table
+    #[inline]
+    pub(super) fn push_table<const N: usize>(&mut self, bytes: &[u8], lut: &[[u8; N]; 256]) -> Result<()> {
+        for part in bytes.chunks(RENDER_BUFFER_LEN / N) {
+            let dst = self.reserve(N * part.len())?;
+            for (byte, chars) in part.iter().zip(dst.chunks_exact_mut(N)) {
+                chars.copy_from_slice(&lut[*byte as usize]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes all collected bytes
+    pub(super) fn flush(&mut self) -> Result<()> {
+        if self.len != 0 {
+            self.out.write_all(&self.buf[..self.len])?;
+            self.len = 0;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn reserve(&mut self, len: usize) -> Result<&mut [u8]> {
+        if self.len + len > RENDER_BUFFER_LEN {
+            self.flush()?;
+        }
+
+        let start = self.len;
+        self.len += len;
+        Ok(&mut self.buf[start..self.len])
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum HexCase {
+    Lower,
+    Upper,
+}
+
+impl HexCase {
+    fn alphabet(self) -> &'static [u8; 16] {
+        match self {
+            HexCase::Lower => LOWER_HEX,
+            HexCase::Upper => UPPER_HEX,
+        }
+    }
+
+    fn lut(self) -> &'static [[u8; 2]; 256] {
+        match self {
+            HexCase::Lower => &HEX_LUT,
+            HexCase::Upper => &UPPER_HEX_LUT,
+        }
+    }
+
+    /// Distance between `'9' + 1` and the first letter
+    #[cfg(target_arch = "x86_64")]
+    fn letters_offset(self) -> u8 {
+        self.alphabet()[10] - b'0' - 10
+    }
+}
+
+/// Hexadecimal representation of `src`. `dst` must be twice as long
+pub(super) fn encode_hex(src: &[u8], dst: &mut [u8], case: HexCase) {
+    assert_eq!(dst.len(), 2 * src.len(), "Wrong hex destination length");
+
+    if src.len() < SIMD_MIN_LEN {
+        return encode_hex_scalar(src, dst, case);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::arch::is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support is checked above
+            return unsafe { x86::encode_hex_avx2(src, dst, case) };
+        }
+        // SAFETY: SSE2 is a part of x86_64 baseline
+        unsafe { x86::encode_hex_sse2(src, dst, case) }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is a part of aarch64 baseline
+        unsafe { arm::encode_hex_neon(src, dst, case) }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    encode_hex_scalar(src, dst, case)
+}
+
+fn encode_hex_scalar(src: &[u8], dst: &mut [u8], case: HexCase) {
+    let lut = case.lut();
+    for (byte, chars) in src.iter().zip(dst.chunks_exact_mut(2)) {
+        chars.copy_from_slice(&lut[*byte as usize]);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::HexCase;
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn encode_hex_sse2(src: &[u8], dst: &mut [u8], case: HexCase) {
+        let mask = _mm_set1_epi8(0x0f);
+        let zero = _mm_set1_epi8(b'0' as i8);
+        let nine = _mm_set1_epi8(9);
+        let letters = _mm_set1_epi8(case.letters_offset() as i8);
+
+        let to_ascii = |n: __m128i| {
+            let above_nine = _mm_and_si128(_mm_cmpgt_epi8(n, nine), letters);
+            _mm_add_epi8(_mm_add_epi8(n, zero), above_nine)
+        };
+
+        let mut chunks = src.chunks_exact(16);
+        let mut dst_chunks = dst.chunks_exact_mut(32);
+        for (chunk, out) in (&mut chunks).zip(&mut dst_chunks) {
+            let v = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            let hi = to_ascii(_mm_and_si128(_mm_srli_epi16(v, 4), mask));
+            let lo = to_ascii(_mm_and_si128(v, mask));
+
+            let out = out.as_mut_ptr() as *mut __m128i;
+            _mm_storeu_si128(out, _mm_unpacklo_epi8(hi, lo));
+            _mm_storeu_si128(out.add(1), _mm_unpackhi_epi8(hi, lo));
+        }
+
+        super::encode_hex_scalar(chunks.remainder(), dst_chunks.into_remainder(), case);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn encode_hex_avx2(src: &[u8], dst: &mut [u8], case: HexCase) {
+        let mask = _mm256_set1_epi8(0x0f);
+        let zero = _mm256_set1_epi8(b'0' as i8);
+        let nine = _mm256_set1_epi8(9);
+        let letters = _mm256_set1_epi8(case.letters_offset() as i8);
+
+        let to_ascii = |n: __m256i| {
+            let above_nine = _mm256_and_si256(_mm256_cmpgt_epi8(n, nine), letters);
+            _mm256_add_epi8(_mm256_add_epi8(n, zero), above_nine)
+        };
+
+        let mut chunks = src.chunks_exact(32);
+        let mut dst_chunks = dst.chunks_exact_mut(64);
+        for (chunk, out) in (&mut chunks).zip(&mut dst_chunks) {
+            let v = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+            let hi = to_ascii(_mm256_and_si256(_mm256_srli_epi16(v, 4), mask));
+            let lo = to_ascii(_mm256_and_si256(v, mask));
+
+            // Unpacking interleaves within 128-bit lanes
+            let first = _mm256_unpacklo_epi8(hi, lo);
+            let second = _mm256_unpackhi_epi8(hi, lo);
+
+            let out = out.as_mut_ptr() as *mut __m256i;
+            _mm256_storeu_si256(out, _mm256_permute2x128_si256(first, second, 0x20));
+            _mm256_storeu_si256(out.add(1), _mm256_permute2x128_si256(first, second, 0x31));
+        }
+
+        encode_hex_sse2(chunks.remainder(), dst_chunks.into_remainder(), case);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arm {
+    use std::arch::aarch64::*;
+
+    use super::HexCase;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn encode_hex_neon(src: &[u8], dst: &mut [u8], case: HexCase) {
+        let table = vld1q_u8(case.alphabet().as_ptr());
+        let mask = vdupq_n_u8(0x0f);
+
+        let mut chunks = src.chunks_exact(16);
+        let mut dst_chunks = dst.chunks_exact_mut(32);
+        for (chunk, out) in (&mut chunks).zip(&mut dst_chunks) {
+            let v = vld1q_u8(chunk.as_ptr());
+            let hi = vqtbl1q_u8(table, vshrq_n_u8::<4>(v));
+            let lo = vqtbl1q_u8(table, vandq_u8(v, mask));
+
+            vst2q_u8(out.as_mut_ptr(), uint8x16x2_t(hi, lo));
+        }
+
+        super::encode_hex_scalar(chunks.remainder(), dst_chunks.into_remainder(), case);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables() {
+        for byte in 0..=255u8 {
+            assert_eq!(HEX_LUT[byte as usize], format!("{byte:02x}").as_bytes());
+            assert_eq!(UPPER_HEX_LUT[byte as usize], format!("{byte:02X}").as_bytes());
+            assert_eq!(C_ESCAPE_LUT[byte as usize], format!("\\x{byte:02x}").as_bytes());
+            assert_eq!(PERCENT_LUT[byte as usize], format!("%{byte:02X}").as_bytes());
+            assert_eq!(BIN_LUT[byte as usize], format!("{byte:08b}").as_bytes());
+            assert_eq!(DEC_LUT[byte as usize], format!("{byte:3}").as_bytes());
+            assert_eq!(OCT_LUT[byte as usize], format!("{byte:03o}").as_bytes());
+        }
+    }
+
+    #[test]
+    fn hex_encoding() {
+        let src: Vec<u8> = (0..=255u8).chain((0..=255u8).rev()).collect();
+
+        for len in [0, 1, 15, 16, 17, 31, 32, 33, 63, 64, 100, src.len()] {
+            for offset in [0, 1, 7] {
+                let part = &src[offset..offset + len.min(src.len() - offset)];
+
+                let mut dst = vec![0u8; 2 * part.len()];
+                encode_hex(part, &mut dst, HexCase::Lower);
+
+                let expected: String = part.iter().map(|b| format!("{b:02x}")).collect();
+                assert_eq!(String::from_utf8(dst.clone()).unwrap(), expected);
+
+                encode_hex(part, &mut dst, HexCase::Upper);
+                assert_eq!(String::from_utf8(dst).unwrap(), expected.to_uppercase());
+            }
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Wrappers, which dump all data passing through readers and writers.
+//!
+//! [`InspectReader`] and [`InspectWriter`] pass data through untouched and send it to [`InspectSink`]:
+//! [`Printer`] prints a continuous dump, [`MarkedPrinter`] prints a separate block for every call,
+//! and a closure receives raw bytes of every call with their address
+
+use std::io::*;
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+/// Which call passed inspected bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Bytes, returned by `read`
+    Read,
+    /// Bytes, accepted by `write`
+    Write,
+}
+
+impl Direction {
+    fn name(&self) -> &'static str {
+        match self {
+            Direction::Read => "read",
+            Direction::Write => "write",
+        }
+    }
+
+    /// Marker of [`Conversation`] chunks
+    pub(crate) fn marker(&self) -> char {
+        match self {
+            Direction::Read => '<',
+            Direction::Write => '>',
+        }
+    }
+}
+
+/// Receiver of bytes, passed through [`InspectReader`] or [`InspectWriter`]
+pub trait InspectSink {
+    /// `bytes` of a single call, where the first byte has `address`
+    fn inspect(&mut self, direction: Direction, address: u64, bytes: &[u8]) -> Result<()>;
+}
+
+/// Continuous dump. Rows may contain bytes of several calls. Address is tracked by the printer itself
+impl<O: Write, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> InspectSink
+    for Printer<O, A, B, C>
+{
+    fn inspect(&mut self, _: Direction, _: u64, bytes: &[u8]) -> Result<()> {
+        self.push(bytes).map(|_| ())
+    }
+}
+
+impl<F: FnMut(Direction, u64, &[u8]) -> Result<()>> InspectSink for F {
+    fn inspect(&mut self, direction: Direction, address: u64, bytes: &[u8]) -> Result<()> {
+        self(direction, address, bytes)
+    }
+}
+
+/// Sink, shared by several wrappers, for example by both halves of a socket
+impl<S: InspectSink> InspectSink for Arc<Mutex<S>> {
+    fn inspect(&mut self, direction: Direction, address: u64, bytes: &[u8]) -> Result<()> {
+        match self.lock() {
+            Ok(mut sink) => sink.inspect(direction, address, bytes),
+            Err(_) => Err(Error::other("Inspection sink is poisoned")),
+        }
+    }
+}
+
+/// Dump, where bytes of every call are printed as a separate block after the marker line, like
+///
+/// ```text
+/// -- write, 5 bytes
+/// 00000010 68656c6c 6f...... ........ ........ |hello           |
+/// 00000015
+/// ```
+///
+/// Deduplication does not cross block boundaries
+pub struct MarkedPrinter<O: Write, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> {
+    out: O,
+    config: Config<A, B, C>,
+}
+
+impl<O: Write, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone>
+    MarkedPrinter<O, A, B, C>
+{
+    /// Fails with [`ConfigError`], if `config` is rejected by [`Config::validate`]
+    pub fn try_new(out: O, config: Config<A, B, C>) -> std::result::Result<MarkedPrinter<O, A, B, C>, ConfigError> {
+        config.validate()?;
+        Ok(Self { out, config })
+    }
+
+    /// Same as [`MarkedPrinter::try_new`], for configs known to be valid, like built by [`ConfigBuilder::build`](crate::ConfigBuilder::build)
+    ///
+    /// ## Panics
+    /// If `config` is rejected by [`Config::validate`]
+    #[track_caller]
+    pub fn new(out: O, config: Config<A, B, C>) -> MarkedPrinter<O, A, B, C> {
+        match Self::try_new(out, config) {
+            Ok(printer) => printer,
+            Err(e) => panic!("Invalid config: {e}"),
+        }
+    }
+
+    /// Turns back the output
+    pub fn into_inner(self) -> O {
+        self.out
+    }
+}
+
+impl<O: Write, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> InspectSink
+    for MarkedPrinter<O, A, B, C>
+{
+    fn inspect(&mut self, direction: Direction, address: u64, bytes: &[u8]) -> Result<()> {
+        writeln!(self.out, "-- {}, {} bytes", direction.name(), bytes.len())?;
+
+        let mut streamer = Streamer::new(self.config.clone(), address);
+        streamer.push(bytes, &mut self.out)?;
+        streamer.write_tail(&mut self.out)
+    }
+}
+
+/// Inspection state, shared by the reader and the writer
+struct Inspection<S: InspectSink> {
+    sink: S,
+    address: u64,
+    /// The first error of sink. Inspection is stopped after it
+    error: Option<Error>,
+}
+
+impl<S: InspectSink> Inspection<S> {
+    fn pass(&mut self, direction: Direction, bytes: &[u8]) {
+        if self.error.is_some() || bytes.is_empty() {
+            return;
+        }
+
+        let result = streamer::advance_address(self.address, bytes.len() as u64)
+            .and_then(|next| self.sink.inspect(direction, self.address, bytes).map(|_| next));
+
+        match result {
+            Ok(next) => self.address = next,
+            Err(e) => self.error = Some(e),
+        }
+    }
+}
+
+/// Reader, which sends all read bytes to `sink`.
+///
+/// Errors of sink do not affect reading: the first error is kept (see [`InspectReader::sink_error`])
+/// and inspection is stopped
+pub struct InspectReader<R: Read, S: InspectSink> {
+    inner: R,
+    inspection: Inspection<S>,
+}
+
+impl<R: Read, S: InspectSink> InspectReader<R, S> {
+    pub fn new(inner: R, sink: S) -> Self {
+        Self {
+            inner,
+            inspection: Inspection {
+                sink,
+                address: 0,
+                error: None,
+            },
+        }
+    }
+
+    /// Address of the first read byte (`0` by default)
+    pub fn with_start_address(mut self, address: u64) -> Self {
+        self.inspection.address = address;
+        self
+    }
+
+    /// The first error of sink
+    pub fn sink_error(&self) -> Option<&Error> {
+        self.inspection.error.as_ref()
+    }
+
+    /// Turns back the reader and the sink
+    pub fn into_parts(self) -> (R, S) {
+        (self.inner, self.inspection.sink)
+    }
+}
+
+impl<R: Read, S: InspectSink> Read for InspectReader<R, S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.inspection.pass(Direction::Read, &buf[..len]);
+
+        Ok(len)
+    }
+}
+
+/// Writer, which sends all written bytes to `sink`.
+///
+/// Errors of sink do not affect writing: the first error is kept (see [`InspectWriter::sink_error`])
+/// and inspection is stopped
+pub struct InspectWriter<W: Write, S: InspectSink> {
+    inner: W,
+    inspection: Inspection<S>,
+}
+
+impl<W: Write, S: InspectSink> InspectWriter<W, S> {
+    pub fn new(inner: W, sink: S) -> Self {
+        Self {
+            inner,
+            inspection: Inspection {
+                sink,
+                address: 0,
+                error: None,
+            },
+        }
+    }
+
+    /// Address of the first written byte (`0` by default)
+    pub fn with_start_address(mut self, address: u64) -> Self {
+        self.inspection.address = address;
+        self
+    }
+
+    /// The first error of sink
+    pub fn sink_error(&self) -> Option<&Error> {
+        self.inspection.error.as_ref()
+    }
+
+    /// Turns back the writer and the sink
+    pub fn into_parts(self) -> (W, S) {
+        (self.inner, self.inspection.sink)
+    }
+}
+
+impl<W: Write, S: InspectSink> Write for InspectWriter<W, S> {
+    /// Only bytes, accepted by the inner writer, are inspected
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.inspection.pass(Direction::Write, &buf[..len]);
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fmt = Config<AddressFormatter, ByteFormatter, CharFormatter>;
+
+    #[test]
+    fn pass_through() {
+        let data = std::fs::read("testable/lorem_ipsum").expect("Could not open testable data");
+
+        let mut expected = vec![];
+        let mut printer = Printer::default_fmt_with(&mut expected, 0x10);
+        _ = printer.push(&data);
+        printer.finish();
+
+        let mut reader = InspectReader::new(&data[..], Printer::default_fmt_with(vec![], 0x10));
+        let mut writer = InspectWriter::new(vec![], Printer::default_fmt_with(vec![], 0x10));
+        let mut buf = [0u8; 7];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                len => writer.write_all(&buf[..len]).unwrap(),
+            }
+        }
+
+        let (_, printer) = reader.into_parts();
+        assert!(printer.finish() == expected);
+
+        let (written, printer) = writer.into_parts();
+        assert!(written == data);
+        assert!(printer.finish() == expected);
+    }
+
+    #[test]
+    fn marked_calls() {
+        let config = Fmt::new(
+            Some(Default::default()),
+            ByteFormatter::new(Default::default(), Groupping::RowWide(4), "", false, Default::default()),
+            None,
+            true,
+        );
+
+        let mut writer = InspectWriter::new(vec![], MarkedPrinter::new(vec![], config)).with_start_address(0x10);
+        writer.write_all(b"hello").unwrap();
+        writer.write_all(b", world").unwrap();
+
+        let (written, printer) = writer.into_parts();
+        assert_eq!(written, b"hello, world");
+
+        let expected = "-- write, 5 bytes\n\
+                        00000010 68656c6c \n\
+                        00000014 6f...... \n\
+                        00000015 \n\
+                        -- write, 7 bytes\n\
+                        00000015 2c20776f \n\
+                        00000019 726c64.. \n\
+                        0000001c \n";
+        assert_eq!(String::from_utf8(printer.into_inner()).unwrap(), expected);
+
+        let mut calls = vec![];
+        let mut reader = InspectReader::new(&b"abc"[..], |direction, address, bytes: &[u8]| {
+            calls.push((direction, address, bytes.to_vec()));
+            Err(Error::other("Sink is closed"))
+        });
+        let mut buf = [0u8; 2];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert!(reader.sink_error().is_some());
+
+        drop(reader);
+        assert_eq!(calls, [(Direction::Read, 0, b"ab".to_vec())]);
+    }
+}
@@ -12,6 +12,31 @@ pub use format::*;
 mod streamer;
 use streamer::*;
 
+mod periodic;
+
+#[cfg(feature = "serde")]
+mod serialization;
+
+pub mod parallel;
+pub use parallel::*;
+
+pub mod reader;
+pub use reader::*;
+
+pub mod rows;
+pub use rows::*;
+
+pub mod inspect;
+pub use inspect::*;
+
+pub mod conversation;
+pub use conversation::*;
+
+#[cfg(any(feature = "log", feature = "tracing"))]
+pub mod logging;
+#[cfg(any(feature = "log", feature = "tracing"))]
+pub use logging::*;
+
 const OUTPUT_LOST_MESSAGE: &str = "Somewhere we lost the output";
 
 /// The topmost struct for data output
@@ -42,23 +67,36 @@ impl<
     ///
     /// `out` - place to ouput string.
     ///
-    /// `start_address` - start address to print.
+    /// `start_address` - start address to print. Addresses are 64-bit on every platform,
+    /// pushing bytes beyond `u64::MAX` address fails with [`ErrorKind::InvalidInput`].
     ///
-    /// `config` - formatting configuration.
+    /// `config` - formatting configuration. Fails with [`ConfigError`], if it is rejected by [`Config::validate`].
     ///
     /// `Printer` does no assumptions on `start_address` where to start reading data,
     /// it just recieving data chunks in `push(...)` function, then increments the `start_address`
-    pub fn new(out: O, start_address: usize, config: Config<A, B, C>) -> Printer<O, A, B, C> {
-        Printer {
+    pub fn try_new(
+        out: O,
+        start_address: u64,
+        config: Config<A, B, C>,
+    ) -> std::result::Result<Printer<O, A, B, C>, ConfigError> {
+        config.validate()?;
+
+        Ok(Printer {
             out: Some(out),
-            streamer: Streamer::new(
-                config.addr,
-                config.byte,
-                config.text,
-                start_address,
-                config.dedup_enabled,
-            ),
+            streamer: Streamer::new(config, start_address),
             is_finished: false,
+        })
+    }
+
+    /// Same as [`Printer::try_new`], for configs known to be valid, like built by [`ConfigBuilder::build`](crate::ConfigBuilder::build)
+    ///
+    /// ## Panics
+    /// If `config` is rejected by [`Config::validate`]
+    #[track_caller]
+    pub fn new(out: O, start_address: u64, config: Config<A, B, C>) -> Printer<O, A, B, C> {
+        match Self::try_new(out, start_address, config) {
+            Ok(printer) => printer,
+            Err(e) => panic!("Invalid config: {e}"),
         }
     }
 
@@ -77,16 +115,34 @@ impl<
         C: CharFormatting + Clone,
     > Printer<O, A, B, C>
 {
-    /// Accepts bytes chunk. Immediately prints `first` and `second` columns to `out`,
+    /// Accepts bytes chunk. Every row is written to `out` with a single write as soon as it is complete,
+    /// incomplete row is printed after finalization.
+    ///
+    /// With [`Config::with_immediate_output`] `first` and `second` columns of incomplete row are printed immediately,
     /// `third` will printed after `second` column is completely filled, or after finalization.
     pub fn push(&mut self, bytes: &[u8]) -> Result<usize> {
         let mut out = self.out.take().expect(OUTPUT_LOST_MESSAGE);
 
-        self.streamer.push(bytes, &mut out)?;
+        let result = self.streamer.push(bytes, &mut out);
 
         self.out = Some(out);
 
-        Ok(bytes.len())
+        result.map(|_| bytes.len())
+    }
+
+    /// Accounts `len` zero bytes without passing them. Useful for holes of sparse files.
+    ///
+    /// Address and deduplication state are updated as if zeros were pushed,
+    /// but whole rows of zeros are printed as single line with the hole range
+    /// (see [`Config::with_hole_placeholder`]). Zeros in incomplete rows at the edges of hole are printed as usual.
+    pub fn push_hole(&mut self, len: u64) -> Result<()> {
+        let mut out = self.out.take().expect(OUTPUT_LOST_MESSAGE);
+
+        let result = self.streamer.push_hole(len, &mut out);
+
+        self.out = Some(out);
+
+        result
     }
 
     fn print_last_line(&mut self) -> Result<()> {
@@ -111,7 +167,7 @@ impl<
         C: CharFormatting + Clone + Default,
     > Printer<O, A, B, C>
 {
-    pub fn default_with(out: O, start_address: usize) -> Printer<O, A, B, C> {
+    pub fn default_with(out: O, start_address: u64) -> Printer<O, A, B, C> {
         Self::new(out, start_address, Config::<A, B, C>::default())
     }
 }
@@ -119,7 +175,7 @@ impl<
 impl<O: Write> Printer<O, AddressFormatter, ByteFormatter, CharFormatter> {
     pub fn default_fmt_with(
         out: O,
-        start_address: usize,
+        start_address: u64,
     ) -> Printer<O, AddressFormatter, ByteFormatter, CharFormatter> {
         Self::new(
             out,
@@ -129,6 +185,22 @@ impl<O: Write> Printer<O, AddressFormatter, ByteFormatter, CharFormatter> {
     }
 }
 
+/// Printer with output and formatters, chosen at runtime.
+///
+/// ```
+/// use kex::*;
+///
+/// let address = match std::env::args().count() {
+///     1 => DynAddressFormatter::new(AddressFormatter::new(AddressStyle::Dec(4), Default::default())),
+///     _ => DynAddressFormatter::default(),
+/// };
+/// let config = DynConfig::new(Some(address), Default::default(), None, true);
+///
+/// let mut printer: DynPrinter = Printer::new(Box::new(std::io::sink()), 0, config);
+/// printer.push(b"bytes").unwrap();
+/// ```
+pub type DynPrinter<'a> = Printer<Box<dyn Write + 'a>, DynAddressFormatter, DynByteFormatter, DynCharFormatter>;
+
 impl<
         O: Write,
         A: AddressFormatting + Clone,
@@ -231,6 +303,7 @@ mod test {
         }
     }
 
+    #[allow(clippy::len_zero, clippy::unused_io_amount)]
     fn string_with(bytes: &[u8], read_len_pattern: Vec<usize>) -> String {
         use std::cmp::min;
 
@@ -273,6 +346,323 @@ mod test {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn detailed_placeholder() {
+        let config = Config::<AddressFormatter, ByteFormatter, CharFormatter>::default()
+            .with_placeholder(DedupPlaceholder::detailed());
+        let mut printer = Printer::new(vec![], 0x100, config);
+
+        _ = printer.push(&[0u8; 64]);
+        _ = printer.push(b"Simple printing!");
+        _ = printer.push(&[0u8; 20]);
+
+        let result = String::from_utf8(printer.finish()).expect("Invalid characters in result");
+        let expected = "00000100 00000000 00000000 00000000 00000000 |................|
+* 3 rows, 48 bytes, 00000110..00000140
+00000140 53696d70 6c652070 72696e74 696e6721 |Simple printing!|
+00000150 00000000 00000000 00000000 00000000 |................|
+00000160 00000000 ........ ........ ........ |....            |
+00000164 \n";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn duplicated_tail() {
+        let mut printer = Printer::default_fmt_with(vec![], 0);
+
+        _ = printer.push(&[0u8; 36]);
+
+        let result = String::from_utf8(printer.finish()).expect("Invalid characters in result");
+        let expected = "00000000 00000000 00000000 00000000 00000000 |................|
+*
+00000020 00000000 ........ ........ ........ |....            |
+00000024 \n";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn periodic_deduplication() {
+        let config = Config::<AddressFormatter, ByteFormatter, CharFormatter>::default()
+            .with_placeholder(DedupPlaceholder::periodic())
+            .with_max_dedup_period(4);
+
+        let structure: Vec<u8> = (0u8..24).collect();
+        let mut data = b"Header of stream".to_vec();
+        for _ in 0..10 {
+            data.extend_from_slice(&structure);
+        }
+        data.extend_from_slice(b"tail");
+
+        let expected = "00000000 48656164 6572206f 66207374 7265616d |Header of stream|
+00000010 00010203 04050607 08090a0b 0c0d0e0f |................|
+00000020 10111213 14151617 00010203 04050607 |................|
+00000030 08090a0b 0c0d0e0f 10111213 14151617 |................|
+* 24-byte pattern repeated 8 times, 00000040..00000100
+00000100 7461696c ........ ........ ........ |tail            |
+00000104 \n";
+
+        for chunk_size in [1, 5, 16, 100] {
+            let mut printer = Printer::new(vec![], 0, config.clone());
+            for chunk in data.chunks(chunk_size) {
+                _ = printer.push(chunk);
+            }
+
+            let result = String::from_utf8(printer.finish()).expect("Invalid characters in result");
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn interrupted_period() {
+        let config = Config::<AddressFormatter, ByteFormatter, CharFormatter>::default()
+            .with_max_dedup_period(3);
+        let mut printer = Printer::new(vec![], 0, config);
+
+        for row in [b'a', b'b', b'a', b'b', b'a', b'c', b'c'] {
+            _ = printer.push(&[row; 16]);
+        }
+
+        let result = String::from_utf8(printer.finish()).expect("Invalid characters in result");
+        let expected = "00000000 61616161 61616161 61616161 61616161 |aaaaaaaaaaaaaaaa|
+00000010 62626262 62626262 62626262 62626262 |bbbbbbbbbbbbbbbb|
+*
+00000040 61616161 61616161 61616161 61616161 |aaaaaaaaaaaaaaaa|
+00000050 63636363 63636363 63636363 63636363 |cccccccccccccccc|
+*
+00000070 \n";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn hole() {
+        let mut printer = Printer::default_fmt_with(vec![], 0);
+
+        _ = printer.push(b"Simple");
+        _ = printer.push_hole(0x1000);
+        _ = printer.push(&[0u8; 32]);
+        _ = printer.push(b"printing");
+        _ = printer.push_hole(12);
+
+        let result = String::from_utf8(printer.finish()).expect("Invalid characters in result");
+        let expected = "00000000 53696d70 6c650000 00000000 00000000 |Simple..........|
+* hole, 4080 bytes, 00000010..00001000
+*
+00001020 00000000 00007072 696e7469 6e670000 |......printing..|
+00001030 00000000 00000000 0000.... ........ |..........      |
+0000103a \n";
+
+        assert_eq!(result, expected);
+
+        // Holes are indistinguishable from zeros with deduplication
+        for config in [Config::default(), Config::default().with_max_dedup_period(4)] {
+            let mut zeros = Printer::new(vec![], 0, config.clone().with_hole_placeholder(DedupPlaceholder::asterisk()));
+            _ = zeros.push(&[0u8; 16]);
+            _ = zeros.push_hole(100);
+            _ = zeros.push(b"simple");
+
+            let mut pushed: Printer<_, AddressFormatter, ByteFormatter, CharFormatter> = Printer::new(vec![], 0, config);
+            _ = pushed.push(&[0u8; 116]);
+            _ = pushed.push(b"simple");
+
+            assert_eq!(zeros.finish(), pushed.finish());
+        }
+    }
+
+    #[test]
+    fn address_wraparound() {
+        let fmt = AddressFormatter::new(AddressStyle::Hex(16), Default::default());
+        let config = Config::<_, ByteFormatter, CharFormatter>::new(Some(fmt), Default::default(), None, false);
+
+        let mut printer = Printer::new(Vec::<u8>::new(), u64::MAX - 20, config);
+        assert!(printer.push(&[0u8; 16]).is_ok());
+        assert!(printer.push_hole(4).is_ok());
+
+        assert_eq!(printer.push(&[0u8; 2]).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(printer.push_hole(2).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let expected = "ffffffffffffffeb 00000000 00000000 00000000 00000000 \n\
+                        fffffffffffffffb 00000000 ........ ........ ........ \n\
+                        ffffffffffffffff \n";
+        assert_eq!(String::from_utf8(printer.finish()).unwrap(), expected);
+    }
+
+    #[test]
+    fn address_columns() {
+        let fmt = |style| AddressFormatter::new(style, Separators::new("", " "));
+        let config = Config::<_, ByteFormatter, CharFormatter>::new(
+            Some(fmt(AddressStyle::Dec(2))),
+            ByteFormatter::new(Default::default(), Groupping::RowWide(4), "", false, Default::default()),
+            None,
+            true,
+        )
+        .with_address_column(AddressKind::Based(0x1000), fmt(AddressStyle::Hex(4)))
+        .with_address_column(AddressKind::RowIndex, fmt(AddressStyle::Dec(1)))
+        .with_placeholder(DedupPlaceholder::new("* {start}..{end}"));
+
+        let mut printer = Printer::new(Vec::<u8>::new(), 10, config);
+        assert!(printer.push(b"head\0\0\0\0\0\0\0\0\0\0\0\0tail!").is_ok());
+
+        let expected = "10 1000 0 68656164 \n\
+                        14 1004 1 00000000 \n\
+                        * 18..26\n\
+                        26 1010 4 7461696c \n\
+                        30 1014 5 21...... \n\
+                        31 1015 5 \n";
+        assert_eq!(String::from_utf8(printer.finish()).unwrap(), expected);
+    }
+
+    #[test]
+    fn dynamic_formatters() {
+        let data = std::fs::read("testable/duplications").expect("Could not open testable data");
+
+        let mut expected = vec![];
+        let mut printer = Printer::default_fmt_with(&mut expected, 0x20);
+        _ = printer.push(&data);
+        printer.finish();
+
+        let mut result = vec![];
+        let config = DynConfig::new(
+            Some(DynAddressFormatter::new(AddressFormatter::default())),
+            DynByteFormatter::new(ByteFormatter::default()),
+            Some(DynCharFormatter::new(CharFormatter::default())),
+            true,
+        );
+        let mut printer: DynPrinter = Printer::new(Box::new(&mut result), 0x20, config);
+        _ = printer.push(&data);
+        printer.finish();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn irregular_groups() {
+        let groupings = [
+            (Groupping::RowWide(6), "00000000 53696d706c65 \n00000006 2070........ \n00000008 \n"),
+            (
+                Groupping::Pattern(vec![Group::new(1, "|"), Group::new(3, " "), Group::new(2, "")]),
+                "00000000 53|696d70 6c65 \n00000006 20|70.... .... \n00000008 \n",
+            ),
+        ];
+
+        for (groupping, expected) in groupings {
+            let fmt = ByteFormatter::new(Default::default(), groupping, "", false, Default::default());
+            let config = Config::<AddressFormatter, _, CharFormatter>::new(Some(Default::default()), fmt, None, false);
+
+            let mut printer = Printer::new(Vec::<u8>::new(), 0, config);
+            for byte in b"Simple p" {
+                _ = printer.push(&[*byte]);
+            }
+
+            assert_eq!(String::from_utf8(printer.finish()).unwrap(), expected);
+        }
+    }
+
+    /// Collects every write separately
+    #[derive(Default)]
+    struct Writes(Vec<Vec<u8>>);
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.0.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn row_writes() {
+        let config = Config::<AddressFormatter, ByteFormatter, CharFormatter>::default();
+
+        let mut printer = Printer::new(Writes::default(), 0, config.clone());
+        for byte in b"Simple printing, row by row" {
+            _ = printer.push(&[*byte]);
+        }
+        let writes = printer.finish().0;
+
+        let expected = [
+            "00000000 53696d70 6c652070 72696e74 696e672c |Simple printing,|\n",
+            "00000010 20726f77 20627920 726f77.. ........ | row by row     |\n",
+            "0000001b \n",
+        ];
+        assert_eq!(writes, expected.map(|s| s.as_bytes().to_vec()));
+
+        // Rows are never printed partially with deduplication
+        let config = Config::new(
+            Some(AddressFormatter::default()),
+            ByteFormatter::default(),
+            Some(CharFormatter::default()),
+            false,
+        );
+        let mut printer = Printer::new(Writes::default(), 0, config.with_immediate_output(true));
+        _ = printer.push(b"Simple");
+        _ = printer.push(b" printing");
+
+        // Only complete groups are formatted
+        let expected = ["00000000 53696d70", " 6c652070 72696e74"];
+        assert_eq!(printer.out.as_ref().unwrap().0, expected.map(|s| s.as_bytes().to_vec()));
+    }
+
+    mod allocations {
+        use super::*;
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        struct CountingAllocator;
+
+        thread_local! {
+            static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                ALLOCATIONS.with(|a| a.set(a.get() + 1));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+        #[test]
+        fn steady_state() {
+            let data: Vec<u8> = (0..4096u32).map(|i| (i * 7 / 48) as u8).collect();
+
+            let configs = [
+                Config::<AddressFormatter, ByteFormatter, CharFormatter>::default(),
+                Config::new(
+                    Some(AddressFormatter::new(AddressStyle::Dec(10), Default::default())),
+                    ByteFormatter::new(ByteStyle::Bin, Default::default(), " ", true, Default::default()),
+                    Some(CharFormatter::default()),
+                    false,
+                ),
+                Config::default()
+                    .with_placeholder(DedupPlaceholder::periodic())
+                    .with_max_dedup_period(4),
+            ];
+
+            for config in configs {
+                let mut printer = Printer::new(sink(), 0, config);
+                _ = printer.push(&data);
+
+                let before = ALLOCATIONS.with(|a| a.get());
+                for part in data.chunks(5) {
+                    _ = printer.push(part);
+                }
+                assert_eq!(ALLOCATIONS.with(|a| a.get()), before);
+            }
+        }
+    }
+
     fn string_with_file(path: &str) -> String {
         let test_data =
             std::fs::read(path).expect("Could not opent testable data");
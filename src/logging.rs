@@ -0,0 +1,336 @@
+//! Dumps in log records.
+//!
+//! [`Dump`] is formatted only when it is displayed, so dumps of disabled records are never formatted.
+//! With `log` feature, [`log_dump!`](crate::log_dump) emits the dump as a single multi-line record
+//! or as a record per row, only when the level is enabled for the target.
+//! With `tracing` feature, [`Dump::field`] makes the field value of an event:
+//!
+//! ```ignore
+//! kex::log_dump!(log::Level::Debug, &payload);
+//! kex::log_dump!(target: "net", log::Level::Trace, Dump::new(&payload).with_limit(256), LogRecords::PerRow);
+//!
+//! tracing::debug!(payload = Dump::new(&payload).field(), "Received");
+//! ```
+
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter};
+
+use super::*;
+
+/// Bytes, displayed as kex dump. Uses default [`Config`] unless [`Dump::with_config`] is called
+pub struct Dump<
+    'a,
+    A: AddressFormatting + Clone = AddressFormatter,
+    B: ByteFormatting + Clone = ByteFormatter,
+    C: CharFormatting + Clone = CharFormatter,
+> {
+    bytes: &'a [u8],
+    config: Cow<'a, Config<A, B, C>>,
+    start_address: u64,
+    /// Maximum number of dumped bytes
+    limit: Option<usize>,
+}
+
+impl<'a> Dump<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            config: Cow::Owned(Config::default()),
+            start_address: 0,
+            limit: None,
+        }
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> From<&'a T> for Dump<'a> {
+    fn from(bytes: &'a T) -> Self {
+        Dump::new(bytes.as_ref())
+    }
+}
+
+impl<'a, A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> Dump<'a, A, B, C> {
+    pub fn with_config<D, E, F>(self, config: &'a Config<D, E, F>) -> Dump<'a, D, E, F>
+    where
+        D: AddressFormatting + Clone,
+        E: ByteFormatting + Clone,
+        F: CharFormatting + Clone,
+    {
+        Dump {
+            bytes: self.bytes,
+            config: Cow::Borrowed(config),
+            start_address: self.start_address,
+            limit: self.limit,
+        }
+    }
+
+    /// Address of the first byte (`0` by default)
+    pub fn with_start_address(mut self, address: u64) -> Self {
+        self.start_address = address;
+        self
+    }
+
+    /// Dump only first `bytes`. Number of the rest bytes is printed after the dump
+    pub fn with_limit(mut self, bytes: usize) -> Self {
+        self.limit = Some(bytes);
+        self
+    }
+
+    /// Text of the dump without the trailing line separator
+    fn render(&self) -> String {
+        let shown = match self.limit {
+            Some(limit) => std::cmp::min(limit, self.bytes.len()),
+            None => self.bytes.len(),
+        };
+
+        let mut printer = match Printer::try_new(vec![], self.start_address, self.config.as_ref().clone()) {
+            Ok(p) => p,
+            Err(e) => return format!("Invalid config: {e}"),
+        };
+        if let Err(e) = printer.push(&self.bytes[..shown]) {
+            return e.to_string();
+        }
+
+        let mut text = String::from_utf8_lossy(&printer.finish()).into_owned();
+        if shown < self.bytes.len() {
+            text.push_str(&format!("... {} more bytes", self.bytes.len() - shown));
+        }
+
+        let len = text.trim_end_matches('\n').len();
+        text.truncate(len);
+        text
+    }
+}
+
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> Display for Dump<'_, A, B, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+/// The same as [`Display`], so `{:?}` of log macros prints the dump too
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> Debug for Dump<'_, A, B, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+/// How [`log_dump!`](crate::log_dump) splits the dump into records
+#[cfg(feature = "log")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogRecords {
+    /// Whole dump in a single multi-line record
+    #[default]
+    Single,
+    /// Record for every row
+    PerRow,
+}
+
+#[cfg(feature = "log")]
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> Dump<'_, A, B, C> {
+    /// Emits the dump, if `level` is enabled for `target`. Prefer [`log_dump!`](crate::log_dump),
+    /// which also records the location of the call
+    pub fn log(&self, target: &str, level: log::Level, records: LogRecords) {
+        if __is_enabled(target, level) {
+            self.emit(target, level, records, None);
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn __log_at(
+        &self,
+        target: &str,
+        level: log::Level,
+        records: LogRecords,
+        location: (&'static str, &'static str, u32),
+    ) {
+        self.emit(target, level, records, Some(location));
+    }
+
+    fn emit(&self, target: &str, level: log::Level, records: LogRecords, location: Option<(&'static str, &'static str, u32)>) {
+        let text = self.render();
+        let emit_record = |message: &str| {
+            log::logger().log(
+                &log::Record::builder()
+                    .args(format_args!("{message}"))
+                    .level(level)
+                    .target(target)
+                    .module_path_static(location.map(|l| l.0))
+                    .file_static(location.map(|l| l.1))
+                    .line(location.map(|l| l.2))
+                    .build(),
+            )
+        };
+
+        match records {
+            LogRecords::Single => emit_record(&text),
+            LogRecords::PerRow => text.lines().for_each(emit_record),
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+#[doc(hidden)]
+pub use log as __log;
+
+#[cfg(feature = "log")]
+#[doc(hidden)]
+pub fn __is_enabled(target: &str, level: log::Level) -> bool {
+    level <= log::max_level() && log::logger().enabled(&log::Metadata::builder().level(level).target(target).build())
+}
+
+/// Logs the dump of bytes (anything like `&[u8]` or [`Dump`]), if the level is enabled.
+/// The dump is not formatted otherwise. Target defaults to the module path
+///
+/// ```ignore
+/// log_dump!(Level::Debug, &buf);
+/// log_dump!(Level::Debug, Dump::new(&buf).with_limit(64), LogRecords::PerRow);
+/// log_dump!(target: "net", Level::Trace, &buf);
+/// ```
+#[cfg(feature = "log")]
+#[macro_export]
+macro_rules! log_dump {
+    (target: $target:expr, $level:expr, $dump:expr, $records:expr) => {{
+        let level: $crate::logging::__log::Level = $level;
+        if $crate::logging::__is_enabled($target, level) {
+            $crate::logging::Dump::from($dump).__log_at($target, level, $records, (module_path!(), file!(), line!()));
+        }
+    }};
+    (target: $target:expr, $level:expr, $dump:expr) => {
+        $crate::log_dump!(target: $target, $level, $dump, $crate::logging::LogRecords::Single)
+    };
+    ($level:expr, $dump:expr, $records:expr) => {
+        $crate::log_dump!(target: module_path!(), $level, $dump, $records)
+    };
+    ($level:expr, $dump:expr) => {
+        $crate::log_dump!(target: module_path!(), $level, $dump, $crate::logging::LogRecords::Single)
+    };
+}
+
+#[cfg(feature = "tracing")]
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> Dump<'_, A, B, C> {
+    /// Field value of `tracing` event. The dump is formatted only when the event is recorded
+    pub fn field(self) -> tracing::field::DisplayValue<Self> {
+        tracing::field::display(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated() {
+        let data = b"Truncated dump\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+        let config = Config::<AddressFormatter, ByteFormatter, CharFormatter>::default();
+
+        let mut printer = Printer::new(vec![], 0x10, config.clone());
+        _ = printer.push(data);
+        let expected = String::from_utf8(printer.finish()).unwrap();
+        let dump = Dump::new(data).with_config(&config).with_start_address(0x10);
+        assert_eq!(format!("{dump}\n"), expected);
+
+        let expected = "00000010 5472756e 63617465 ........ ........ |Truncate        |\n\
+                        00000018 \n\
+                        ... 42 more bytes";
+        assert_eq!(Dump::new(data).with_start_address(0x10).with_limit(8).to_string(), expected);
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn log_records() {
+        use std::sync::Mutex;
+
+        /// Keeps records of `kex` target
+        struct Recorder(Mutex<Vec<(log::Level, String)>>);
+
+        impl log::Log for Recorder {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.target() == "kex" && metadata.level() <= log::Level::Debug
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) {
+                    self.0.lock().unwrap().push((record.level(), record.args().to_string()));
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static RECORDER: Recorder = Recorder(Mutex::new(vec![]));
+        log::set_logger(&RECORDER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let data = vec![0x41u8; 20];
+        crate::log_dump!(target: "kex", log::Level::Debug, &data);
+        crate::log_dump!(target: "kex", log::Level::Info, Dump::new(&data).with_limit(17), LogRecords::PerRow);
+        crate::log_dump!(target: "kex", log::Level::Trace, &data);
+        crate::log_dump!(target: "other", log::Level::Error, &data);
+
+        let rows = [
+            "00000000 41414141 41414141 41414141 41414141 |AAAAAAAAAAAAAAAA|",
+            "00000010 41...... ........ ........ ........ |A               |",
+            "00000011 ",
+            "... 3 more bytes",
+        ];
+        let full = "00000000 41414141 41414141 41414141 41414141 |AAAAAAAAAAAAAAAA|\n\
+                    00000010 41414141 ........ ........ ........ |AAAA            |\n\
+                    00000014 ";
+        let mut expected = vec![(log::Level::Debug, full.to_string())];
+        expected.extend(rows.iter().map(|r| (log::Level::Info, r.to_string())));
+
+        assert_eq!(*RECORDER.0.lock().unwrap(), expected);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_field() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Level, Metadata};
+
+        /// Keeps `payload` fields of events up to `DEBUG`
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl Visit for &Recorder {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                if field.name() == "payload" {
+                    self.0.lock().unwrap().push(format!("{value:?}"));
+                }
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+                *metadata.level() <= Level::DEBUG
+            }
+
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                event.record(&mut &*self);
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let fields = Arc::new(Mutex::new(vec![]));
+        let data = b"Field";
+        tracing::subscriber::with_default(Recorder(fields.clone()), || {
+            tracing::debug!(payload = Dump::new(data).field(), "Debug");
+            tracing::trace!(payload = Dump::new(data).field(), "Trace");
+        });
+
+        let expected = "00000000 4669656c 64...... ........ ........ |Field           |\n00000005 ";
+        assert_eq!(*fields.lock().unwrap(), [expected]);
+    }
+}
@@ -7,8 +7,31 @@ mod app;
 use app::*;
 
 fn main() {
-    let app_config = match get_app_config() {
-        Ok(c) => c,
+    let app_config = match get_command() {
+        Ok(Command::Dump(c)) => *c,
+        Ok(Command::PrintConfig(description)) => {
+            print!("{description}");
+            return;
+        }
+        Ok(Command::Captures(captures)) => {
+            if let Err(e) = captures.run() {
+                exit_with_error(e);
+            }
+            return;
+        }
+        Ok(Command::Sections(sections)) => {
+            if let Err(e) = sections.run() {
+                exit_with_error(e);
+            }
+            return;
+        }
+        Ok(Command::Proxy(proxy)) => {
+            if let Err(e) = proxy.run() {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return;
+        }
         Err(e) => {
             eprintln!("{e}");
             std::process::exit(1);
@@ -32,51 +55,51 @@ fn handle_stdin(output: impl Write, range: ContentRange) {
     handle(input, output, range.len)
 }
 
-fn handle_file_path(path: &str, output: &mut impl Write, range: ContentRange) {
+fn handle_file_path(path: &str, output: &mut Output, range: ContentRange) {
     use std::io::SeekFrom;
 
     match File::open(Path::new(path)) {
         Ok(mut file) => {
-            match file.seek(SeekFrom::Start(range.skip as u64)) {
+            match file.seek(SeekFrom::Start(range.skip)) {
                 Ok(_) => (),
                 Err(e) => {
                     eprintln!("{e}");
                     exit(1);
                 },
             }
-            handle(&mut file, output, range.len)
+
+            let size = match file.metadata() {
+                Ok(m) if m.is_file() => m.len(),
+                _ => 0,
+            };
+
+            // Sizes of special files (like ones in /proc) are not reliable
+            if size != 0 {
+                if let Err(e) = stream_sparse(&mut file, output, &range, size) {
+                    exit_with_error(e);
+                }
+            } else {
+                handle(&mut file, output, range.len)
+            }
         },
         Err(err) => {
             eprintln!("{path}: {err}");
-            return;
         }
     }
 }
 
-fn handle(mut input: impl Read, mut output: impl Write, n_bytes: Option<usize>) {
-    use std::cmp::min;
-
-    let mut buf = [0u8; 4096];
-    
-    let mut elapsed = 0;
-    let mut to_read = buf.len();
-
-    while to_read != 0 {
-        if let Some(n_bytes) = n_bytes {
-            let diff = n_bytes - min(n_bytes, elapsed);
-            to_read = min(to_read, diff);
-        }
-
-        if let Ok(size) = input.read(&mut buf[..to_read]) {
-            if size == 0 {
-                break;
-            }
-            assert!(output.write_all(&mut buf[..size]).is_ok());
-
-            elapsed += size;
-        } else {
-            break;
-        }
+fn handle(input: impl Read, output: impl Write, n_bytes: Option<u64>) {
+    if let Err(e) = stream(input, output, n_bytes) {
+        exit_with_error(e);
+    }
+}
 
+/// Closed output (like `kex file | head`) is not an error
+fn exit_with_error(e: std::io::Error) -> ! {
+    if e.kind() == std::io::ErrorKind::BrokenPipe {
+        exit(0);
     }
+
+    eprintln!("{e}");
+    exit(1);
 }
@@ -0,0 +1,606 @@
+//! Multi-threaded printing of large inputs
+
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::io::*;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use super::*;
+
+const DEFAULT_ROWS_PER_CHUNK: usize = 4096;
+
+/// Rows of preceding data, used to bring the worker's streamer into the state at the chunk start
+const CLASSIC_DEDUP_LOOKBACK_ROWS: usize = 2;
+const PERIODIC_LOOKBACK_PERIODS: usize = 4;
+
+const WORKERS_LOST_MESSAGE: &str = "Formatting threads are terminated";
+
+/// Parallel printer with output and formatters, chosen at runtime. See [`crate::DynPrinter`]
+pub type DynParallelPrinter<'a> =
+    ParallelPrinter<Box<dyn Write + 'a>, DynAddressFormatter, DynByteFormatter, DynCharFormatter>;
+
+struct Job {
+    index: usize,
+    /// Lookback rows followed by the chunk
+    data: Arc<Vec<u8>>,
+    lookback: usize,
+    /// Address of the first lookback byte
+    address: u64,
+    /// Address of the first byte of the dump
+    first_address: u64,
+}
+
+struct Formatted<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> {
+    text: Vec<u8>,
+    /// State after formatting of the lookback rows
+    start: Streamer<A, B, C>,
+    end: Streamer<A, B, C>,
+}
+
+struct Done<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> {
+    index: usize,
+    data: Arc<Vec<u8>>,
+    lookback: usize,
+    result: Result<Formatted<A, B, C>>,
+}
+
+/// Printer, which formats row-aligned chunks of input in worker threads.
+///
+/// Output is identical to [`Printer`] with the same configuration. Each worker restores
+/// the deduplication state at the start of its chunk from a few preceding rows. If restored state
+/// differs from the actual one (for example, inside a long run of duplicates with a placeholder showing
+/// run statistics), the chunk is formatted again in the calling thread.
+///
+/// Formatted chunks are written to `out` in order from the calling thread
+pub struct ParallelPrinter<
+    O: Write,
+    A: AddressFormatting + Clone + Send + 'static,
+    B: ByteFormatting + Clone + Send + 'static,
+    C: CharFormatting + Clone + Send + 'static,
+> {
+    out: Option<O>,
+
+    /// State after the last written chunk
+    streamer: Streamer<A, B, C>,
+
+    start_address: u64,
+    bpr: usize,
+    chunk_len: usize,
+    lookback_len: usize,
+
+    /// Bytes, which were not sent to workers yet
+    pending: Vec<u8>,
+    /// Last bytes before `pending`
+    recent: Vec<u8>,
+    /// Number of bytes, sent to workers or written directly
+    dispatched: u64,
+
+    next_index: usize,
+    next_to_write: usize,
+    in_flight: usize,
+    max_in_flight: usize,
+    done: BTreeMap<usize, Done<A, B, C>>,
+
+    jobs: Option<Sender<Job>>,
+    results: Receiver<Done<A, B, C>>,
+    workers: Vec<JoinHandle<()>>,
+
+    is_finished: bool,
+}
+
+impl<
+        O: Write,
+        A: AddressFormatting + Clone + Send + 'static,
+        B: ByteFormatting + Clone + Send + 'static,
+        C: CharFormatting + Clone + Send + 'static,
+    > ParallelPrinter<O, A, B, C>
+{
+    /// Same as [`Printer::try_new`], but with `threads` formatting threads.
+    ///
+    /// Up to `2 * threads` chunks are kept in memory. Chunk is 4096 rows by default
+    pub fn try_new(
+        out: O,
+        start_address: u64,
+        config: Config<A, B, C>,
+        threads: usize,
+    ) -> std::result::Result<ParallelPrinter<O, A, B, C>, ConfigError> {
+        config.validate()?;
+
+        let threads = std::cmp::max(threads, 1);
+        let bpr = config.byte.groupping().bytes_per_row();
+
+        let lookback_rows = match (config.dedup_enabled, config.max_dedup_period) {
+            (false, _) => 0,
+            (true, p) if p > 1 => PERIODIC_LOOKBACK_PERIODS * p,
+            (true, _) => CLASSIC_DEDUP_LOOKBACK_ROWS,
+        };
+
+        let (jobs, job_receiver) = channel::<Job>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (result_sender, results) = channel();
+
+        let workers = (0..threads)
+            .map(|_| {
+                let config = config.clone();
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                std::thread::spawn(move || work(config, job_receiver, result_sender))
+            })
+            .collect();
+
+        Ok(ParallelPrinter {
+            out: Some(out),
+            streamer: Streamer::new(config, start_address),
+            start_address,
+            bpr,
+            chunk_len: DEFAULT_ROWS_PER_CHUNK * bpr,
+            lookback_len: lookback_rows * bpr,
+            pending: vec![],
+            recent: vec![],
+            dispatched: 0,
+            next_index: 0,
+            next_to_write: 0,
+            in_flight: 0,
+            max_in_flight: 2 * threads,
+            done: BTreeMap::new(),
+            jobs: Some(jobs),
+            results,
+            workers,
+            is_finished: false,
+        })
+    }
+
+    /// Same as [`ParallelPrinter::try_new`], for configs known to be valid, like built by [`ConfigBuilder::build`](crate::ConfigBuilder::build)
+    ///
+    /// ## Panics
+    /// If `config` is rejected by [`Config::validate`]
+    #[track_caller]
+    pub fn new(
+        out: O,
+        start_address: u64,
+        config: Config<A, B, C>,
+        threads: usize,
+    ) -> ParallelPrinter<O, A, B, C> {
+        match Self::try_new(out, start_address, config, threads) {
+            Ok(printer) => printer,
+            Err(e) => panic!("Invalid config: {e}"),
+        }
+    }
+
+    /// Number of rows, formatted by a single worker at once
+    pub fn with_rows_per_chunk(mut self, rows: usize) -> Self {
+        self.chunk_len = std::cmp::max(rows, 1) * self.bpr;
+        self
+    }
+
+    /// Finalize manually. Prints last unfinished line with paddings and turns back given output
+    pub fn finish(mut self) -> O {
+        _ = self.print_last_line();
+        self.is_finished = true;
+        self.out.take().unwrap()
+    }
+
+    /// Accepts bytes chunk. Bytes are printed as soon as the whole chunk is collected and formatted
+    pub fn push(&mut self, bytes: &[u8]) -> Result<usize> {
+        let pushed = self.dispatched + self.pending.len() as u64;
+        advance_address(self.start_address + pushed, bytes.len() as u64)?;
+
+        let mut tmp = bytes;
+
+        // Finish the row, started by direct writing
+        let bpr = self.bpr as u64;
+        let unaligned = ((bpr - self.dispatched % bpr) % bpr) as usize;
+        if unaligned != 0 {
+            let to_write = min(unaligned, tmp.len());
+            self.write_directly(&tmp[..to_write])?;
+            tmp = &tmp[to_write..];
+        }
+
+        self.pending.extend_from_slice(tmp);
+        while self.pending.len() >= self.chunk_len {
+            self.dispatch()?;
+        }
+
+        Ok(bytes.len())
+    }
+
+    /// Same as [`Printer::push_hole`]. Waits for all previously pushed bytes to be printed
+    pub fn push_hole(&mut self, len: u64) -> Result<()> {
+        self.drain()?;
+
+        let mut out = self.out.take().expect(OUTPUT_LOST_MESSAGE);
+        let result = self.streamer.push_hole(len, &mut out);
+        self.out = Some(out);
+        result?;
+
+        self.dispatched += len;
+        let zeros = min(len, self.lookback_len as u64) as usize;
+        self.remember(&vec![0u8; zeros]);
+
+        Ok(())
+    }
+
+    fn dispatch(&mut self) -> Result<()> {
+        let lookback = self.recent.len();
+
+        let mut data = Vec::with_capacity(lookback + self.chunk_len);
+        data.extend_from_slice(&self.recent);
+        data.extend_from_slice(&self.pending[..self.chunk_len]);
+
+        let job = Job {
+            index: self.next_index,
+            address: self.start_address + self.dispatched - lookback as u64,
+            first_address: self.start_address,
+            data: Arc::new(data),
+            lookback,
+        };
+
+        let chunk = self.pending.drain(..self.chunk_len).collect::<Vec<u8>>();
+        self.remember(&chunk);
+        self.dispatched += chunk.len() as u64;
+
+        let sent = match &self.jobs {
+            Some(jobs) => jobs.send(job).is_ok(),
+            None => false,
+        };
+        if !sent {
+            return Err(Error::other(WORKERS_LOST_MESSAGE));
+        }
+
+        self.next_index += 1;
+        self.in_flight += 1;
+
+        while self.in_flight >= self.max_in_flight {
+            self.receive()?;
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<()> {
+        let done = match self.results.recv() {
+            Ok(d) => d,
+            Err(_) => {
+                return Err(Error::other(WORKERS_LOST_MESSAGE));
+            }
+        };
+
+        self.in_flight -= 1;
+        self.done.insert(done.index, done);
+
+        let mut out = self.out.take().expect(OUTPUT_LOST_MESSAGE);
+        let result = self.write_ready(&mut out);
+        self.out = Some(out);
+
+        result
+    }
+
+    fn write_ready(&mut self, out: &mut O) -> Result<()> {
+        while let Some(done) = self.done.remove(&self.next_to_write) {
+            self.next_to_write += 1;
+
+            let formatted = done.result?;
+
+            if self.streamer.same_state(&formatted.start) {
+                out.write_all(&formatted.text)?;
+                self.streamer = formatted.end;
+            } else {
+                self.streamer.push(&done.data[done.lookback..], out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits for all chunks and prints remaining bytes directly
+    fn drain(&mut self) -> Result<()> {
+        while self.in_flight != 0 {
+            self.receive()?;
+        }
+
+        let pending = std::mem::take(&mut self.pending);
+        self.write_directly(&pending)
+    }
+
+    fn write_directly(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        // Bytes must follow all previous chunks
+        while self.in_flight != 0 {
+            self.receive()?;
+        }
+
+        let mut out = self.out.take().expect(OUTPUT_LOST_MESSAGE);
+        let result = self.streamer.push(bytes, &mut out);
+        self.out = Some(out);
+        result?;
+
+        self.remember(bytes);
+        self.dispatched += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    fn remember(&mut self, bytes: &[u8]) {
+        self.recent.extend_from_slice(bytes);
+        if self.recent.len() > self.lookback_len {
+            let excess = self.recent.len() - self.lookback_len;
+            self.recent.drain(..excess);
+        }
+    }
+
+    fn print_last_line(&mut self) -> Result<()> {
+        if self.is_finished {
+            return Ok(());
+        }
+
+        self.drain()?;
+
+        let mut out = self.out.take().expect(OUTPUT_LOST_MESSAGE);
+        let result = self.streamer.write_tail(&mut out);
+        self.out = Some(out);
+
+        result
+    }
+}
+
+fn work<
+    A: AddressFormatting + Clone,
+    B: ByteFormatting + Clone,
+    C: CharFormatting + Clone,
+>(
+    config: Config<A, B, C>,
+    jobs: Arc<Mutex<Receiver<Job>>>,
+    results: Sender<Done<A, B, C>>,
+) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        let job = match job {
+            Ok(j) => j,
+            Err(_) => return,
+        };
+
+        let result = format_chunk(&config, &job);
+        let done = Done {
+            index: job.index,
+            data: job.data,
+            lookback: job.lookback,
+            result,
+        };
+
+        if results.send(done).is_err() {
+            return;
+        }
+    }
+}
+
+fn format_chunk<
+    A: AddressFormatting + Clone,
+    B: ByteFormatting + Clone,
+    C: CharFormatting + Clone,
+>(
+    config: &Config<A, B, C>,
+    job: &Job,
+) -> Result<Formatted<A, B, C>> {
+    let mut streamer = Streamer::new(config.clone(), job.address).continuing(job.first_address);
+    streamer.push(&job.data[..job.lookback], &mut sink())?;
+
+    let start = streamer.clone();
+
+    let mut text = Vec::with_capacity(4 * job.data.len());
+    streamer.push(&job.data[job.lookback..], &mut text)?;
+
+    Ok(Formatted {
+        text,
+        start,
+        end: streamer,
+    })
+}
+
+impl<
+        O: Write,
+        A: AddressFormatting + Clone + Send + 'static,
+        B: ByteFormatting + Clone + Send + 'static,
+        C: CharFormatting + Clone + Send + 'static,
+    > Write for ParallelPrinter<O, A, B, C>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.push(buf)
+    }
+
+    /// Does nothing. Always returns `Ok(())`
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<
+        O: Write,
+        A: AddressFormatting + Clone + Send + 'static,
+        B: ByteFormatting + Clone + Send + 'static,
+        C: CharFormatting + Clone + Send + 'static,
+    > Drop for ParallelPrinter<O, A, B, C>
+{
+    fn drop(&mut self) {
+        if self.out.is_some() {
+            _ = self.print_last_line();
+        }
+
+        // Closed channel stops workers
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fmt = Config<AddressFormatter, ByteFormatter, CharFormatter>;
+
+    /// Random rows, zero runs and repeating patterns of several rows
+    fn sample() -> Vec<u8> {
+        let mut seed = 0x2545f491u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        let mut data = vec![];
+        while data.len() < 12000 {
+            let len = (random() % 300) as usize;
+            match random() % 4 {
+                0 => data.extend((0..len).map(|_| random() as u8)),
+                1 => data.extend(std::iter::repeat_n(0u8, len * 4)),
+                _ => {
+                    let pattern: Vec<u8> = (0..(random() % 70 + 1)).map(|_| random() as u8).collect();
+                    data.extend(pattern.iter().cycle().take(len * 8));
+                }
+            }
+        }
+
+        data
+    }
+
+    fn byte_fmt(style: ByteStyle, groupping: Groupping, byte_separator: &str) -> ByteFormatter {
+        ByteFormatter::new(style, groupping, byte_separator, false, Separators::new(" ", " "))
+    }
+
+    fn configs() -> Vec<Fmt> {
+        let pattern = Groupping::Pattern(vec![Group::new(4, " | "), Group::new(2, "  "), Group::new(3, "")]);
+        let nested = Groupping::Nested(vec![Group::new(2, " "), Group::new(6, "  ")], 3);
+        let entries: Vec<String> = (0..BYTE_TABLE_LEN).map(|b| format!("{b:02X}")).collect();
+
+        vec![
+            Config::new(
+                Some(AddressFormatter::default()),
+                ByteFormatter::default(),
+                Some(CharFormatter::default()),
+                false,
+            ),
+            Config::default(),
+            Config::default().with_placeholder(DedupPlaceholder::detailed()),
+            Config::default().with_max_dedup_period(4),
+            Config::default()
+                .with_placeholder(DedupPlaceholder::periodic())
+                .with_max_dedup_period(3),
+            Config::new(None, Default::default(), None, true)
+                .with_address_column(AddressKind::Relative, Default::default())
+                .with_address_column(AddressKind::RowIndex, Default::default())
+                .with_placeholder(DedupPlaceholder::detailed()),
+            Config::new(
+                Some(AddressFormatter::new(AddressStyle::UpperHex(6), Separators::new("[", "] ")).with_prefix("0x")),
+                byte_fmt(ByteStyle::CEscape, pattern.clone(), ",").with_affixes("<", ">"),
+                Some(CharFormatter::default()),
+                true,
+            )
+            .with_address_column(
+                AddressKind::Based(0x400000),
+                AddressFormatter::new(AddressStyle::Dec(10), Default::default()),
+            )
+            .with_placeholder(DedupPlaceholder::periodic())
+            .with_max_dedup_period(5),
+            Config::new(
+                Some(AddressFormatter::new(AddressStyle::Bin(4), Default::default())),
+                byte_fmt(ByteStyle::Percent, nested.clone(), ""),
+                Some(CharFormatter::default().with_gutters(nested.clone(), " ")),
+                true,
+            )
+            .with_address_column(AddressKind::Relative, Default::default())
+            .with_max_dedup_period(2),
+            Config::new(
+                Some(AddressFormatter::default()),
+                byte_fmt(ByteStyle::Table(ByteTable::new(&entries).unwrap()), nested.clone(), " ")
+                    .with_affixes("'", ""),
+                None,
+                true,
+            )
+            .with_address_column(AddressKind::RowIndex, Default::default())
+            .with_placeholder(DedupPlaceholder::detailed()),
+            Config::new(
+                None,
+                byte_fmt(ByteStyle::CaretAscii, Groupping::RowWide(13), ""),
+                None,
+                true,
+            )
+            .with_max_dedup_period(3),
+            Config::new(None, byte_fmt(ByteStyle::RustLiteral, pattern, ""), None, false)
+                .with_immediate_output(true),
+            Config::default().with_immediate_output(true),
+            Config::new(
+                Some(AddressFormatter::default()),
+                byte_fmt(ByteStyle::UpperHex, nested.clone(), ""),
+                Some(CharFormatter::default().with_gutters(nested, "|")),
+                true,
+            )
+            .with_address_column(AddressKind::Relative, Default::default())
+            .with_placeholder(DedupPlaceholder::periodic())
+            .with_max_dedup_period(4)
+            .with_immediate_output(true),
+        ]
+    }
+
+    /// Pushes `data` by parts of `part_len`, replacing each `hole_every`-th part with a hole
+    fn dump<W: Write>(
+        data: &[u8],
+        part_len: usize,
+        hole_every: usize,
+        mut push: impl FnMut(&mut W, &[u8]) -> Result<usize>,
+        mut push_hole: impl FnMut(&mut W, u64) -> Result<()>,
+        printer: &mut W,
+    ) {
+        for (idx, part) in data.chunks(part_len).enumerate() {
+            if hole_every != 0 && idx % hole_every == hole_every - 1 {
+                push_hole(printer, part.len() as u64).unwrap();
+            } else {
+                push(printer, part).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_printer() {
+        let data = sample();
+
+        for config in configs() {
+            for (part_len, hole_every) in [(4096, 0), (1000, 7), (13, 0), (333, 3)] {
+                let mut printer = Printer::new(vec![], 0x10, config.clone());
+                dump(&data, part_len, hole_every, Printer::push, Printer::push_hole, &mut printer);
+                let expected = printer.finish();
+
+                for threads in [1, 2, 4] {
+                    for rows in [1, 3, 64] {
+                        let mut printer = ParallelPrinter::new(vec![], 0x10, config.clone(), threads)
+                            .with_rows_per_chunk(rows);
+                        dump(
+                            &data,
+                            part_len,
+                            hole_every,
+                            ParallelPrinter::push,
+                            ParallelPrinter::push_hole,
+                            &mut printer,
+                        );
+                        let result = printer.finish();
+
+                        assert!(
+                            result == expected,
+                            "Output differs: {part_len} bytes per push, {threads} threads, {rows} rows per chunk"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
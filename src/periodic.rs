@@ -0,0 +1,184 @@
+//! Detection of patterns, repeating with a period of several rows
+
+use std::io::Result;
+
+/// Receiver of rows, which were not collapsed by [`PeriodDetector`]
+pub(super) trait RowSink {
+    fn write_row(&mut self, row: &[u8]) -> Result<()>;
+
+    /// Single repetition of pattern with `period_rows` rows was collapsed.
+    /// `pattern` is provided for the first repetition of run only
+    fn collapse(&mut self, period_rows: usize, pattern: Option<&[u8]>) -> Result<()>;
+
+    /// Collapsed run is interrupted by a different row or by the end of data
+    fn finish_run(&mut self) -> Result<()>;
+}
+
+/// Keeps last rows and holds back rows, which may repeat some of previous rows.
+///
+/// Memory usage is bounded by `2 * max_period` rows
+#[derive(Clone)]
+pub(super) struct PeriodDetector {
+    max_period: usize,
+    bpr: usize,
+
+    /// Ring of last rows
+    history: Vec<u8>,
+    /// Number of rows ever stored. Index of the next row
+    rows: usize,
+    /// Number of last rows, which were neither written nor collapsed
+    pending: usize,
+    /// Index of the first row after the last collapsed run.
+    /// Only written rows may be the first occurrence of pattern
+    visible_from: usize,
+    /// Period of the current collapsed run
+    run_period: Option<usize>,
+
+    pattern: Vec<u8>,
+}
+
+impl PeriodDetector {
+    pub(super) fn new(max_period: usize, bpr: usize) -> Self {
+        assert!(max_period != 0, "Period must be at least one row");
+
+        Self {
+            max_period,
+            bpr,
+            history: vec![0u8; 2 * max_period * bpr],
+            rows: 0,
+            pending: 0,
+            visible_from: 0,
+            run_period: None,
+            pattern: Vec::with_capacity(max_period * bpr),
+        }
+    }
+
+    /// Holds back the row. It must be followed by [`PeriodDetector::settle`]
+    pub(super) fn store_row(&mut self, row: &[u8]) {
+        assert_eq!(row.len(), self.bpr, "Incomplete row");
+
+        let slot = self.slot(self.rows);
+        self.history[slot..slot + self.bpr].copy_from_slice(row);
+        self.rows += 1;
+        self.pending += 1;
+    }
+
+    /// Accounts the row, which was shown by other means than [`RowSink`]. Must follow [`PeriodDetector::flush`]
+    pub(super) fn assume_written(&mut self, row: &[u8]) {
+        assert_eq!(self.pending, 0, "Rows are held back");
+
+        self.store_row(row);
+        self.pending = 0;
+        self.visible_from = self.rows - 1;
+    }
+
+    /// Writes all held rows. Must be called before writing an incomplete row
+    pub(super) fn flush<S: RowSink>(&mut self, sink: &mut S) -> Result<()> {
+        if self.run_period.take().is_some() {
+            sink.finish_run()?;
+        }
+
+        while self.pending != 0 {
+            self.write_oldest_pending(sink)?;
+        }
+
+        self.visible_from = self.rows;
+        Ok(())
+    }
+
+    /// Writes or collapses held rows as soon as it is known whether they repeat previous rows
+    pub(super) fn settle<S: RowSink>(&mut self, sink: &mut S) -> Result<()> {
+        loop {
+            if let Some(period) = self.run_period {
+                if self.pending_matches(period) {
+                    if self.pending == period {
+                        self.pending = 0;
+                        sink.collapse(period, None)?;
+                    }
+                    return Ok(());
+                }
+
+                self.run_period = None;
+                self.visible_from = self.rows - self.pending;
+                sink.finish_run()?;
+            }
+
+            if self.pending == 0 {
+                return Ok(());
+            }
+
+            let first_pending = self.rows - self.pending;
+            let candidate = (self.pending..=self.max_period)
+                .filter(|p| first_pending >= self.visible_from + p)
+                .find(|p| self.pending_matches(*p));
+
+            match candidate {
+                Some(period) if period == self.pending => {
+                    self.pending = 0;
+                    self.run_period = Some(period);
+
+                    self.pattern.clear();
+                    for idx in first_pending..first_pending + period {
+                        let slot = self.slot(idx);
+                        self.pattern.extend_from_slice(&self.history[slot..slot + self.bpr]);
+                    }
+
+                    return sink.collapse(period, Some(&self.pattern));
+                }
+                // Wait for the rest of repetition
+                Some(_) => return Ok(()),
+                None => self.write_oldest_pending(sink)?,
+            }
+        }
+    }
+
+    /// Detectors in the same state make the same decisions on the same rows
+    pub(super) fn same_state(&self, other: &Self) -> bool {
+        use std::cmp::min;
+
+        let ring_rows = 2 * self.max_period;
+        let depth = min(self.rows, ring_rows);
+
+        // Older runs do not affect candidates for the following rows
+        let visible_distance = |d: &Self| min(d.rows - d.visible_from, ring_rows);
+
+        self.max_period == other.max_period
+            && depth == min(other.rows, ring_rows)
+            && self.pending == other.pending
+            && self.run_period == other.run_period
+            && visible_distance(self) == visible_distance(other)
+            && (1..=depth).all(|i| self.row(self.rows - i) == other.row(other.rows - i))
+    }
+
+    /// Every pending row equals to the row `period` rows before
+    fn pending_matches(&self, period: usize) -> bool {
+        let first_pending = self.rows - self.pending;
+        (first_pending..self.rows).all(|idx| self.row(idx) == self.row(idx - period))
+    }
+
+    fn write_oldest_pending<S: RowSink>(&mut self, sink: &mut S) -> Result<()> {
+        let idx = self.rows - self.pending;
+        self.pending -= 1;
+
+        sink.write_row(self.row(idx))
+    }
+
+    fn row(&self, idx: usize) -> &[u8] {
+        let slot = self.slot(idx);
+        &self.history[slot..slot + self.bpr]
+    }
+
+    fn slot(&self, idx: usize) -> usize {
+        (idx % (2 * self.max_period)) * self.bpr
+    }
+}
+
+/// Length of the shortest pattern, which repeats over the whole `block`
+pub(super) fn minimal_period(block: &[u8]) -> usize {
+    let len = block.len();
+
+    (1..len)
+        .filter(|d| len.is_multiple_of(*d))
+        .find(|d| (*d..len).all(|i| block[i] == block[i - d]))
+        .unwrap_or(len)
+}
@@ -0,0 +1,186 @@
+//! Pull-based dumping of byte sources
+
+use std::io::*;
+
+use super::*;
+
+/// Rows of source, read at once
+const ROWS_PER_READ: usize = 256;
+
+/// Reader of the dump text, formatted from the `source`.
+///
+/// Output is identical to [`Printer`] with the same configuration, including the dedup placeholder
+/// and the final address line. Source is read by portions of a few hundred rows, as text is consumed,
+/// so memory usage does not depend on the source length
+pub struct DumpReader<
+    R: Read,
+    A: AddressFormatting + Clone,
+    B: ByteFormatting + Clone,
+    C: CharFormatting + Clone,
+> {
+    source: R,
+    streamer: Streamer<A, B, C>,
+
+    input: Vec<u8>,
+    /// Formatted text, which was not consumed yet
+    text: Vec<u8>,
+    pos: usize,
+
+    is_finished: bool,
+}
+
+impl<
+        R: Read,
+        A: AddressFormatting + Clone,
+        B: ByteFormatting + Clone,
+        C: CharFormatting + Clone,
+    > DumpReader<R, A, B, C>
+{
+    /// Same as [`Printer::try_new`], but dump is read from the returned reader
+    pub fn try_new(
+        source: R,
+        start_address: u64,
+        config: Config<A, B, C>,
+    ) -> std::result::Result<DumpReader<R, A, B, C>, ConfigError> {
+        config.validate()?;
+        let bpr = config.byte.bytes_per_row();
+
+        Ok(DumpReader {
+            source,
+            streamer: Streamer::new(config, start_address),
+            input: vec![0u8; ROWS_PER_READ * bpr],
+            text: vec![],
+            pos: 0,
+            is_finished: false,
+        })
+    }
+
+    /// Same as [`DumpReader::try_new`], for configs known to be valid, like built by [`ConfigBuilder::build`](crate::ConfigBuilder::build)
+    ///
+    /// ## Panics
+    /// If `config` is rejected by [`Config::validate`]
+    #[track_caller]
+    pub fn new(source: R, start_address: u64, config: Config<A, B, C>) -> DumpReader<R, A, B, C> {
+        match Self::try_new(source, start_address, config) {
+            Ok(reader) => reader,
+            Err(e) => panic!("Invalid config: {e}"),
+        }
+    }
+
+    /// Turns back the source. Unread text is lost
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Formats the next portion of source. Text may be empty, when rows are held back by deduplication
+    fn format_next(&mut self) -> Result<()> {
+        self.text.clear();
+        self.pos = 0;
+
+        let len = match self.source.read(&mut self.input) {
+            Ok(len) => len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        if len == 0 {
+            self.is_finished = true;
+            return self.streamer.write_tail(&mut self.text);
+        }
+
+        self.streamer.push(&self.input[..len], &mut self.text)
+    }
+}
+
+impl<
+        R: Read,
+        A: AddressFormatting + Clone,
+        B: ByteFormatting + Clone,
+        C: CharFormatting + Clone,
+    > Read for DumpReader<R, A, B, C>
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let available = self.fill_buf()?;
+        let len = std::cmp::min(available.len(), buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+
+        Ok(len)
+    }
+}
+
+impl<
+        R: Read,
+        A: AddressFormatting + Clone,
+        B: ByteFormatting + Clone,
+        C: CharFormatting + Clone,
+    > BufRead for DumpReader<R, A, B, C>
+{
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        while self.pos == self.text.len() && !self.is_finished {
+            self.format_next()?;
+        }
+
+        Ok(&self.text[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = std::cmp::min(self.pos + amt, self.text.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::min;
+
+    /// Source, which returns at most `limit` bytes per read
+    struct Trickle<'a> {
+        data: &'a [u8],
+        limit: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let len = min(min(self.limit, buf.len()), self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn same_as_printer() {
+        let data = std::fs::read("testable/duplications").expect("Could not open testable data");
+        let configs: [Config<AddressFormatter, ByteFormatter, CharFormatter>; 2] = [
+            Config::default().with_placeholder(DedupPlaceholder::detailed()),
+            Config::default().with_max_dedup_period(4),
+        ];
+
+        for config in configs {
+            let mut printer = Printer::new(vec![], 0x100, config.clone());
+            _ = printer.push(&data);
+            let expected = printer.finish();
+
+            for (limit, buf_len) in [(data.len(), 1 << 16), (13, 7), (1, 1000)] {
+                let source = Trickle { data: &data, limit };
+                let mut reader = DumpReader::new(source, 0x100, config.clone());
+
+                let mut result = vec![];
+                let mut buf = vec![0u8; buf_len];
+                loop {
+                    match reader.read(&mut buf).unwrap() {
+                        0 => break,
+                        len => result.extend_from_slice(&buf[..len]),
+                    }
+                }
+
+                assert!(result == expected, "Output differs: {limit} bytes per source read, {buf_len} bytes buffer");
+            }
+        }
+
+        let reader = DumpReader::new(&b"Lines"[..], 0, Config::<AddressFormatter, ByteFormatter, CharFormatter>::default());
+        let lines: Vec<String> = reader.lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines, ["00000000 4c696e65 73...... ........ ........ |Lines           |", "00000005 "]);
+    }
+}
@@ -0,0 +1,366 @@
+//! Structured rows of the dump.
+//!
+//! [`Rows`] yields rows as values instead of text: bytes of every printed row with its group boundaries,
+//! collapsed runs of duplicates and the final address. Deduplication is done the same way,
+//! as by [`Printer`]. Rows may be filtered or annotated, then printed with [`RowRenderer`]
+
+use std::collections::VecDeque;
+use std::io::*;
+use std::ops::Range;
+
+use super::*;
+
+/// Rows of source, read at once
+const ROWS_PER_READ: usize = 256;
+
+/// Row of the dump
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DumpRow {
+    /// Printed bytes
+    Data(DataRow),
+    /// Run of duplicated rows, collapsed into a single placeholder
+    Duplicates(DuplicateRun),
+    /// Address following the last byte. Always the last row.
+    /// If deduplication is disabled and the last data row is full, [`Printer`] prints only the line separator
+    End(u64),
+}
+
+/// Printed row of bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataRow {
+    /// Address of the first byte
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    /// Ranges of the innermost groups in `bytes`
+    pub groups: Vec<Range<usize>>,
+    /// Row is shorter than a full row. Only the last data row may be partial
+    pub is_partial: bool,
+}
+
+/// Iterator over structured rows of the dump of `source`.
+///
+/// Rows are the same, as printed by [`Printer`] with the same `config`, but runs of duplicates
+/// are always reported after the run is finished, with the number of collapsed rows.
+/// Formatters of `config` are not used, only groupping and deduplication settings
+pub struct Rows<R: Read> {
+    source: R,
+    streamer: Streamer<Silent, Silent, Silent>,
+
+    input: Vec<u8>,
+    pending: VecDeque<DumpRow>,
+
+    is_finished: bool,
+}
+
+impl<R: Read> Rows<R> {
+    /// Fails with [`ConfigError`], if `config` is rejected by [`Config::validate`]
+    pub fn try_new<A, B, C>(
+        source: R,
+        start_address: u64,
+        config: &Config<A, B, C>,
+    ) -> std::result::Result<Rows<R>, ConfigError>
+    where
+        A: AddressFormatting + Clone,
+        B: ByteFormatting + Clone,
+        C: CharFormatting + Clone,
+    {
+        config.validate()?;
+
+        let silent = Silent {
+            groupping: config.byte.groupping(),
+            separators: Separators::new("", ""),
+        };
+        let bpr = silent.groupping.bytes_per_row();
+
+        // Placeholder with statistics is written after the whole run
+        let silent_config = Config::new(None, silent, None, config.dedup_enabled)
+            .with_placeholder(DedupPlaceholder::detailed())
+            .with_max_dedup_period(config.max_dedup_period);
+
+        Ok(Rows {
+            source,
+            streamer: Streamer::new(silent_config, start_address).recording(),
+            input: vec![0u8; ROWS_PER_READ * bpr],
+            pending: VecDeque::new(),
+            is_finished: false,
+        })
+    }
+
+    /// Same as [`Rows::try_new`], for configs known to be valid, like built by [`ConfigBuilder::build`](crate::ConfigBuilder::build)
+    ///
+    /// ## Panics
+    /// If `config` is rejected by [`Config::validate`]
+    #[track_caller]
+    pub fn new<A, B, C>(source: R, start_address: u64, config: &Config<A, B, C>) -> Rows<R>
+    where
+        A: AddressFormatting + Clone,
+        B: ByteFormatting + Clone,
+        C: CharFormatting + Clone,
+    {
+        match Self::try_new(source, start_address, config) {
+            Ok(rows) => rows,
+            Err(e) => panic!("Invalid config: {e}"),
+        }
+    }
+
+    /// Turns back the source. Pending rows are lost
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    fn read_next(&mut self) -> Result<()> {
+        let len = match self.source.read(&mut self.input) {
+            Ok(len) => len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let result = match len {
+            0 => {
+                self.is_finished = true;
+                self.streamer.write_tail(&mut sink())
+            }
+            _ => self.streamer.push(&self.input[..len], &mut sink()),
+        };
+
+        self.pending.extend(self.streamer.take_recorded());
+        result
+    }
+}
+
+impl<R: Read> Iterator for Rows<R> {
+    type Item = Result<DumpRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.is_finished {
+            if let Err(e) = self.read_next() {
+                self.is_finished = true;
+                return Some(Err(e));
+            }
+        }
+
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// Prints structured rows with formatters of [`Config`].
+///
+/// Rendering of all rows of [`Rows`] gives the same text, as [`Printer`] with the same `config`
+pub struct RowRenderer<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> {
+    config: Config<A, B, C>,
+    /// Address of the first byte of the dump. Base of relative address columns
+    first_address: u64,
+}
+
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> RowRenderer<A, B, C> {
+    /// `start_address` - the same, as of [`Rows`]
+    pub fn new(config: Config<A, B, C>, start_address: u64) -> Self {
+        Self {
+            config,
+            first_address: start_address,
+        }
+    }
+
+    /// Writes `row` with the line separator
+    pub fn render<O: Write>(&self, row: &DumpRow, out: &mut O) -> Result<()> {
+        match row {
+            DumpRow::Data(data) => self.render_data(data, out)?,
+            DumpRow::Duplicates(run) => self.render_run(&self.config.placeholder, run, out)?,
+            DumpRow::End(address) if self.is_end_address_omitted(*address) => {}
+            DumpRow::End(address) => self.render_address(*address, out)?,
+        }
+
+        out.write_all(b"\n")
+    }
+
+    fn render_data<O: Write>(&self, row: &DataRow, out: &mut O) -> Result<()> {
+        let byte_fmt = &self.config.byte;
+
+        self.render_address(row.address, out)?;
+        out.write_all(&byte_fmt.separators().trailing)?;
+
+        byte_fmt.format(&row.bytes, 0, out)?;
+        byte_fmt.format_padding(row.bytes.len(), out)?;
+        out.write_all(&byte_fmt.separators().leading)?;
+
+        if let Some(fmt) = &self.config.text {
+            out.write_all(&fmt.separators().trailing)?;
+            fmt.format(&row.bytes, out)?;
+            fmt.format_padding(byte_fmt.bytes_per_row() - row.bytes.len(), out)?;
+            out.write_all(&fmt.separators().leading)?;
+        }
+
+        Ok(())
+    }
+
+    fn render_run<O: Write>(&self, placeholder: &DedupPlaceholder, run: &DuplicateRun, out: &mut O) -> Result<()> {
+        let col = self.config.addr.first();
+        let run = match col {
+            Some(col) => DuplicateRun {
+                start: self.resolve(col.kind, run.start)?,
+                end: self.resolve(col.kind, run.end)?,
+                ..*run
+            },
+            None => *run,
+        };
+
+        placeholder.format(&run, col.map(|c| &c.fmt), out)
+    }
+
+    fn render_address<O: Write>(&self, address: u64, out: &mut O) -> Result<()> {
+        for col in self.config.addr.iter() {
+            out.write_all(&col.fmt.separators().trailing)?;
+            col.fmt.format(self.resolve(col.kind, address)?, out)?;
+            out.write_all(&col.fmt.separators().leading)?;
+        }
+
+        Ok(())
+    }
+
+    /// The same, as [`Printer`] ends the dump
+    fn is_end_address_omitted(&self, address: u64) -> bool {
+        let bpr = self.config.byte.bytes_per_row() as u64;
+
+        !self.config.dedup_enabled && address.wrapping_sub(self.first_address).is_multiple_of(bpr)
+    }
+
+    fn resolve(&self, kind: AddressKind, address: u64) -> Result<u64> {
+        kind.resolve(address, self.first_address, self.config.byte.bytes_per_row())
+    }
+}
+
+/// Formatter of [`Rows`], which prints nothing
+#[derive(Clone)]
+struct Silent {
+    groupping: Groupping,
+    separators: Separators,
+}
+
+impl AddressFormatting for Silent {
+    fn format<O: Write>(&self, _: u64, _: &mut O) -> Result<()> {
+        Ok(())
+    }
+
+    fn separators(&self) -> &Separators {
+        &self.separators
+    }
+}
+
+impl ByteFormatting for Silent {
+    fn byte_order(&self) -> GroupAtomicity {
+        GroupAtomicity::Optional
+    }
+
+    fn groupping(&self) -> Groupping {
+        self.groupping.clone()
+    }
+
+    fn format<O: Write>(&self, bytes: &[u8], _: usize, _: &mut O) -> Result<usize> {
+        Ok(bytes.len())
+    }
+
+    fn format_padding<O: Write>(&self, _: usize, _: &mut O) -> Result<()> {
+        Ok(())
+    }
+
+    fn separators(&self) -> &Separators {
+        &self.separators
+    }
+}
+
+impl CharFormatting for Silent {
+    fn format<O: Write>(&self, bytes: &[u8], _: &mut O) -> Result<usize> {
+        Ok(bytes.len())
+    }
+
+    fn format_padding<O: Write>(&self, _: usize, _: &mut O) -> Result<()> {
+        Ok(())
+    }
+
+    fn separators(&self) -> &Separators {
+        &self.separators
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fmt = Config<AddressFormatter, ByteFormatter, CharFormatter>;
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn structured() {
+        let data = b"Structured rows\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0end";
+        let config = Fmt::new(
+            Some(Default::default()),
+            ByteFormatter::new(
+                Default::default(),
+                Groupping::Pattern(vec![Group::new(3, " "), Group::new(5, " ")]),
+                "",
+                false,
+                Default::default(),
+            ),
+            None,
+            true,
+        );
+
+        let rows: Vec<DumpRow> = Rows::new(&data[..], 0x10, &config).map(|r| r.unwrap()).collect();
+
+        let data_row = |address, bytes: &[u8], groups: Vec<Range<usize>>| {
+            DumpRow::Data(DataRow {
+                address,
+                bytes: bytes.to_vec(),
+                is_partial: bytes.len() < 8,
+                groups,
+            })
+        };
+        let expected = [
+            data_row(0x10, b"Structur", vec![0..3, 3..8]),
+            data_row(0x18, b"ed rows\0", vec![0..3, 3..8]),
+            data_row(0x20, &[0u8; 8], vec![0..3, 3..8]),
+            DumpRow::Duplicates(DuplicateRun {
+                rows: 2,
+                bytes: 16,
+                start: 0x28,
+                end: 0x38,
+                period: 1,
+                repeats: 16,
+            }),
+            data_row(0x38, b"end", vec![0..3]),
+            DumpRow::End(0x3b),
+        ];
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn same_as_printer() {
+        let data = std::fs::read("testable/duplications").expect("Could not open testable data");
+        let configs = [
+            Fmt::default(),
+            Fmt::default().with_placeholder(DedupPlaceholder::detailed()),
+            Fmt::default()
+                .with_placeholder(DedupPlaceholder::periodic())
+                .with_max_dedup_period(4),
+            Fmt::default().with_address_column(AddressKind::Relative, Default::default()),
+            Fmt::new(Some(Default::default()), Default::default(), Some(Default::default()), false),
+        ];
+
+        // Full last row, partial last row and no rows
+        for data in [&data[..32], &data[..45], &data[..0], &data[..]] {
+            for config in configs.iter() {
+                let mut printer = Printer::new(vec![], 0x100, config.clone());
+                _ = printer.push(data);
+                let expected = printer.finish();
+
+                let renderer = RowRenderer::new(config.clone(), 0x100);
+                let mut result = vec![];
+                for row in Rows::new(data, 0x100, config) {
+                    renderer.render(&row.unwrap(), &mut result).unwrap();
+                }
+
+                assert!(result == expected, "Output differs");
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! `serde` support of [`crate::Config`] and builtin formatters.
+//!
+//! Byte strings (separators, prefixes, placeholders) are represented as strings,
+//! [`DedupPlaceholder`] as its template and [`ByteTable`] as the list of entries.
+//! Enums are externally tagged with snake case names. Deserialized [`crate::Config`] is checked
+//! by [`crate::Config::validate`], invalid one fails with the message of [`crate::ConfigError`]:
+//!
+//! ```json
+//! {
+//!     "address": [{ "kind": "absolute", "format": { "style": { "hex": 8 } } }],
+//!     "bytes": {
+//!         "style": "hex",
+//!         "groupping": { "repeating_group": { "group": { "size": 4, "separator": " " }, "count": 4 } }
+//!     },
+//!     "text": { "placeholder": "." },
+//!     "placeholder": "* {rows} rows"
+//! }
+//! ```
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::format::*;
+use crate::{Config, ConfigError};
+
+/// Byte string as UTF-8 string
+pub(crate) mod utf8 {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(bytes) {
+            Ok(s) => serializer.serialize_str(s),
+            Err(e) => Err(serde::ser::Error::custom(e)),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        String::deserialize(deserializer).map(String::into_bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GuttersRepr {
+    groupping: Groupping,
+    separator: String,
+}
+
+/// Gutters of [`CharFormatter`]
+pub(crate) mod gutters {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        gutters: &Option<(Groupping, Vec<u8>)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let repr = match gutters {
+            Some((groupping, separator)) => match String::from_utf8(separator.clone()) {
+                Ok(separator) => Some(GuttersRepr {
+                    groupping: groupping.clone(),
+                    separator,
+                }),
+                Err(e) => return Err(serde::ser::Error::custom(e)),
+            },
+            None => None,
+        };
+
+        repr.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<(Groupping, Vec<u8>)>, D::Error> {
+        let repr = Option::<GuttersRepr>::deserialize(deserializer)?;
+        Ok(repr.map(|r| (r.groupping, r.separator.into_bytes())))
+    }
+}
+
+/// Representation of [`Groupping`] with named fields
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum GrouppingRepr {
+    RowWide(usize),
+    RepeatingGroup { group: Group, count: usize },
+    Pattern(Vec<Group>),
+    Nested { levels: Vec<Group>, count: usize },
+}
+
+impl From<Groupping> for GrouppingRepr {
+    fn from(groupping: Groupping) -> Self {
+        match groupping {
+            Groupping::RowWide(r) => GrouppingRepr::RowWide(r),
+            Groupping::RepeatingGroup(group, count) => GrouppingRepr::RepeatingGroup { group, count },
+            Groupping::Pattern(groups) => GrouppingRepr::Pattern(groups),
+            Groupping::Nested(levels, count) => GrouppingRepr::Nested { levels, count },
+        }
+    }
+}
+
+impl From<GrouppingRepr> for Groupping {
+    fn from(repr: GrouppingRepr) -> Self {
+        match repr {
+            GrouppingRepr::RowWide(r) => Groupping::RowWide(r),
+            GrouppingRepr::RepeatingGroup { group, count } => Groupping::RepeatingGroup(group, count),
+            GrouppingRepr::Pattern(groups) => Groupping::Pattern(groups),
+            GrouppingRepr::Nested { levels, count } => Groupping::Nested(levels, count),
+        }
+    }
+}
+
+impl Serialize for ByteTable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq((0..BYTE_TABLE_LEN).map(|b| String::from_utf8_lossy(self.get(b as u8))))
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<String>::deserialize(deserializer)?;
+        ByteTable::new(&entries).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for DedupPlaceholder {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.template())
+    }
+}
+
+impl<'de> Deserialize<'de> for DedupPlaceholder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|t| DedupPlaceholder::new(&t))
+    }
+}
+
+/// Unchecked [`Config`], which is validated on conversion
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "A: Deserialize<'de>, B: Deserialize<'de>, C: Deserialize<'de>"))]
+pub(crate) struct ConfigRepr<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> {
+    #[serde(rename = "address", default)]
+    addr: Vec<AddressColumn<A>>,
+    #[serde(rename = "bytes")]
+    byte: B,
+    #[serde(default)]
+    text: Option<C>,
+
+    #[serde(default = "default_true")]
+    dedup_enabled: bool,
+    #[serde(default)]
+    placeholder: DedupPlaceholder,
+    #[serde(default = "DedupPlaceholder::hole")]
+    hole_placeholder: DedupPlaceholder,
+    #[serde(default = "default_max_dedup_period")]
+    max_dedup_period: usize,
+    #[serde(default)]
+    immediate_output: bool,
+}
+
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone> TryFrom<ConfigRepr<A, B, C>>
+    for Config<A, B, C>
+{
+    type Error = ConfigError;
+
+    fn try_from(repr: ConfigRepr<A, B, C>) -> Result<Self, Self::Error> {
+        let config = Config {
+            addr: repr.addr,
+            byte: repr.byte,
+            text: repr.text,
+            dedup_enabled: repr.dedup_enabled,
+            placeholder: repr.placeholder,
+            hole_placeholder: repr.hole_placeholder,
+            max_dedup_period: repr.max_dedup_period,
+            immediate_output: repr.immediate_output,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_dedup_period() -> usize {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use serde::{Deserialize, Serialize};
+
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(value: &T) -> String {
+        let json = serde_json::to_string(value).unwrap();
+        let restored: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        json
+    }
+
+    fn print(config: Config<AddressFormatter, ByteFormatter, CharFormatter>) -> Vec<u8> {
+        let mut printer = Printer::new(vec![], 0x10, config);
+        _ = printer.push(b"Serialized config\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        printer.finish()
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let entries: Vec<String> = (0..BYTE_TABLE_LEN).map(|b| format!("{b:02X}")).collect();
+        let configs = [
+            Config::default(),
+            Config::new(
+                Some(AddressFormatter::new(AddressStyle::UpperHex(6), Separators::new("[", "] ")).with_prefix("0x")),
+                ByteFormatter::new(
+                    ByteStyle::Table(ByteTable::new(&entries).unwrap()),
+                    Groupping::Pattern(vec![Group::new(4, " | "), Group::new(2, "  "), Group::new(2, "")]),
+                    ",",
+                    true,
+                    Separators::new(" ", " "),
+                )
+                .with_affixes("<", ">"),
+                None,
+                true,
+            )
+            .with_address_column(
+                AddressKind::Based(0x400000),
+                AddressFormatter::new(AddressStyle::Dec(10), Default::default()),
+            )
+            .with_placeholder(DedupPlaceholder::detailed())
+            .with_max_dedup_period(3),
+            Config::new(
+                None,
+                ByteFormatter::new(
+                    ByteStyle::RustLiteral,
+                    Groupping::Nested(vec![Group::new(2, " "), Group::new(4, "  ")], 2),
+                    "",
+                    false,
+                    Default::default(),
+                ),
+                Some(CharFormatter::new("_", Separators::new("|", "|")).with_gutters(Groupping::RowWide(8), " ")),
+                false,
+            ),
+        ];
+
+        for config in configs {
+            let json = round_trip(&config);
+            let restored: Config<AddressFormatter, ByteFormatter, CharFormatter> =
+                serde_json::from_str(&json).unwrap();
+            assert_eq!(print(restored), print(config), "{json}");
+        }
+    }
+
+    #[test]
+    fn human_friendly() {
+        let json = r#"{
+            "address": [
+                { "format": { "style": { "hex": 8 } } },
+                { "kind": "row_index", "format": { "style": { "dec": 2 }, "separators": { "trailing": "", "leading": " " } } }
+            ],
+            "bytes": {
+                "style": "hex",
+                "groupping": { "repeating_group": { "group": { "size": 4, "separator": " " }, "count": 2 } }
+            },
+            "text": { "placeholder": "." },
+            "placeholder": "* {rows} rows"
+        }"#;
+
+        let config: Config<AddressFormatter, ByteFormatter, CharFormatter> = serde_json::from_str(json).unwrap();
+        let expected = "00000010  0 53657269 616c697a |Serializ|\n\
+                        00000018  1 65642063 6f6e6669 |ed confi|\n\
+                        00000020  2 67000000 00000000 |g.......|\n\
+                        00000028  3 00000000 00000000 |........|\n\
+                        * 2 rows\n\
+                        00000040  6 00000000 0000.... |......  |\n\
+                        00000046  6 \n";
+        assert_eq!(String::from_utf8(print(config)).unwrap(), expected);
+
+        let toml = r#"
+            placeholder = "* {rows} rows"
+
+            [[address]]
+            format = { style = { hex = 8 } }
+
+            [[address]]
+            kind = "row_index"
+            format = { style = { dec = 2 }, separators = { trailing = "", leading = " " } }
+
+            [bytes]
+            style = "hex"
+            groupping = { repeating_group = { group = { size = 4, separator = " " }, count = 2 } }
+
+            [text]
+            placeholder = "."
+        "#;
+
+        let config: Config<AddressFormatter, ByteFormatter, CharFormatter> = toml::from_str(toml).unwrap();
+        assert_eq!(String::from_utf8(print(config)).unwrap(), expected);
+
+        assert_eq!(
+            serde_json::to_string(&AddressKind::Based(16)).unwrap(),
+            r#"{"based":16}"#
+        );
+        assert_eq!(serde_json::to_string(&ByteStyle::CEscape).unwrap(), r#""c_escape""#);
+        assert!(serde_json::from_str::<ByteTable>(r#"["a"]"#).is_err());
+    }
+
+    #[test]
+    fn invalid_config() {
+        type TestConfig = Config<AddressFormatter, ByteFormatter, CharFormatter>;
+
+        let cases = [
+            (r#"{"bytes":{"groupping":{"row_wide":0}}}"#, ConfigError::EmptyRow),
+            (r#"{"bytes":{},"max_dedup_period":0}"#, ConfigError::EmptyDedupPeriod),
+            (
+                r#"{"address":[{"format":{"style":{"hex":10000}}}],"bytes":{}}"#,
+                ConfigError::AddressWidth(10000),
+            ),
+            (
+                r#"{"bytes":{},"text":{"gutters":{"groupping":{"row_wide":8},"separator":" "}}}"#,
+                ConfigError::GuttersRow { gutters: 8, row: 16 },
+            ),
+        ];
+
+        for (json, expected) in cases {
+            match serde_json::from_str::<TestConfig>(json) {
+                Ok(_) => panic!("{json} must be rejected"),
+                Err(e) => assert!(e.to_string().starts_with(&expected.to_string()), "{json}: {e}"),
+            }
+        }
+
+        assert!(serde_json::from_str::<TestConfig>(r#"{"bytes":{"groupping":{"row_wide":8}}}"#).is_ok());
+    }
+}
@@ -1,64 +1,159 @@
+use super::config::*;
 use super::format::*;
+use super::periodic::*;
+use super::rows::*;
 use std::io::*;
 
 const ROW_SEPARATOR: &[u8] = b"\n";
-const DUPLICATE_PLACEHOLDER: &[u8] = b"*";
+const ROW_BUFFER_CAPACITY: usize = 256;
 
+const ADDRESS_OVERFLOW_MESSAGE: &str = "Address exceeds u64 range";
+
+/// Address, following `len` bytes from `address`. Wraparound is reported as error
+pub(crate) fn advance_address(address: u64, len: u64) -> Result<u64> {
+    match address.checked_add(len) {
+        Some(a) => Ok(a),
+        None => Err(Error::new(ErrorKind::InvalidInput, ADDRESS_OVERFLOW_MESSAGE)),
+    }
+}
+
+#[derive(Clone)]
 pub(super) struct Streamer<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> {
-    addr_fmt: Option<A>,
+    addr_cols: Vec<AddressColumn<A>>,
     byte_fmt: B,
     char_fmt: Option<C>,
+    groupping: Groupping,
 
-    total_formatted: usize,
-    printable_offset: usize,
+    total_formatted: u64,
+    printable_offset: u64,
+    /// Address of the first byte of the whole dump. Base of relative address columns
+    first_address: u64,
+    /// Address of the next pushed byte
+    next_address: u64,
 
     cache: Vec<u8>,
     available: usize,
 
     dedup_enabled: bool,
     row_state: RowState,
+
+    placeholder: DedupPlaceholder,
+    hole_placeholder: DedupPlaceholder,
+    /// Duplicated rows, collapsed since the last printed row
+    skipped_rows: u64,
+    /// Length of the shortest repeating pattern in collapsed rows
+    run_period: usize,
+
+    /// Used instead of row-by-row deduplication, when maximum period is greater than one row
+    periodic: Option<PeriodDetector>,
+
+    /// Output of the current row. Reused for every row
+    row_buf: Vec<u8>,
+    immediate_output: bool,
+
+    /// Structured rows in order of printing. Collected only for [`Rows`]
+    recorded: Option<Vec<DumpRow>>,
 }
 
-impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B, C> {
-    pub(super) fn new(
-        addr_fmt: Option<A>,
-        byte_fmt: B,
-        char_fmt: Option<C>,
-        printable_offset: usize,
-        dedup_enabled: bool,
-    ) -> Self {
-        let bpr = byte_fmt.groupping().bytes_per_row();
+impl<A: AddressFormatting + Clone, B: ByteFormatting + Clone, C: CharFormatting + Clone>
+    Streamer<A, B, C>
+{
+    pub(super) fn new(config: Config<A, B, C>, printable_offset: u64) -> Self {
+        let groupping = config.byte.groupping();
+        let bpr = groupping.bytes_per_row();
+        let periodic = match config.dedup_enabled && config.max_dedup_period > 1 {
+            true => Some(PeriodDetector::new(config.max_dedup_period, bpr)),
+            false => None,
+        };
         Self {
-            addr_fmt,
-            byte_fmt,
-            char_fmt,
+            addr_cols: config.addr,
+            byte_fmt: config.byte,
+            char_fmt: config.text,
+            groupping,
             total_formatted: 0,
             printable_offset,
+            first_address: printable_offset,
+            next_address: printable_offset,
             cache: vec![0u8; bpr],
             available: 0,
-            dedup_enabled,
+            dedup_enabled: config.dedup_enabled,
             row_state: RowState::Changed,
+            placeholder: config.placeholder,
+            hole_placeholder: config.hole_placeholder,
+            skipped_rows: 0,
+            run_period: 0,
+            periodic,
+            row_buf: Vec::with_capacity(ROW_BUFFER_CAPACITY),
+            immediate_output: config.immediate_output,
+            recorded: None,
         }
     }
 
-    pub(crate) fn push<O: std::io::Write>(&mut self, bytes: &[u8], out: &mut O) -> Result<()> {
-        if self.dedup_enabled {
+    /// Streamer, which also records structured rows. See [`Streamer::take_recorded`]
+    pub(super) fn recording(mut self) -> Self {
+        self.recorded = Some(vec![]);
+        self
+    }
+
+    /// Streamer, which continues the dump, started at `first_address`
+    pub(super) fn continuing(mut self, first_address: u64) -> Self {
+        self.first_address = first_address;
+        self
+    }
+}
+
+impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B, C> {
+    pub(crate) fn push<O: Write>(&mut self, bytes: &[u8], out: &mut O) -> Result<()> {
+        self.next_address = advance_address(self.next_address, bytes.len() as u64)?;
+        self.with_rows(out, |streamer, rows| streamer.push_rows(bytes, rows))
+    }
+
+    /// Accounts `len` zero bytes. Zeros of whole rows are replaced by single hole placeholder
+    pub(crate) fn push_hole<O: Write>(&mut self, len: u64, out: &mut O) -> Result<()> {
+        self.next_address = advance_address(self.next_address, len)?;
+        self.with_rows(out, |streamer, rows| streamer.push_hole_rows(len, rows))
+    }
+
+    pub(crate) fn write_tail<O: Write>(&mut self, out: &mut O) -> Result<()> {
+        self.with_rows(out, |streamer, rows| streamer.write_tail_rows(rows))
+    }
+
+    /// Collects output in the row buffer, which is written as soon as row is finished
+    fn with_rows<O: Write>(
+        &mut self,
+        out: &mut O,
+        f: impl FnOnce(&mut Self, &mut RowWriter<O>) -> Result<()>,
+    ) -> Result<()> {
+        let mut buf = std::mem::take(&mut self.row_buf);
+        let mut rows = RowWriter { buf: &mut buf, out };
+
+        let mut result = f(self, &mut rows);
+        if result.is_ok() && self.immediate_output {
+            result = rows.write_incomplete();
+        }
+
+        self.row_buf = buf;
+        result
+    }
+
+    fn push_rows<O: Write>(&mut self, bytes: &[u8], out: &mut RowWriter<O>) -> Result<()> {
+        if self.periodic.is_some() {
+            self.push_periodic(bytes, out)
+        } else if self.dedup_enabled {
             self.push_deduplicated(bytes, out)
         } else {
             self.push_groupped(bytes, out)
         }
     }
 
-    fn push_groupped<O: std::io::Write>(&mut self, bytes: &[u8], out: &mut O) -> Result<()> {
+    fn push_groupped<O: Write>(&mut self, bytes: &[u8], out: &mut RowWriter<O>) -> Result<()> {
         use std::cmp::min;
-        let mut tmp = &bytes[..];
+        let mut tmp = bytes;
 
-        let gr = &self.byte_fmt.groupping();
-        let bpr = gr.bytes_per_row();
-        let group_size = gr.max_group_size();
+        let bpr = self.groupping.bytes_per_row();
 
-        while tmp.len() != 0 {
-            let byte_in_row = self.total_formatted % bpr;
+        while !tmp.is_empty() {
+            let byte_in_row = self.formatted_in_row();
 
             if self.available == 0 {
                 self.start_row(out)?;
@@ -75,11 +170,10 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
             assert!(self.available <= bpr, "Too much bytes written");
 
             let group_cache = self.calculated_group_cache(old_available, self.available);
-            assert_eq!(group_cache.len() % group_size, 0, "Unaligned group cache");
 
             // Start reading from cache
-            if group_cache.len() != 0 {
-                self.total_formatted += self.byte_fmt.format(group_cache, byte_in_row, out)?;
+            if !group_cache.is_empty() {
+                self.total_formatted += self.byte_fmt.format(group_cache, byte_in_row, out)? as u64;
             }
 
             // Finish row
@@ -91,15 +185,14 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
         Ok(())
     }
 
-    fn push_deduplicated<O: std::io::Write>(&mut self, bytes: &[u8], out: &mut O) -> Result<()> {
+    fn push_deduplicated<O: Write>(&mut self, bytes: &[u8], out: &mut RowWriter<O>) -> Result<()> {
         use std::cmp::min;
-        let mut tmp = &bytes[..];
+        let mut tmp = bytes;
 
-        let gr = &self.byte_fmt.groupping();
-        let bpr = gr.bytes_per_row();
+        let bpr = self.groupping.bytes_per_row();
 
-        while tmp.len() != 0 {
-            let ignore_dedup = self.total_formatted < bpr;
+        while !tmp.is_empty() {
+            let ignore_dedup = self.total_formatted < bpr as u64;
 
             let to_check = min(self.cache.len() - self.available, tmp.len());
 
@@ -110,9 +203,9 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
                 self.row_state = RowState::Changed;
             }
 
-            let mut cache_part = &mut self.cache[self.available..self.available + to_check];
+            let cache_part = &mut self.cache[self.available..self.available + to_check];
             if should_write {
-                tmp.read_exact(&mut cache_part)?;
+                tmp.read_exact(cache_part)?;
             } else {
                 tmp = &tmp[to_check..];
             }
@@ -123,13 +216,15 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
             if self.cache.len() - self.available == 0 {
                 match self.row_state {
                     RowState::Changed => {
+                        self.write_deferred_placeholder(out)?;
                         self.start_row(out)?;
-                        self.total_formatted += self.byte_fmt.format(&self.cache, 0, out)?;
+                        self.total_formatted += self.byte_fmt.format(&self.cache, 0, out)? as u64;
                         self.row_state = RowState::Changed;
                     },
                     RowState::NeedsPlaceholder | RowState::Skipped => {
                         // We suppose what duplicate bytes formatted too
-                        self.total_formatted += self.cache.len();
+                        self.total_formatted += self.cache.len() as u64;
+                        self.skipped_rows += 1;
                     },
                 }
 
@@ -140,31 +235,216 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
         Ok(())
     }
 
-    pub(crate) fn write_tail<O: Write>(&mut self, out: &mut O) -> Result<()> {
+    fn push_periodic<O: Write>(&mut self, bytes: &[u8], out: &mut RowWriter<O>) -> Result<()> {
+        use std::cmp::min;
+        let mut tmp = bytes;
+
+        while !tmp.is_empty() {
+            let to_cache = min(self.cache.len() - self.available, tmp.len());
+            tmp.read_exact(&mut self.cache[self.available..self.available + to_cache])?;
+            self.available += to_cache;
+
+            if self.available == self.cache.len() {
+                self.available = 0;
+
+                let mut detector = self.periodic.take().expect(DETECTOR_LOST_MESSAGE);
+                detector.store_row(&self.cache);
+
+                let result = detector.settle(&mut PeriodicSink { streamer: self, out });
+
+                self.periodic = Some(detector);
+
+                result?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rows, recorded since the last call
+    pub(crate) fn take_recorded(&mut self) -> Vec<DumpRow> {
+        match &mut self.recorded {
+            Some(rows) => std::mem::take(rows),
+            None => vec![],
+        }
+    }
+
+    fn record(&mut self, row: impl FnOnce(&Self) -> DumpRow) {
+        if let Some(mut rows) = self.recorded.take() {
+            rows.push(row(self));
+            self.recorded = Some(rows);
+        }
+    }
+
+    fn data_row(&self) -> DumpRow {
+        let bytes = &self.cache[..self.available];
+
+        let mut groups = vec![];
+        let mut start = 0;
+        while start < bytes.len() {
+            let end = std::cmp::min(start + self.groupping.group_size_at(start), bytes.len());
+            groups.push(start..end);
+            start = end;
+        }
+
+        DumpRow::Data(DataRow {
+            address: self.address() - self.available as u64,
+            bytes: bytes.to_vec(),
+            groups,
+            is_partial: self.available < self.cache.len(),
+        })
+    }
+
+    /// Address of the next formatted byte
+    pub(crate) fn address(&self) -> u64 {
+        self.total_formatted + self.printable_offset
+    }
+
+    fn formatted_in_row(&self) -> usize {
+        (self.total_formatted % self.cache.len() as u64) as usize
+    }
+
+    /// Streamers in the same state produce the same output from the same input
+    pub(crate) fn same_state(&self, other: &Self) -> bool {
+        let bpr = self.cache.len();
+        let is_classic_dedup = self.dedup_enabled && self.periodic.is_none();
+        let is_dynamic = !self.placeholder.is_static();
+
+        let periodic_matches = match (&self.periodic, &other.periodic) {
+            (Some(a), Some(b)) => a.same_state(b),
+            (None, None) => true,
+            _ => false,
+        };
+
+        self.address() == other.address()
+            && self.available == other.available
+            && self.cache[..self.available] == other.cache[..other.available]
+            && (!is_classic_dedup
+                || (self.cache == other.cache
+                    && self.row_state == other.row_state
+                    && (self.total_formatted < bpr as u64) == (other.total_formatted < bpr as u64)))
+            && (!is_dynamic
+                || (self.skipped_rows == other.skipped_rows
+                    && (self.skipped_rows == 0 || self.run_period == other.run_period)))
+            && periodic_matches
+    }
+
+    fn push_hole_rows<O: Write>(&mut self, len: u64, out: &mut RowWriter<O>) -> Result<()> {
+        let bpr = self.cache.len();
+
+        // Less than a row
+        let head = ((bpr - self.available) % bpr) as u64;
+        let head = head.min(len) as usize;
+        self.push_zeros(head, out)?;
+
+        let rows = (len - head as u64) / bpr as u64;
+        if rows != 0 {
+            self.write_hole(rows, out)?;
+        }
+
+        self.push_zeros((len - head as u64 - rows * bpr as u64) as usize, out)
+    }
+
+    fn push_zeros<O: Write>(&mut self, mut len: usize, out: &mut RowWriter<O>) -> Result<()> {
+        use std::cmp::min;
+
+        const ZEROS: [u8; 64] = [0u8; 64];
+
+        while len != 0 {
+            let to_push = min(len, ZEROS.len());
+            self.push_rows(&ZEROS[..to_push], out)?;
+            len -= to_push;
+        }
+
+        Ok(())
+    }
+
+    fn write_hole<O: Write>(&mut self, rows: u64, out: &mut RowWriter<O>) -> Result<()> {
+        let bpr = self.cache.len();
+
+        if let Some(mut detector) = self.periodic.take() {
+            let result = detector.flush(&mut PeriodicSink { streamer: self, out });
+            detector.assume_written(&vec![0u8; bpr]);
+            self.periodic = Some(detector);
+
+            result?;
+        }
+
         if self.dedup_enabled {
+            self.write_deferred_placeholder(out)?;
+        }
+
+        let bytes = rows * bpr as u64;
+        let start = self.total_formatted + self.printable_offset;
+        let run = DuplicateRun {
+            rows,
+            bytes,
+            start,
+            end: start + bytes,
+            period: 1,
+            repeats: bytes,
+        };
+
+        let run = self.resolve_run(run)?;
+        self.hole_placeholder.format(&run, self.placeholder_addr_fmt(), out)?;
+        out.end_row()?;
+
+        self.total_formatted += bytes;
+
+        // Following zero rows are duplicates of the hole
+        if self.dedup_enabled {
+            self.cache.fill(0);
+            self.row_state = RowState::NeedsPlaceholder;
+        }
+
+        Ok(())
+    }
+
+    fn write_tail_rows<O: Write>(&mut self, out: &mut RowWriter<O>) -> Result<()> {
+        if let Some(mut detector) = self.periodic.take() {
+            let available = self.available;
+            let row = self.cache[..available].to_vec();
+
+            let result = detector.flush(&mut PeriodicSink { streamer: self, out });
+
+            self.cache[..available].copy_from_slice(&row);
+            self.available = available;
+            self.periodic = Some(detector);
+
+            result?;
+        }
+
+        if self.dedup_enabled {
+            self.write_deferred_placeholder(out)?;
             self.start_row(out)?;
+
+            // Incomplete row is never replaced by placeholder
+            if self.available != 0 {
+                self.row_state = RowState::Changed;
+            }
         }
 
         if self.available == 0 {
-            out.write_all(ROW_SEPARATOR)?;
+            self.record(|s| DumpRow::End(s.address()));
+            out.end_row()?;
             return Ok(());
         }
 
-        let bpr = self.byte_fmt.groupping().bytes_per_row();
-        let formatted_in_row = self.total_formatted % bpr;
+        let formatted_in_row = self.formatted_in_row();
         assert!(
             self.available >= formatted_in_row,
             "Bytes written more than available"
         );
 
         let remaining = &self.cache[formatted_in_row..self.available];
-        self.total_formatted += self.byte_fmt.format(remaining, formatted_in_row, out)?;
+        self.total_formatted += self.byte_fmt.format(remaining, formatted_in_row, out)? as u64;
         
         self.finish_row(out)?;
         
+        self.record(|s| DumpRow::End(s.address()));
         self.write_current_offset(out)?;
         
-        out.write_all(ROW_SEPARATOR)?;
+        out.end_row()?;
 
         Ok(())
     }
@@ -176,46 +456,68 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
             "Unbalanced groupping. Start number greater than end number"
         );
 
-        let gr = &self.byte_fmt.groupping();
-        let group_size = gr.max_group_size();
+        let gr = &self.groupping;
 
-        let gr = self.byte_fmt.groupping();
-
-        let start = gr.group_of_byte(at_number) * group_size;
-        let end = gr.group_of_byte(available) * group_size;
+        let start = gr.group_start(at_number);
+        let end = gr.group_start(available);
 
         &self.cache[start..end]
     }
 
-    fn start_row<O: Write>(&self, out: &mut O) -> Result<()> {
+    fn start_row<O: Write>(&self, out: &mut RowWriter<O>) -> Result<()> {
         self.write_current_offset(out)?;
         out.write_all(&self.byte_fmt.separators().trailing)?;
 
         Ok(())
     }
 
-    fn write_current_offset<O: Write>(&self, out: &mut O) -> Result<()> {
-        if let Some(fmt) = &self.addr_fmt {
-            out.write_all(&fmt.separators().trailing)?;
-            fmt.format(self.total_formatted + self.printable_offset, out)?;
-            out.write_all(&fmt.separators().leading)?;
+    fn write_current_offset<O: Write>(&self, out: &mut RowWriter<O>) -> Result<()> {
+        let address = self.total_formatted + self.printable_offset;
+
+        for col in self.addr_cols.iter() {
+            let value = col.kind.resolve(address, self.first_address, self.cache.len())?;
+
+            out.write_all(&col.fmt.separators().trailing)?;
+            col.fmt.format(value, out)?;
+            out.write_all(&col.fmt.separators().leading)?;
         }
 
         Ok(())
     }
 
-    fn finish_row<O: Write>(&mut self, out: &mut O) -> Result<()> {
+    /// Placeholder addresses are shown by the first column
+    fn placeholder_addr_fmt(&self) -> Option<&A> {
+        self.addr_cols.first().map(|col| &col.fmt)
+    }
+
+    fn resolve_run(&self, run: DuplicateRun) -> Result<DuplicateRun> {
+        let kind = match self.addr_cols.first() {
+            Some(col) => col.kind,
+            None => return Ok(run),
+        };
+
+        let bpr = self.cache.len();
+        Ok(DuplicateRun {
+            start: kind.resolve(run.start, self.first_address, bpr)?,
+            end: kind.resolve(run.end, self.first_address, bpr)?,
+            ..run
+        })
+    }
+
+    fn finish_row<O: Write>(&mut self, out: &mut RowWriter<O>) -> Result<()> {
         if self.row_state.is_changed() || !self.dedup_enabled {
+            self.record(Self::data_row);
             self.byte_fmt.format_padding(self.available, out)?;
     
             out.write_all(&self.byte_fmt.separators().leading)?;
     
             self.write_text(out)?;
     
-            out.write_all(ROW_SEPARATOR)?;
+            out.end_row()?;
 
             self.row_state = RowState::NeedsPlaceholder;
         } else if let RowState::NeedsPlaceholder = self.row_state {
+            self.run_period = minimal_period(&self.cache);
             self.replace_row_with_placeholder(out)?;
         }
 
@@ -223,25 +525,58 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
         Ok(())
     }
 
-    fn replace_row_with_placeholder<O: Write>(&mut self, out: &mut O) -> Result<()> {
+    fn replace_row_with_placeholder<O: Write>(&mut self, out: &mut RowWriter<O>) -> Result<()> {
         match self.row_state {
             RowState::NeedsPlaceholder => {
                 self.row_state = RowState::Skipped;
 
-                out.write_all(DUPLICATE_PLACEHOLDER)?;
-                out.write_all(ROW_SEPARATOR)
+                if self.placeholder.is_static() {
+                    let run = self.resolve_run(self.duplicate_run())?;
+                    self.placeholder.format(&run, self.placeholder_addr_fmt(), out)?;
+                    out.end_row()?;
+                }
+
+                Ok(())
             }
             _ => panic!("replace_row_with_placeholder(): Row does not need a placeholder"),
         }
     }
 
-    fn write_text<O: Write>(&self, out: &mut O) -> Result<()> {
+    /// Prints placeholder, which was postponed until the end of duplicated rows
+    fn write_deferred_placeholder<O: Write>(&mut self, out: &mut RowWriter<O>) -> Result<()> {
+        if self.skipped_rows != 0 && !self.placeholder.is_static() {
+            self.record(|s| DumpRow::Duplicates(s.duplicate_run()));
+
+            let run = self.resolve_run(self.duplicate_run())?;
+            self.placeholder.format(&run, self.placeholder_addr_fmt(), out)?;
+            out.end_row()?;
+        }
+
+        self.skipped_rows = 0;
+        Ok(())
+    }
+
+    fn duplicate_run(&self) -> DuplicateRun {
+        let bytes = self.skipped_rows * self.groupping.bytes_per_row() as u64;
+        let end = self.total_formatted + self.printable_offset;
+
+        DuplicateRun {
+            rows: self.skipped_rows,
+            bytes,
+            start: end - bytes,
+            end,
+            period: self.run_period,
+            repeats: bytes / self.run_period as u64,
+        }
+    }
+
+    fn write_text<O: Write>(&self, out: &mut RowWriter<O>) -> Result<()> {
         if let Some(fmt) = &self.char_fmt {
             out.write_all(&fmt.separators().trailing)?;
 
             fmt.format(&self.cache[..self.available], out)?;
 
-            let tail_len = self.byte_fmt.groupping().bytes_per_row() - self.available;
+            let tail_len = self.groupping.bytes_per_row() - self.available;
             fmt.format_padding(tail_len, out)?;
 
             out.write_all(&fmt.separators().leading)?;
@@ -251,6 +586,49 @@ impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting> Streamer<A, B,
     }
 }
 
+const DETECTOR_LOST_MESSAGE: &str = "Somewhere we lost the period detector";
+
+struct PeriodicSink<'a, 'b, A: AddressFormatting, B: ByteFormatting, C: CharFormatting, O: Write> {
+    streamer: &'a mut Streamer<A, B, C>,
+    out: &'a mut RowWriter<'b, O>,
+}
+
+impl<A: AddressFormatting, B: ByteFormatting, C: CharFormatting, O: Write> RowSink
+    for PeriodicSink<'_, '_, A, B, C, O>
+{
+    fn write_row(&mut self, row: &[u8]) -> Result<()> {
+        let streamer = &mut *self.streamer;
+
+        streamer.cache.copy_from_slice(row);
+        streamer.available = row.len();
+        streamer.row_state = RowState::Changed;
+
+        streamer.start_row(self.out)?;
+        streamer.total_formatted += streamer.byte_fmt.format(row, 0, self.out)? as u64;
+        streamer.finish_row(self.out)
+    }
+
+    fn collapse(&mut self, period_rows: usize, pattern: Option<&[u8]>) -> Result<()> {
+        let streamer = &mut *self.streamer;
+
+        streamer.skipped_rows += period_rows as u64;
+        streamer.total_formatted += (period_rows * streamer.cache.len()) as u64;
+
+        if let Some(pattern) = pattern {
+            streamer.run_period = minimal_period(pattern);
+            streamer.row_state = RowState::NeedsPlaceholder;
+            streamer.replace_row_with_placeholder(self.out)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish_run(&mut self) -> Result<()> {
+        self.streamer.write_deferred_placeholder(self.out)
+    }
+}
+
+#[derive(Clone, PartialEq)]
 enum RowState {
     Changed,
     NeedsPlaceholder,
@@ -259,9 +637,47 @@ enum RowState {
 
 impl RowState {
     fn is_changed(&self) -> bool {
-        match self {
-            RowState::Changed => true,
-            _ => false
+        matches!(self, RowState::Changed)
+    }
+}
+
+/// Collects output of the current row
+struct RowWriter<'a, O: Write> {
+    buf: &'a mut Vec<u8>,
+    out: &'a mut O,
+}
+
+impl<O: Write> RowWriter<'_, O> {
+    /// Writes the whole row at once
+    fn end_row(&mut self) -> Result<()> {
+        self.buf.extend_from_slice(ROW_SEPARATOR);
+        self.write_incomplete()
+    }
+
+    fn write_incomplete(&mut self) -> Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
         }
+
+        let result = self.out.write_all(self.buf);
+        self.buf.clear();
+
+        result
     }
-}
\ No newline at end of file
+}
+
+impl<O: Write> Write for RowWriter<'_, O> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.buf.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}